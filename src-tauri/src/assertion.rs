@@ -0,0 +1,237 @@
+use jsonschema::JSONSchema;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::extraction::{find_json_path, value_to_string};
+
+// 断言的对象
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum AssertionTarget {
+    // 响应状态码
+    Status,
+    // 响应头，property为响应头名称
+    Header,
+    // 响应数据的JSONPath，property为JSONPath
+    JsonPath,
+    // 响应数据
+    Body,
+    // 以JSON Schema校验响应数据，expected为schema
+    JsonSchema,
+    // 请求耗时(ms)，即HTTPStats.total
+    Latency,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum AssertionOperator {
+    Equal,
+    NotEqual,
+    GreaterThan,
+    GreaterOrEqual,
+    LessThan,
+    LessOrEqual,
+    // 在范围内（包括边界），expected为min,max
+    Between,
+    Contains,
+    NotContains,
+    // 正则匹配
+    Matches,
+    Exists,
+    NotExists,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+// 请求完成后对响应的断言，保存在API配置的assertions字段
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionRule {
+    pub target: AssertionTarget,
+    // 响应头名称或JSONPath
+    #[serde(default)]
+    pub property: String,
+    pub operator: AssertionOperator,
+    #[serde(default)]
+    pub expected: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionResult {
+    pub target: AssertionTarget,
+    pub property: String,
+    pub operator: AssertionOperator,
+    pub expected: String,
+    // 实际的值，不存在时为null
+    pub actual: Option<String>,
+    pub passed: bool,
+    // 未通过的原因
+    pub message: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct AssertionSetting {
+    #[serde(default)]
+    assertions: Vec<AssertionRule>,
+}
+
+// 从API配置中获取启用的断言，配置无法解析时忽略
+pub fn get_assertion_rules(setting: &str) -> Vec<AssertionRule> {
+    serde_json::from_str::<AssertionSetting>(setting)
+        .unwrap_or_default()
+        .assertions
+        .into_iter()
+        .filter(|item| item.enabled)
+        .collect()
+}
+
+// 用于断言的响应数据，headers的key为小写
+pub struct AssertionResponse<'a> {
+    pub status: u16,
+    pub headers: &'a HashMap<String, Vec<String>>,
+    pub body: &'a [u8],
+    pub latency: u32,
+}
+
+fn to_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(value) => value.as_f64(),
+        Value::String(value) => value.trim().parse::<f64>().ok(),
+        _ => None,
+    }
+}
+
+fn parse_number(value: &str) -> Result<f64, String> {
+    value
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| format!("{} is not a number", value))
+}
+
+// expected为json时按json对比（如1与1.0、true），否则按字符串对比
+fn is_equal(actual: &Value, expected: &str) -> bool {
+    if let Ok(expected) = serde_json::from_str::<Value>(expected) {
+        if let (Some(a), Some(b)) = (actual.as_f64(), expected.as_f64()) {
+            return a == b;
+        }
+        if actual == &expected {
+            return true;
+        }
+    }
+    value_to_string(actual) == expected
+}
+
+fn compare(
+    operator: AssertionOperator,
+    actual: Option<&Value>,
+    expected: &str,
+) -> Result<bool, String> {
+    let actual = match (operator, actual) {
+        (AssertionOperator::Exists, actual) => return Ok(actual.is_some()),
+        (AssertionOperator::NotExists, actual) => return Ok(actual.is_none()),
+        (_, Some(actual)) => actual,
+        (_, None) => return Err("value is not found".to_string()),
+    };
+    let number = || to_number(actual).ok_or_else(|| "value is not a number".to_string());
+    let result = match operator {
+        AssertionOperator::Equal => is_equal(actual, expected),
+        AssertionOperator::NotEqual => !is_equal(actual, expected),
+        AssertionOperator::GreaterThan => number()? > parse_number(expected)?,
+        AssertionOperator::GreaterOrEqual => number()? >= parse_number(expected)?,
+        AssertionOperator::LessThan => number()? < parse_number(expected)?,
+        AssertionOperator::LessOrEqual => number()? <= parse_number(expected)?,
+        AssertionOperator::Between => {
+            let (min, max) = expected
+                .split_once(',')
+                .ok_or_else(|| format!("{} should be min,max", expected))?;
+            let value = number()?;
+            value >= parse_number(min)? && value <= parse_number(max)?
+        }
+        AssertionOperator::Contains => value_to_string(actual).contains(expected),
+        AssertionOperator::NotContains => !value_to_string(actual).contains(expected),
+        AssertionOperator::Matches => Regex::new(expected)
+            .map_err(|err| err.to_string())?
+            .is_match(&value_to_string(actual)),
+        AssertionOperator::Exists | AssertionOperator::NotExists => true,
+    };
+    Ok(result)
+}
+
+fn validate_json_schema(body: &[u8], schema: &str) -> Result<(), String> {
+    let schema: Value = serde_json::from_str(schema).map_err(|err| err.to_string())?;
+    let schema = JSONSchema::compile(&schema).map_err(|err| err.to_string())?;
+    let data: Value = serde_json::from_slice(body).map_err(|err| err.to_string())?;
+    let result = schema.validate(&data);
+    if let Err(errors) = result {
+        let messages: Vec<String> = errors
+            .map(|err| format!("{}: {}", err.instance_path, err))
+            .collect();
+        return Err(messages.join("; "));
+    }
+    Ok(())
+}
+
+fn evaluate(
+    rule: &AssertionRule,
+    resp: &AssertionResponse,
+) -> Result<(Option<Value>, bool), String> {
+    let actual = match rule.target {
+        AssertionTarget::Status => Some(Value::from(resp.status)),
+        AssertionTarget::Latency => Some(Value::from(resp.latency)),
+        AssertionTarget::Header => resp
+            .headers
+            .get(&rule.property.to_lowercase())
+            .map(|values| Value::String(values.join(", "))),
+        AssertionTarget::JsonPath => find_json_path(resp.body, &rule.property)?,
+        AssertionTarget::Body => Some(Value::String(
+            String::from_utf8_lossy(resp.body).to_string(),
+        )),
+        AssertionTarget::JsonSchema => {
+            validate_json_schema(resp.body, &rule.expected)?;
+            return Ok((None, true));
+        }
+    };
+    let passed = compare(rule.operator, actual.as_ref(), &rule.expected)?;
+    Ok((actual, passed))
+}
+
+pub fn evaluate_assertions(
+    rules: &[AssertionRule],
+    resp: &AssertionResponse,
+) -> Vec<AssertionResult> {
+    rules
+        .iter()
+        .map(|rule| {
+            let mut item = AssertionResult {
+                target: rule.target,
+                property: rule.property.clone(),
+                operator: rule.operator,
+                expected: rule.expected.clone(),
+                actual: None,
+                passed: false,
+                message: "".to_string(),
+            };
+            match evaluate(rule, resp) {
+                Ok((actual, passed)) => {
+                    // 响应数据可能较大，不返回
+                    if rule.target != AssertionTarget::Body {
+                        item.actual = actual.as_ref().map(value_to_string);
+                    }
+                    item.passed = passed;
+                    if !passed {
+                        item.message = "assertion failed".to_string();
+                    }
+                }
+                Err(message) => item.message = message,
+            }
+            item
+        })
+        .collect()
+}
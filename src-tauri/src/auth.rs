@@ -0,0 +1,572 @@
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use hyper::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::HashMap;
+use url::Url;
+
+use crate::error::CyberAPIError;
+use crate::oauth::OAuthConfig;
+use crate::template::{TemplateError, TemplateRenderer};
+
+fn default_hmac_header() -> String {
+    "X-Signature".to_string()
+}
+
+fn default_timestamp_header() -> String {
+    "X-Timestamp".to_string()
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum HmacAlgorithm {
+    #[default]
+    Sha256,
+    Sha512,
+}
+
+// 签名的编码方式
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SignatureEncoding {
+    #[default]
+    Hex,
+    Base64,
+}
+
+// 请求的认证方式，在请求的url与body确定后生成相应的请求头
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum HTTPAuth {
+    #[serde(rename_all = "camelCase")]
+    Basic { username: String, password: String },
+    #[serde(rename_all = "camelCase")]
+    Bearer { token: String },
+    // 首次请求返回401后根据WWW-Authenticate重新发送
+    #[serde(rename_all = "camelCase")]
+    Digest { username: String, password: String },
+    #[serde(rename_all = "camelCase")]
+    AwsSigV4 {
+        access_key: String,
+        secret_key: String,
+        #[serde(default)]
+        session_token: String,
+        region: String,
+        service: String,
+    },
+    // 签名的内容为：METHOD\npath?query\ntimestamp\nhex(sha256(body))
+    // 未设置时间戳的请求头时，签名的内容为：METHOD\npath?query\nhex(sha256(body))
+    // key_id不为空时，签名头的值为key_id:signature
+    #[serde(rename_all = "camelCase")]
+    Hmac {
+        secret: String,
+        #[serde(default)]
+        key_id: String,
+        #[serde(default)]
+        algorithm: HmacAlgorithm,
+        #[serde(default)]
+        encoding: SignatureEncoding,
+        #[serde(default = "default_hmac_header")]
+        header: String,
+        // 为空则不添加时间戳，签名中也不包含时间戳
+        #[serde(default = "default_timestamp_header")]
+        timestamp_header: String,
+    },
+    // 使用获取的access token作为Bearer认证
+    #[serde(rename = "oauth2")]
+    OAuth2(OAuthConfig),
+}
+
+impl HTTPAuth {
+    // 替换认证配置中的变量，如{{token}}
+    pub fn render(self, renderer: &mut TemplateRenderer) -> Result<Self, TemplateError> {
+        let mut render =
+            |field: &str, value: String| renderer.render(&format!("auth.{}", field), &value);
+        let auth = match self {
+            HTTPAuth::Basic { username, password } => HTTPAuth::Basic {
+                username: render("username", username)?,
+                password: render("password", password)?,
+            },
+            HTTPAuth::Bearer { token } => HTTPAuth::Bearer {
+                token: render("token", token)?,
+            },
+            HTTPAuth::Digest { username, password } => HTTPAuth::Digest {
+                username: render("username", username)?,
+                password: render("password", password)?,
+            },
+            HTTPAuth::AwsSigV4 {
+                access_key,
+                secret_key,
+                session_token,
+                region,
+                service,
+            } => HTTPAuth::AwsSigV4 {
+                access_key: render("accessKey", access_key)?,
+                secret_key: render("secretKey", secret_key)?,
+                session_token: render("sessionToken", session_token)?,
+                region: render("region", region)?,
+                service: render("service", service)?,
+            },
+            HTTPAuth::Hmac {
+                secret,
+                key_id,
+                algorithm,
+                encoding,
+                header,
+                timestamp_header,
+            } => HTTPAuth::Hmac {
+                secret: render("secret", secret)?,
+                key_id: render("keyId", key_id)?,
+                algorithm,
+                encoding,
+                header,
+                timestamp_header,
+            },
+            HTTPAuth::OAuth2(config) => HTTPAuth::OAuth2(config.render(renderer)?),
+        };
+        Ok(auth)
+    }
+}
+
+// 认证过程中获取的信息，用于生成认证的请求头
+#[derive(Debug, Clone, Default)]
+pub struct AuthState {
+    // 服务端返回的Digest认证信息（首次请求时为None）
+    pub digest: Option<DigestChallenge>,
+    // OAuth获取的access token
+    pub access_token: Option<String>,
+}
+
+// 服务端返回的Digest认证信息
+#[derive(Debug, Clone, Default)]
+pub struct DigestChallenge {
+    pub realm: String,
+    pub nonce: String,
+    pub opaque: String,
+    pub algorithm: String,
+    // 服务端支持的qop，如auth、auth-int
+    pub qop: Vec<String>,
+}
+
+// 解析WWW-Authenticate中的Digest认证信息，多个认证方式时取Digest
+pub fn parse_digest_challenge(values: &[String]) -> Option<DigestChallenge> {
+    let value = values.iter().find_map(|value| {
+        let value = value.trim();
+        if value.len() > 7 && value[..7].eq_ignore_ascii_case("digest ") {
+            Some(&value[7..])
+        } else {
+            None
+        }
+    })?;
+    let params = parse_auth_params(value);
+    let nonce = params.get("nonce")?.clone();
+    Some(DigestChallenge {
+        realm: params.get("realm").cloned().unwrap_or_default(),
+        nonce,
+        opaque: params.get("opaque").cloned().unwrap_or_default(),
+        algorithm: params.get("algorithm").cloned().unwrap_or_default(),
+        qop: params
+            .get("qop")
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(|item| item.trim().to_string())
+                    .collect()
+            })
+            .unwrap_or_default(),
+    })
+}
+
+// 解析key=value或key="value"形式的参数，key转换为小写
+fn parse_auth_params(value: &str) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    let mut rest = value.trim();
+    while !rest.is_empty() {
+        let eq = match rest.find('=') {
+            Some(index) => index,
+            None => break,
+        };
+        let key = rest[..eq]
+            .trim()
+            .trim_start_matches(',')
+            .trim()
+            .to_lowercase();
+        rest = rest[eq + 1..].trim_start();
+        let value;
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let mut result = String::new();
+            let mut escaped = false;
+            let mut end = quoted.len();
+            for (index, c) in quoted.char_indices() {
+                if escaped {
+                    result.push(c);
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == '"' {
+                    end = index + 1;
+                    break;
+                } else {
+                    result.push(c);
+                }
+            }
+            value = result;
+            rest = &quoted[end.min(quoted.len())..];
+        } else {
+            let end = rest.find(',').unwrap_or(rest.len());
+            value = rest[..end].trim().to_string();
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start().trim_start_matches(',').trim_start();
+        params.insert(key, value);
+    }
+    params
+}
+
+fn digest_hash(algorithm: &str, data: &str) -> String {
+    if algorithm.to_uppercase().starts_with("SHA-256") {
+        hex::encode(Sha256::digest(data.as_bytes()))
+    } else {
+        hex::encode(Md5::digest(data.as_bytes()))
+    }
+}
+
+// 生成Digest认证的Authorization(RFC 7616)，uri为请求的path与query
+#[allow(clippy::too_many_arguments)]
+pub fn digest_authorization(
+    username: &str,
+    password: &str,
+    challenge: &DigestChallenge,
+    method: &str,
+    uri: &str,
+    body: &[u8],
+    cnonce: &str,
+    nc: u32,
+) -> String {
+    let algorithm = if challenge.algorithm.is_empty() {
+        "MD5".to_string()
+    } else {
+        challenge.algorithm.clone()
+    };
+    let nc = format!("{:08x}", nc);
+    let mut ha1 = digest_hash(
+        &algorithm,
+        &format!("{}:{}:{}", username, challenge.realm, password),
+    );
+    if algorithm.to_lowercase().ends_with("-sess") {
+        ha1 = digest_hash(
+            &algorithm,
+            &format!("{}:{}:{}", ha1, challenge.nonce, cnonce),
+        );
+    }
+    // 优先使用auth
+    let qop = if challenge.qop.iter().any(|item| item == "auth") {
+        "auth"
+    } else if challenge.qop.iter().any(|item| item == "auth-int") {
+        "auth-int"
+    } else {
+        ""
+    };
+    let ha2 = if qop == "auth-int" {
+        let body_hash = if algorithm.to_uppercase().starts_with("SHA-256") {
+            hex::encode(Sha256::digest(body))
+        } else {
+            hex::encode(Md5::digest(body))
+        };
+        digest_hash(&algorithm, &format!("{}:{}:{}", method, uri, body_hash))
+    } else {
+        digest_hash(&algorithm, &format!("{}:{}", method, uri))
+    };
+    let response = if qop.is_empty() {
+        digest_hash(&algorithm, &format!("{}:{}:{}", ha1, challenge.nonce, ha2))
+    } else {
+        digest_hash(
+            &algorithm,
+            &format!(
+                "{}:{}:{}:{}:{}:{}",
+                ha1, challenge.nonce, nc, cnonce, qop, ha2
+            ),
+        )
+    };
+
+    let mut arr = vec![
+        format!(r#"username="{}""#, username),
+        format!(r#"realm="{}""#, challenge.realm),
+        format!(r#"nonce="{}""#, challenge.nonce),
+        format!(r#"uri="{}""#, uri),
+        format!("algorithm={}", algorithm),
+        format!(r#"response="{}""#, response),
+    ];
+    if !qop.is_empty() {
+        arr.push(format!("qop={}", qop));
+        arr.push(format!("nc={}", nc));
+        arr.push(format!(r#"cnonce="{}""#, cnonce));
+    }
+    if !challenge.opaque.is_empty() {
+        arr.push(format!(r#"opaque="{}""#, challenge.opaque));
+    }
+    format!("Digest {}", arr.join(", "))
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    // hmac支持任意长度的key，不会出错
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hmac_sha512(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).unwrap();
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+// AWS要求的URI编码，仅保留A-Z a-z 0-9 - _ . ~
+fn aws_uri_encode(value: &str, keep_slash: bool) -> String {
+    let mut result = String::new();
+    for b in value.bytes() {
+        let c = b as char;
+        if c.is_ascii_alphanumeric()
+            || matches!(c, '-' | '_' | '.' | '~')
+            || (keep_slash && c == '/')
+        {
+            result.push(c);
+        } else {
+            result.push_str(&format!("%{:02X}", b));
+        }
+    }
+    result
+}
+
+fn get_host(url: &Url) -> String {
+    let host = url.host_str().unwrap_or_default();
+    match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    }
+}
+
+// AWS Signature Version 4，签名host、content-type与所有x-amz-*请求头
+#[allow(clippy::too_many_arguments)]
+pub fn aws_sigv4_sign(
+    access_key: &str,
+    secret_key: &str,
+    session_token: &str,
+    region: &str,
+    service: &str,
+    method: &str,
+    url: &Url,
+    headers: &mut HeaderMap,
+    body: &[u8],
+    now: DateTime<Utc>,
+) -> Result<(), CyberAPIError> {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let payload_hash = hex::encode(Sha256::digest(body));
+
+    headers.insert("host", HeaderValue::from_str(&get_host(url))?);
+    headers.insert("x-amz-date", HeaderValue::from_str(&amz_date)?);
+    if !session_token.is_empty() {
+        headers.insert(
+            "x-amz-security-token",
+            HeaderValue::from_str(session_token)?,
+        );
+    }
+    // s3需要x-amz-content-sha256
+    if service == "s3" {
+        headers.insert(
+            "x-amz-content-sha256",
+            HeaderValue::from_str(&payload_hash)?,
+        );
+    }
+
+    // s3以外的服务path需要再次编码
+    let path = if url.path().is_empty() {
+        "/"
+    } else {
+        url.path()
+    };
+    let canonical_uri = if service == "s3" {
+        path.to_string()
+    } else {
+        aws_uri_encode(path, true)
+    };
+    let mut query: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| (aws_uri_encode(&key, false), aws_uri_encode(&value, false)))
+        .collect();
+    query.sort();
+    let canonical_query = query
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    let mut signed: Vec<(String, String)> = vec![];
+    for (name, value) in headers.iter() {
+        let name = name.as_str().to_lowercase();
+        if name == "host" || name == "content-type" || name.starts_with("x-amz-") {
+            let value = value.to_str()?;
+            let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+            match signed.iter_mut().find(|(key, _)| key == &name) {
+                Some(item) => item.1 = format!("{},{}", item.1, value),
+                None => signed.push((name, value)),
+            }
+        }
+    }
+    signed.sort();
+    let canonical_headers: String = signed
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value))
+        .collect();
+    let signed_headers = signed
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = [
+        method.to_uppercase(),
+        canonical_uri,
+        canonical_query,
+        canonical_headers,
+        signed_headers.clone(),
+        payload_hash,
+    ]
+    .join("\n");
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    let mut key = hmac_sha256(format!("AWS4{}", secret_key).as_bytes(), date.as_bytes());
+    for item in [region, service, "aws4_request"] {
+        key = hmac_sha256(&key, item.as_bytes());
+    }
+    let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+    let authorization = format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        access_key, scope, signed_headers, signature
+    );
+    headers.insert(AUTHORIZATION, HeaderValue::from_str(&authorization)?);
+    Ok(())
+}
+
+fn get_path_and_query(url: &Url) -> String {
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    }
+}
+
+// 生成认证的请求头
+pub fn apply_auth(
+    auth: &HTTPAuth,
+    method: &str,
+    url: &Url,
+    headers: &mut HeaderMap,
+    body: &[u8],
+    state: &AuthState,
+) -> Result<(), CyberAPIError> {
+    match auth {
+        HTTPAuth::Basic { username, password } => {
+            let value = general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+            headers.insert(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Basic {}", value))?,
+            );
+        }
+        HTTPAuth::Bearer { token } => {
+            headers.insert(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {}", token))?,
+            );
+        }
+        HTTPAuth::Digest { username, password } => {
+            if let Some(challenge) = &state.digest {
+                let cnonce = ulid::Ulid::new().to_string().to_lowercase();
+                let value = digest_authorization(
+                    username,
+                    password,
+                    challenge,
+                    &method.to_uppercase(),
+                    &get_path_and_query(url),
+                    body,
+                    &cnonce,
+                    1,
+                );
+                headers.insert(AUTHORIZATION, HeaderValue::from_str(&value)?);
+            }
+        }
+        HTTPAuth::AwsSigV4 {
+            access_key,
+            secret_key,
+            session_token,
+            region,
+            service,
+        } => aws_sigv4_sign(
+            access_key,
+            secret_key,
+            session_token,
+            region,
+            service,
+            method,
+            url,
+            headers,
+            body,
+            Utc::now(),
+        )?,
+        HTTPAuth::Hmac {
+            secret,
+            key_id,
+            algorithm,
+            encoding,
+            header,
+            timestamp_header,
+        } => {
+            let timestamp = Utc::now().timestamp().to_string();
+            let mut lines = vec![method.to_uppercase(), get_path_and_query(url)];
+            // 未添加时间戳的请求头时，服务端无法获取时间戳，不参与签名
+            if !timestamp_header.is_empty() {
+                lines.push(timestamp.clone());
+            }
+            lines.push(hex::encode(Sha256::digest(body)));
+            let content = lines.join("\n");
+            let signature = match algorithm {
+                HmacAlgorithm::Sha256 => hmac_sha256(secret.as_bytes(), content.as_bytes()),
+                HmacAlgorithm::Sha512 => hmac_sha512(secret.as_bytes(), content.as_bytes()),
+            };
+            let mut signature = match encoding {
+                SignatureEncoding::Hex => hex::encode(signature),
+                SignatureEncoding::Base64 => general_purpose::STANDARD.encode(signature),
+            };
+            if !key_id.is_empty() {
+                signature = format!("{}:{}", key_id, signature);
+            }
+            if !timestamp_header.is_empty() {
+                headers.insert(
+                    timestamp_header.parse::<HeaderName>()?,
+                    HeaderValue::from_str(&timestamp)?,
+                );
+            }
+            headers.insert(
+                header.parse::<HeaderName>()?,
+                HeaderValue::from_str(&signature)?,
+            );
+        }
+        HTTPAuth::OAuth2(_) => {
+            if let Some(token) = &state.access_token {
+                headers.insert(
+                    AUTHORIZATION,
+                    HeaderValue::from_str(&format!("Bearer {}", token))?,
+                );
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,314 @@
+use std::{env, fs, path::Path, process};
+use tracing_subscriber::prelude::*;
+
+use cyberapi::error::CyberAPIError;
+use cyberapi::http_request::{HTTPTraceLayer, RequestTimeout};
+use cyberapi::runner::{self, RunnerOptions, RunnerProgress, RunnerReport};
+use cyberapi::schemas::{self, APICollection};
+use cyberapi::util;
+
+const USAGE: &str = "Usage:
+  cyberapi-cli list [source]
+  cyberapi-cli run <collection> [source] [options]
+
+Source (one of):
+  --db <file>            workspace database file
+  --backup <file>        cyberapi-backup-*.zip exported by the app
+
+Options:
+  --folder <folder>      run the folder only (name or id)
+  --env <environment>    environment to use (name or id)
+  --iterations <n>       number of iterations (default: 1)
+  --delay <ms>           delay between requests
+  --data <file>          csv or json data file, one iteration per row
+  --rows <1,2,...>       run these rows of the data file only
+  --timeout <seconds>    read timeout of each request (default: 300)
+  --bail                 stop on the first failure
+  --passphrase <value>   unlock secret variables (or CYBERAPI_PASSPHRASE)
+  --key-file <file>      unlock secret variables with the key file of the app
+  --junit <file>         write the JUnit XML report
+  --json <file>          write the JSON report
+
+Exit code: 0 all passed, 1 some requests failed, 2 invalid arguments or errors";
+
+#[derive(Default)]
+struct Args {
+    command: String,
+    collection: String,
+    db: String,
+    backup: String,
+    folder: String,
+    env: Option<String>,
+    iterations: u32,
+    delay: u64,
+    data: String,
+    rows: Vec<usize>,
+    timeout: u64,
+    bail: bool,
+    passphrase: String,
+    key_file: String,
+    junit: String,
+    json: String,
+}
+
+fn new_error(message: String) -> CyberAPIError {
+    CyberAPIError::new(message, "cli")
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, CyberAPIError> {
+    value
+        .trim()
+        .parse::<T>()
+        .map_err(|_| new_error(format!("{} should be a number, {}", name, value)))
+}
+
+fn parse_args(list: Vec<String>) -> Result<Args, CyberAPIError> {
+    let mut args = Args {
+        iterations: 1,
+        timeout: 300,
+        passphrase: env::var("CYBERAPI_PASSPHRASE").unwrap_or_default(),
+        ..Default::default()
+    };
+    let mut positional = vec![];
+    let mut iter = list.into_iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--help" || arg == "-h" {
+            args.command = "help".to_string();
+            return Ok(args);
+        }
+        if !arg.starts_with("--") {
+            positional.push(arg);
+            continue;
+        }
+        if arg == "--bail" {
+            args.bail = true;
+            continue;
+        }
+        let value = iter
+            .next()
+            .ok_or_else(|| new_error(format!("{} requires a value", arg)))?;
+        match arg.as_str() {
+            "--db" => args.db = value,
+            "--backup" => args.backup = value,
+            "--folder" => args.folder = value,
+            "--env" => args.env = Some(value),
+            "--iterations" => args.iterations = parse_number(&arg, &value)?,
+            "--delay" => args.delay = parse_number(&arg, &value)?,
+            "--data" => args.data = value,
+            "--rows" => {
+                for row in value.split(',').filter(|item| !item.trim().is_empty()) {
+                    args.rows.push(parse_number(&arg, row)?);
+                }
+            }
+            "--timeout" => args.timeout = parse_number(&arg, &value)?,
+            "--passphrase" => args.passphrase = value,
+            "--key-file" => args.key_file = value,
+            "--junit" => args.junit = value,
+            "--json" => args.json = value,
+            _ => return Err(new_error(format!("unknown option {}", arg))),
+        }
+    }
+    let mut positional = positional.into_iter();
+    args.command = positional.next().unwrap_or_default();
+    args.collection = positional.next().unwrap_or_default();
+    if args.command.is_empty() {
+        return Err(new_error("command should be specified".to_string()));
+    }
+    if args.db.is_empty() == args.backup.is_empty() && args.command != "help" {
+        return Err(new_error(
+            "either --db or --backup should be specified".to_string(),
+        ));
+    }
+    Ok(args)
+}
+
+// 打开数据库，数据库文件复制至临时目录后使用，备份文件则导入至临时的数据库，
+// 执行过程中的修改（如激活环境、提取的变量）不影响原数据，
+// 因此需取消与目录的关联，避免修改同步至原数据库关联的目录
+async fn open_database(args: &Args, dir: &Path) -> Result<(), CyberAPIError> {
+    if !args.db.is_empty() {
+        if !Path::new(&args.db).is_file() {
+            return Err(new_error(format!("database file not found, {}", args.db)));
+        }
+        let file = dir.join("workspace.db");
+        fs::copy(&args.db, &file)?;
+        schemas::switch_database(&file.to_string_lossy()).await?;
+    } else {
+        let file = dir.join("backup.db");
+        schemas::switch_database(&file.to_string_lossy()).await?;
+        schemas::import_tables(args.backup.clone()).await?;
+    }
+    schemas::unlink_all_file_store().await?;
+    // 临时应用目录中无密钥文件，需导入指定的密钥文件后再解锁
+    if !args.key_file.is_empty() {
+        schemas::import_key_file(&args.key_file).await?;
+        schemas::unlock_secret(None).await?;
+    } else if !args.passphrase.is_empty() {
+        if schemas::get_secret_status().await?.category == schemas::SECRET_CATEGORY_KEY_FILE {
+            return Err(new_error(
+                "secret key uses key file, --key-file should be specified".to_string(),
+            ));
+        }
+        schemas::unlock_secret(Some(args.passphrase.clone())).await?;
+    }
+    Ok(())
+}
+
+async fn find_collection(name: &str) -> Result<APICollection, CyberAPIError> {
+    schemas::list_api_collection()
+        .await?
+        .into_iter()
+        .find(|item| item.id == name || item.name.as_deref() == Some(name))
+        .ok_or_else(|| new_error(format!("collection not found, {}", name)))
+}
+
+async fn list(_: &Args) -> Result<i32, CyberAPIError> {
+    for collection in schemas::list_api_collection().await? {
+        println!("{}  {}", collection.id, collection.name.unwrap_or_default());
+        for folder in schemas::list_api_folder(collection.id.clone()).await? {
+            println!(
+                "    folder  {}  {}",
+                folder.id,
+                folder.name.unwrap_or_default()
+            );
+        }
+        for environment in schemas::list_environment(collection.id.clone()).await? {
+            println!(
+                "    env     {}  {}",
+                environment.id,
+                environment.name.unwrap_or_default()
+            );
+        }
+    }
+    Ok(0)
+}
+
+fn print_progress(progress: RunnerProgress) {
+    let step = progress.step;
+    let mut prefix = format!("#{}", step.iteration);
+    if step.row != 0 {
+        prefix = format!("{} row {}", prefix, step.row);
+    }
+    println!(
+        "{} [{}] {} {} {} {} {}ms",
+        if step.passed { "PASS" } else { "FAIL" },
+        prefix,
+        step.name,
+        step.method,
+        step.uri,
+        step.status,
+        step.duration
+    );
+    if !step.error.is_empty() {
+        println!("    error: {}", step.error);
+    }
+    for item in step.assertions.iter().filter(|item| !item.passed) {
+        println!("    {}", runner::describe_assertion(item));
+    }
+}
+
+fn write_reports(args: &Args, name: &str, report: &RunnerReport) -> Result<(), CyberAPIError> {
+    if !args.json.is_empty() {
+        runner::save_report(&args.json, report)?;
+    }
+    if !args.junit.is_empty() {
+        fs::write(&args.junit, runner::to_junit_report(name, report))?;
+    }
+    Ok(())
+}
+
+async fn run(args: &Args) -> Result<i32, CyberAPIError> {
+    if args.collection.is_empty() {
+        return Err(new_error("collection should be specified".to_string()));
+    }
+    let collection = find_collection(&args.collection).await?;
+    let mut folder = "".to_string();
+    if !args.folder.is_empty() {
+        folder = schemas::list_api_folder(collection.id.clone())
+            .await?
+            .into_iter()
+            .find(|item| item.id == args.folder || item.name.as_deref() == Some(&args.folder))
+            .map(|item| item.id)
+            .ok_or_else(|| new_error(format!("folder not found, {}", args.folder)))?;
+    }
+    if let Some(env) = &args.env {
+        let id = schemas::list_environment(collection.id.clone())
+            .await?
+            .into_iter()
+            .find(|item| &item.id == env || item.name.as_ref() == Some(env))
+            .map(|item| item.id)
+            .ok_or_else(|| new_error(format!("environment not found, {}", env)))?;
+        schemas::activate_environment(collection.id.clone(), Some(id)).await?;
+    }
+
+    let name = collection.name.clone().unwrap_or_default();
+    println!("Collection: {} ({})", name, collection.id);
+    let options = RunnerOptions {
+        collection: collection.id.clone(),
+        folder,
+        top_tree_items: vec![],
+        iterations: args.iterations,
+        delay: args.delay,
+        stop_on_failure: args.bail,
+        timeout: RequestTimeout {
+            connect: 10,
+            write: 120,
+            read: args.timeout,
+        },
+        data_file: args.data.clone(),
+        rows: args.rows.clone(),
+    };
+    let report = runner::run(options, print_progress).await?;
+
+    println!(
+        "Total: {}, executed: {}, passed: {}, failed: {}, duration: {}ms{}",
+        report.total,
+        report.steps.len(),
+        report.passed,
+        report.failed,
+        report.duration,
+        if report.stopped { ", stopped" } else { "" }
+    );
+    write_reports(args, &name, &report)?;
+    Ok(if report.failed == 0 { 0 } else { 1 })
+}
+
+async fn execute(args: Args) -> Result<i32, CyberAPIError> {
+    if args.command == "help" {
+        println!("{}", USAGE);
+        return Ok(0);
+    }
+    // 使用临时的应用目录，避免修改本机应用的工作区与cookie
+    let dir = env::temp_dir().join(format!("cyberapi-cli-{}", ulid::Ulid::new()));
+    fs::create_dir_all(&dir)?;
+    util::set_app_dir(dir.to_string_lossy().to_string());
+    let result = match open_database(&args, &dir).await {
+        Ok(()) => match args.command.as_str() {
+            "list" => list(&args).await,
+            "run" => run(&args).await,
+            _ => Err(new_error(format!("unknown command {}", args.command))),
+        },
+        Err(err) => Err(err),
+    };
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+fn main() {
+    tracing_subscriber::registry().with(HTTPTraceLayer).init();
+    let args = match parse_args(env::args().skip(1).collect()) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err.message(), USAGE);
+            process::exit(2);
+        }
+    };
+    let code = match tauri::async_runtime::block_on(execute(args)) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("{}", err.message());
+            2
+        }
+    };
+    process::exit(code);
+}
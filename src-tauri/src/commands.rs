@@ -1,8 +1,18 @@
 use crate::error::CyberAPIError;
-use crate::schemas::{self, APICollection, APIFolder, APISetting, Variable};
+use crate::file_watcher::{self, FileStoreSyncResult};
+use crate::load_test::{self, LoadTestOptions, LoadTestReport};
+use crate::mock_server::{self, MockRequestLog, MockServerStatus};
+use crate::oauth::{self, OAuthConfig};
+use crate::runner::{self, RunnerOptions, RunnerReport};
+use crate::schemas::{
+    self, APICollection, APIExample, APIFolder, APISetting, Environment, EnvironmentChange,
+    GlobalVariable, TrashItem, Variable,
+};
+use crate::template::{TemplatePreview, TemplateVariable};
+use crate::workspace::{self, Workspace};
 use crate::{cookies, http_request};
 use tauri::Manager;
-use tauri::{command, Window};
+use tauri::{command, AppHandle, Window};
 
 pub type CommandResult<T> = Result<T, CyberAPIError>;
 
@@ -35,6 +45,7 @@ pub async fn update_api_setting(setting: APISetting) -> CommandResult<APISetting
 #[command(async)]
 pub async fn init_tables() -> CommandResult<()> {
     schemas::init_tables().await?;
+    workspace::migrate_shared_cookies().await?;
     Ok(())
 }
 
@@ -50,6 +61,13 @@ pub async fn import_tables(file: String) -> CommandResult<()> {
     Ok(())
 }
 
+// 查找并修复孤立数据
+#[command(async)]
+pub async fn repair_database(dry_run: bool) -> CommandResult<schemas::DatabaseRepairResult> {
+    let result = schemas::repair_database(dry_run).await?;
+    Ok(result)
+}
+
 // 获取所有API配置
 #[command(async)]
 pub async fn list_api_setting(collection: String) -> CommandResult<Vec<APISetting>> {
@@ -57,13 +75,134 @@ pub async fn list_api_setting(collection: String) -> CommandResult<Vec<APISettin
     Ok(result)
 }
 
-// 删除API配置
+// 删除API配置（移至回收站）
 #[command(async)]
 pub async fn delete_api_settings(ids: Vec<String>) -> CommandResult<()> {
     schemas::delete_api_settings(ids).await?;
     Ok(())
 }
 
+// 获取API配置的历史版本
+#[command(async)]
+pub async fn list_api_setting_revision(
+    api_setting: String,
+) -> CommandResult<Vec<schemas::APISettingRevision>> {
+    let result = schemas::list_api_setting_revision(api_setting).await?;
+    Ok(result)
+}
+
+// 对比API配置的两个历史版本，to为空则与当前配置对比
+#[command(async)]
+pub async fn diff_api_setting_revision(
+    from: String,
+    to: Option<String>,
+) -> CommandResult<Vec<schemas::APISettingChange>> {
+    let result = schemas::diff_api_setting_revision(from, to).await?;
+    Ok(result)
+}
+
+// 恢复API配置至历史版本
+#[command(async)]
+pub async fn restore_api_setting_revision(id: String) -> CommandResult<APISetting> {
+    let result = schemas::restore_api_setting_revision(id).await?;
+    Ok(result)
+}
+
+// 清除超出数量或已过期的历史版本
+#[command(async)]
+pub async fn clean_api_setting_revision(max_count: u32, expired_days: u32) -> CommandResult<u64> {
+    let result = schemas::clean_api_setting_revision(max_count, expired_days).await?;
+    Ok(result)
+}
+
+// 保存响应为API配置的示例
+#[command(async)]
+pub async fn add_api_example(example: APIExample) -> CommandResult<APIExample> {
+    let result = schemas::add_api_example(example).await?;
+    Ok(result)
+}
+
+// 获取API配置的示例
+#[command(async)]
+pub async fn list_api_example(api_setting: String) -> CommandResult<Vec<APIExample>> {
+    let result = schemas::list_api_example(api_setting).await?;
+    Ok(result)
+}
+
+// 获取collection下所有API配置的示例
+#[command(async)]
+pub async fn list_collection_api_example(collection: String) -> CommandResult<Vec<APIExample>> {
+    let result = schemas::list_collection_api_example(collection).await?;
+    Ok(result)
+}
+
+// 修改示例名称
+#[command(async)]
+pub async fn rename_api_example(id: String, name: String) -> CommandResult<APIExample> {
+    let result = schemas::rename_api_example(id, name).await?;
+    Ok(result)
+}
+
+// 删除示例
+#[command(async)]
+pub async fn delete_api_example(id: String) -> CommandResult<u64> {
+    let result = schemas::delete_api_example(id).await?;
+    Ok(result)
+}
+
+// 全文搜索API配置
+#[command(async)]
+pub async fn search_api_settings(
+    keyword: String,
+    collection: Option<String>,
+    limit: u64,
+) -> CommandResult<Vec<schemas::SearchResult>> {
+    let result = schemas::search_api_settings(keyword, collection, limit).await?;
+    Ok(result)
+}
+
+// 获取所有保存至目录的collection
+#[command(async)]
+pub async fn list_file_store() -> CommandResult<Vec<schemas::FileStore>> {
+    let result = schemas::list_file_store().await?;
+    Ok(result)
+}
+
+// 将collection保存至目录，并监听目录的修改
+#[command(async)]
+pub async fn link_file_store(
+    app_handle: AppHandle,
+    collection: String,
+    dir: String,
+) -> CommandResult<schemas::FileStore> {
+    let result = schemas::link_file_store(collection, dir).await?;
+    file_watcher::watch(app_handle, result.collection.clone(), result.dir.clone())?;
+    Ok(result)
+}
+
+// 打开collection目录，并监听目录的修改
+#[command(async)]
+pub async fn open_file_store(app_handle: AppHandle, dir: String) -> CommandResult<APICollection> {
+    let result = schemas::open_file_store(dir.clone()).await?;
+    file_watcher::watch(app_handle, result.id.clone(), dir)?;
+    Ok(result)
+}
+
+// 取消collection与目录的关联
+#[command(async)]
+pub async fn unlink_file_store(collection: String) -> CommandResult<()> {
+    file_watcher::unwatch(&collection);
+    schemas::unlink_file_store(collection).await?;
+    Ok(())
+}
+
+// 同步所有collection目录并开始监听
+#[command(async)]
+pub async fn watch_file_stores(app_handle: AppHandle) -> CommandResult<Vec<FileStoreSyncResult>> {
+    let result = file_watcher::watch_all(app_handle).await?;
+    Ok(result)
+}
+
 // 新增collection
 #[command(async)]
 pub async fn add_api_collection(collection: APICollection) -> CommandResult<APICollection> {
@@ -85,10 +224,9 @@ pub async fn list_api_collection() -> CommandResult<Vec<APICollection>> {
     Ok(result)
 }
 
+// 删除collection（移至回收站）
 #[command(async)]
 pub async fn delete_api_collection(id: String) -> CommandResult<u64> {
-    schemas::delete_api_setting_by_collection(id.clone()).await?;
-    schemas::delete_api_folder_by_collection(id.clone()).await?;
     let count = schemas::delete_api_collection(id).await?;
     Ok(count)
 }
@@ -114,13 +252,38 @@ pub async fn list_api_folder(collection: String) -> CommandResult<Vec<APIFolder>
     Ok(result)
 }
 
-// 删除API目录对应的所有子目录
+// 删除API目录对应的所有子目录（移至回收站）
 #[command(async)]
 pub async fn delete_api_folder(id: String) -> CommandResult<schemas::APIFolderChildren> {
-    let mut result = schemas::list_api_folder_all_children(id.clone()).await?;
-    result.folders.push(id);
-    schemas::delete_api_folders(result.folders.clone()).await?;
-    schemas::delete_api_settings(result.settings.clone()).await?;
+    let result = schemas::delete_api_folder(id).await?;
+    Ok(result)
+}
+
+// 获取回收站数据
+#[command(async)]
+pub async fn list_trash() -> CommandResult<Vec<TrashItem>> {
+    let result = schemas::list_trash().await?;
+    Ok(result)
+}
+
+// 从回收站恢复
+#[command(async)]
+pub async fn restore_trash(ids: Vec<String>) -> CommandResult<()> {
+    schemas::restore_trash(ids).await?;
+    Ok(())
+}
+
+// 永久删除回收站数据
+#[command(async)]
+pub async fn purge_trash(ids: Vec<String>) -> CommandResult<()> {
+    schemas::purge_trash(ids).await?;
+    Ok(())
+}
+
+// 清除回收站中已过期的数据
+#[command(async)]
+pub async fn clean_trash(expired_days: u32) -> CommandResult<Vec<TrashItem>> {
+    let result = schemas::clean_trash(expired_days).await?;
     Ok(result)
 }
 
@@ -144,13 +307,180 @@ pub async fn delete_variable(ids: Vec<String>) -> CommandResult<u64> {
     let count = schemas::delete_variable(ids).await?;
     Ok(count)
 }
-// 获取所有变量
+// 获取所有变量，未指定环境则为基础环境的变量
+#[command(async)]
+pub async fn list_variable(
+    collection: String,
+    category: String,
+    environment: Option<String>,
+    reveal: Option<bool>,
+) -> CommandResult<Vec<Variable>> {
+    let result = schemas::list_variable(
+        collection,
+        category,
+        environment.unwrap_or_default(),
+        reveal.unwrap_or_default(),
+    )
+    .await?;
+    Ok(result)
+}
+
+// 获取单个变量的值，加密变量则返回解密后的值
+#[command(async)]
+pub async fn reveal_variable(id: String) -> CommandResult<String> {
+    let value = schemas::reveal_variable(id).await?;
+    Ok(value)
+}
+
+// 新增环境
+#[command(async)]
+pub async fn add_environment(environment: Environment) -> CommandResult<Environment> {
+    let result = schemas::add_environment(environment).await?;
+    Ok(result)
+}
+
+// 更新环境
+#[command(async)]
+pub async fn update_environment(environment: Environment) -> CommandResult<Environment> {
+    let result = schemas::update_environment(environment).await?;
+    Ok(result)
+}
+
+// 删除环境及其变量
+#[command(async)]
+pub async fn delete_environment(id: String) -> CommandResult<()> {
+    schemas::delete_environment(id).await?;
+    Ok(())
+}
+
+// 获取collection的所有环境
+#[command(async)]
+pub async fn list_environment(collection: String) -> CommandResult<Vec<Environment>> {
+    let result = schemas::list_environment(collection).await?;
+    Ok(result)
+}
+
+// 复制环境
+#[command(async)]
+pub async fn clone_environment(id: String, name: String) -> CommandResult<Environment> {
+    let result = schemas::clone_environment(id, name).await?;
+    Ok(result)
+}
+
+// 对比环境的变量，为空则为基础环境
 #[command(async)]
-pub async fn list_variable(collection: String, category: String) -> CommandResult<Vec<Variable>> {
-    let result = schemas::list_variable(collection, category).await?;
+pub async fn compare_environment(
+    collection: String,
+    from: Option<String>,
+    to: Option<String>,
+) -> CommandResult<Vec<EnvironmentChange>> {
+    let result = schemas::compare_environment(collection, from, to).await?;
     Ok(result)
 }
 
+// 设置collection当前使用的环境
+#[command(async)]
+pub async fn activate_environment(collection: String, id: Option<String>) -> CommandResult<()> {
+    schemas::activate_environment(collection, id).await?;
+    Ok(())
+}
+
+// 获取collection当前使用的环境
+#[command(async)]
+pub async fn get_active_environment(collection: String) -> CommandResult<Option<Environment>> {
+    let result = schemas::get_active_environment(collection).await?;
+    Ok(result)
+}
+
+// 新增全局变量
+#[command(async)]
+pub async fn add_global_variable(value: GlobalVariable) -> CommandResult<GlobalVariable> {
+    let result = schemas::add_global_variable(value).await?;
+    Ok(result)
+}
+
+// 更新全局变量
+#[command(async)]
+pub async fn update_global_variable(value: GlobalVariable) -> CommandResult<GlobalVariable> {
+    let result = schemas::update_global_variable(value).await?;
+    Ok(result)
+}
+
+// 删除全局变量
+#[command(async)]
+pub async fn delete_global_variable(ids: Vec<String>) -> CommandResult<u64> {
+    let count = schemas::delete_global_variable(ids).await?;
+    Ok(count)
+}
+
+// 获取所有全局变量
+#[command(async)]
+pub async fn list_global_variable(reveal: Option<bool>) -> CommandResult<Vec<GlobalVariable>> {
+    let result = schemas::list_global_variable(reveal.unwrap_or_default()).await?;
+    Ok(result)
+}
+
+fn to_local_variables(
+    variables: Option<Vec<http_request::HTTPRequestKVParam>>,
+) -> Vec<(String, String)> {
+    variables
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|item| item.enabled)
+        .map(|item| (item.key, item.value))
+        .collect()
+}
+
+// 预览模板替换变量后的结果，未定义的变量保持原样
+#[command(async)]
+pub async fn resolve_template(
+    collection: Option<String>,
+    template: String,
+    variables: Option<Vec<http_request::HTTPRequestKVParam>>,
+) -> CommandResult<TemplatePreview> {
+    let context = schemas::get_template_context(collection, to_local_variables(variables)).await?;
+    let result = context.preview(&template)?;
+    Ok(result)
+}
+
+// 获取生效的变量及其作用域
+#[command(async)]
+pub async fn list_resolved_variable(
+    collection: Option<String>,
+    variables: Option<Vec<http_request::HTTPRequestKVParam>>,
+) -> CommandResult<Vec<TemplateVariable>> {
+    let context = schemas::get_template_context(collection, to_local_variables(variables)).await?;
+    Ok(context.list())
+}
+
+// 获取加密变量的密钥状态
+#[command(async)]
+pub async fn get_secret_status() -> CommandResult<schemas::SecretStatus> {
+    let result = schemas::get_secret_status().await?;
+    Ok(result)
+}
+
+// 初始化加密变量的密钥，未指定密码则使用本机的密钥文件
+#[command(async)]
+pub async fn init_secret_key(passphrase: Option<String>) -> CommandResult<schemas::SecretStatus> {
+    let result = schemas::init_secret_key(passphrase).await?;
+    Ok(result)
+}
+
+// 解锁加密变量
+#[command(async)]
+pub async fn unlock_secret(passphrase: Option<String>) -> CommandResult<schemas::SecretStatus> {
+    let result = schemas::unlock_secret(passphrase).await?;
+    Ok(result)
+}
+
+// 锁定加密变量
+#[command(async)]
+pub fn lock_secret() -> CommandResult<()> {
+    schemas::lock_secret();
+    Ok(())
+}
+
 // 执行HTTP请求
 #[command(async)]
 pub async fn do_http_request(
@@ -161,33 +491,194 @@ pub async fn do_http_request(
     http_request::request(api, req, timeout).await
 }
 
-// 获取所有cookie
+// 按顺序执行collection或目录下的请求，每个请求完成后推送进度事件
+#[command(async)]
+pub async fn run_collection(
+    app_handle: AppHandle,
+    options: RunnerOptions,
+) -> CommandResult<RunnerReport> {
+    runner::run(options, |progress| {
+        let _ = app_handle.emit_all(runner::RUNNER_PROGRESS_EVENT, progress);
+    })
+    .await
+}
+
+// 保存执行报告至文件
+#[command(async)]
+pub fn save_runner_report(file: String, report: RunnerReport) -> CommandResult<()> {
+    runner::save_report(&file, &report)
+}
+
+// 对请求压测，定时推送进度事件
 #[command(async)]
-pub fn list_cookie() -> CommandResult<Vec<String>> {
-    cookies::list_cookie()
+pub async fn load_test(
+    app_handle: AppHandle,
+    api: String,
+    req: http_request::HTTPRequest,
+    timeout: http_request::RequestTimeout,
+    options: LoadTestOptions,
+) -> CommandResult<LoadTestReport> {
+    load_test::run(api, req, timeout, options, |progress| {
+        let _ = app_handle.emit_all(load_test::LOAD_TEST_PROGRESS_EVENT, progress);
+    })
+    .await
+}
+
+// 停止压测
+#[command(async)]
+pub fn stop_load_test(id: String) -> CommandResult<()> {
+    load_test::stop(&id);
+    Ok(())
+}
+
+// 启动collection的mock服务，接收到请求后推送事件
+#[command(async)]
+pub async fn start_mock_server(
+    app_handle: AppHandle,
+    collection: String,
+    port: u16,
+) -> CommandResult<MockServerStatus> {
+    mock_server::start(collection, port, move |log| {
+        let _ = app_handle.emit_all(mock_server::MOCK_REQUEST_EVENT, log);
+    })
+    .await
+}
+
+// 停止mock服务
+#[command(async)]
+pub fn stop_mock_server() -> CommandResult<()> {
+    mock_server::stop();
+    Ok(())
+}
+
+// 获取mock服务的状态
+#[command(async)]
+pub fn get_mock_server_status() -> CommandResult<MockServerStatus> {
+    Ok(mock_server::get_status())
+}
+
+// 获取mock服务接收到的请求
+#[command(async)]
+pub fn list_mock_requests() -> CommandResult<Vec<MockRequestLog>> {
+    Ok(mock_server::list_requests())
+}
+
+// 清除mock服务的请求记录
+#[command(async)]
+pub fn clear_mock_requests() -> CommandResult<()> {
+    mock_server::clear_requests();
+    Ok(())
+}
+
+// 获取最近打开的工作区
+#[command(async)]
+pub fn list_workspace() -> CommandResult<Vec<Workspace>> {
+    Ok(workspace::list_workspace())
+}
+
+// 获取当前工作区
+#[command(async)]
+pub fn get_current_workspace() -> CommandResult<Workspace> {
+    Ok(workspace::get_current_workspace())
+}
+
+// 创建工作区并切换
+#[command(async)]
+pub async fn create_workspace(name: String, path: String) -> CommandResult<Workspace> {
+    workspace::create_workspace(name, path).await
+}
+
+// 打开数据库文件作为工作区
+#[command(async)]
+pub async fn open_workspace(path: String) -> CommandResult<Workspace> {
+    workspace::open_workspace(path).await
+}
+
+// 切换至最近打开的工作区
+#[command(async)]
+pub async fn switch_workspace(id: String) -> CommandResult<Workspace> {
+    workspace::switch_workspace(id).await
+}
+
+// 关闭工作区，返回关闭后的当前工作区
+#[command(async)]
+pub async fn close_workspace(id: String) -> CommandResult<Workspace> {
+    workspace::close_workspace(id).await
+}
+
+// 获取作用域内的所有cookie
+#[command(async)]
+pub fn list_cookie(scope: cookies::CookieScope) -> CommandResult<Vec<cookies::Cookie>> {
+    cookies::list_cookie(&scope)
 }
 
 // 删除cookie
 #[command(async)]
-pub fn delete_cookie(c: cookies::Cookie) -> CommandResult<()> {
-    cookies::delete_cookie_from_store(c)?;
+pub fn delete_cookie(scope: cookies::CookieScope, c: cookies::Cookie) -> CommandResult<()> {
+    cookies::delete_cookie_from_store(&scope, c)?;
     Ok(())
 }
 
 // 添加cookie
 #[command(async)]
-pub fn add_cookie(c: cookies::Cookie) -> CommandResult<()> {
-    cookies::add_cookie(c)?;
+pub fn add_cookie(scope: cookies::CookieScope, c: cookies::Cookie) -> CommandResult<()> {
+    cookies::add_cookie(&scope, c)?;
     Ok(())
 }
 
-// 清除cookie
+// 更新cookie，original为更新前cookie的name、domain与path
 #[command(async)]
-pub fn clear_cookie() -> CommandResult<()> {
-    cookies::clear_cookie_from_store()?;
+pub fn update_cookie(
+    scope: cookies::CookieScope,
+    original: cookies::CookieKey,
+    c: cookies::Cookie,
+) -> CommandResult<()> {
+    cookies::update_cookie(&scope, original, c)?;
     Ok(())
 }
 
+// 导入cookies.txt（Netscape格式）的cookie
+#[command(async)]
+pub fn import_cookie(
+    scope: cookies::CookieScope,
+    file: String,
+) -> CommandResult<cookies::CookieImportResult> {
+    cookies::import_cookie_file(&scope, &file)
+}
+
+// 导出cookie为cookies.txt（Netscape格式），domain为空则导出所有cookie
+#[command(async)]
+pub fn export_cookie(
+    scope: cookies::CookieScope,
+    file: String,
+    domain: String,
+) -> CommandResult<()> {
+    cookies::export_cookie_file(&scope, &file, &domain)
+}
+
+// 清除作用域内的cookie
+#[command(async)]
+pub fn clear_cookie(scope: cookies::CookieScope) -> CommandResult<()> {
+    cookies::clear_cookie_from_store(&scope)?;
+    Ok(())
+}
+
+// 获取collection当前环境的OAuth token，已缓存且未过期则直接返回
+#[command(async)]
+pub async fn get_oauth_token(
+    collection: String,
+    config: OAuthConfig,
+) -> CommandResult<schemas::OAuthToken> {
+    oauth::get_collection_token(collection, config).await
+}
+
+// 删除collection缓存的OAuth token，下次请求时重新获取
+#[command(async)]
+pub async fn delete_oauth_token(collection: String) -> CommandResult<u64> {
+    let result = schemas::delete_oauth_token(collection.clone()).await?;
+    Ok(result + oauth::delete_memory_tokens(&collection))
+}
+
 // 获取最新版本
 #[command(async)]
 pub async fn get_latest_version() -> CommandResult<schemas::Version> {
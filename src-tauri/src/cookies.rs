@@ -1,44 +1,302 @@
 use cookie_store::CookieStore;
-use once_cell::sync::OnceCell;
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use std::{
-    fs::File, fs::OpenOptions, io::BufReader, io::BufWriter, path::Path, sync::Mutex,
-    sync::MutexGuard,
+    collections::HashMap, collections::HashSet, fs, fs::File, io::BufReader, io::ErrorKind,
+    io::Write, path::Path, path::PathBuf, sync::Mutex,
 };
 use url::Url;
 
 use crate::error::CyberAPIError;
 use crate::util::get_app_dir;
+use crate::workspace::{get_current_workspace, DEFAULT_WORKSPACE};
 
-static COOKIE_STORE: OnceCell<Mutex<CookieStore>> = OnceCell::new();
-
-fn init_store() -> &'static Mutex<CookieStore> {
-    COOKIE_STORE.get_or_init(|| {
-        let filename = Path::new(get_app_dir()).join(COOKIE_FILE);
-        let file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .read(true)
-            .open(filename)
-            .map(BufReader::new)
-            .unwrap();
-        let store = cookie_store::CookieStore::load_json(file).unwrap();
-        Mutex::new(store)
-    })
+// cookie的创建与最近访问时间，cookie_store中无此信息，单独保存
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CookieTimes {
+    created_at: String,
+    last_accessed_at: String,
+}
+
+// 已加载的cookie store及各cookie的时间，时间以domain;path;name为key
+#[derive(Clone)]
+struct ScopedStore {
+    store: CookieStore,
+    times: HashMap<String, CookieTimes>,
+}
+
+fn get_cookie_key(domain: &str, path: &str, name: &str) -> String {
+    format!("{};{};{}", domain, path, name)
+}
+
+fn get_store_cookie_key(c: &cookie_store::Cookie) -> String {
+    let domain = c.domain.as_cow().unwrap_or_default();
+    get_cookie_key(&domain, &c.path, c.name())
 }
 
+// cookie时间保存的文件，如cookies.json对应cookies.times.json
+fn get_times_file(filename: &Path) -> PathBuf {
+    filename.with_extension("times.json")
+}
+
+// 已加载的cookie store，以文件区分工作区与作用域
+// 会话cookie不会保存至文件，因此切换工作区后仍保留原工作区的cookie store
+static COOKIE_STORES: Lazy<Mutex<HashMap<PathBuf, ScopedStore>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
 const COOKIE_FILE: &str = "cookies.json";
 
-#[derive(Debug, Serialize, Deserialize)]
+// collection（或环境）的cookie保存的目录
+const COOKIE_DIR: &str = "cookies";
+
+// 已将工作区共用的cookie复制至各collection的标记文件，位于工作区的cookie目录
+const COOKIE_MIGRATED_FILE: &str = ".migrated";
+
+// cookie的作用域，collection为空时为工作区共用的cookie
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CookieScope {
+    #[serde(default)]
+    pub collection: String,
+    // 环境，为空则collection的所有环境共用
+    #[serde(default)]
+    pub environment: String,
+}
+
+// 请求使用的cookie
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CookieJar {
+    // collection的所有环境共用
+    #[default]
+    Collection,
+    // collection的各环境分别使用
+    Environment,
+    // 不发送也不保存cookie
+    Disabled,
+}
+
+// cookie保存在本机的应用目录，各工作区使用不同的文件
+fn get_cookie_file(workspace: &str, scope: &CookieScope) -> PathBuf {
+    let dir = Path::new(get_app_dir());
+    if scope.collection.is_empty() {
+        let name = if workspace == DEFAULT_WORKSPACE {
+            COOKIE_FILE.to_string()
+        } else {
+            format!("cookies-{}.json", workspace)
+        };
+        return dir.join(name);
+    }
+    let name = if scope.environment.is_empty() {
+        format!("{}.json", scope.collection)
+    } else {
+        format!("{}-{}.json", scope.collection, scope.environment)
+    };
+    dir.join(COOKIE_DIR).join(workspace).join(name)
+}
+
+// 删除已过期的cookie及其时间
+fn sweep_expired(scoped: &mut ScopedStore) {
+    let expired: Vec<(String, String, String)> = scoped
+        .store
+        .iter_any()
+        .filter(|c| c.is_expired())
+        .map(|c| {
+            (
+                c.domain.as_cow().unwrap_or_default().to_string(),
+                c.path.to_string(),
+                c.name().to_string(),
+            )
+        })
+        .collect();
+    for (domain, path, name) in expired {
+        scoped.store.remove(&domain, &path, &name);
+        scoped.times.remove(&get_cookie_key(&domain, &path, &name));
+    }
+}
+
+// 无法解析的文件（如写入时崩溃导致内容不完整）重命名备份，如cookies.corrupt-20230101120000.json
+fn backup_corrupt_file(filename: &Path) -> Result<(), CyberAPIError> {
+    let suffix = format!(
+        "corrupt-{}.json",
+        chrono::Local::now().format("%Y%m%d%H%M%S")
+    );
+    fs::rename(filename, filename.with_extension(suffix))?;
+    Ok(())
+}
+
+// 从文件中加载cookie，文件已损坏则备份后使用空的cookie store
+fn load_store(filename: &Path) -> Result<ScopedStore, CyberAPIError> {
+    let store = match File::open(filename) {
+        Ok(file) => match CookieStore::load_json(BufReader::new(file)) {
+            Ok(store) => store,
+            Err(_) => {
+                backup_corrupt_file(filename)?;
+                CookieStore::default()
+            }
+        },
+        Err(err) if err.kind() == ErrorKind::NotFound => CookieStore::default(),
+        Err(err) => return Err(err.into()),
+    };
+    // 时间文件不存在或无法解析时忽略，仅影响创建与访问时间的展示
+    let times = fs::read(get_times_file(filename))
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default();
+    let mut scoped = ScopedStore { store, times };
+    sweep_expired(&mut scoped);
+    Ok(scoped)
+}
+
+// 获取作用域对应的cookie store并处理，未加载时从文件中加载
+fn with_cookie_store<T, F>(scope: &CookieScope, f: F) -> Result<T, CyberAPIError>
+where
+    F: FnOnce(&mut ScopedStore, &Path) -> Result<T, CyberAPIError>,
+{
+    let workspace = get_current_workspace().id;
+    let filename = get_cookie_file(&workspace, scope);
+    let mut stores = COOKIE_STORES.lock().unwrap();
+    if !stores.contains_key(&filename) {
+        stores.insert(filename.clone(), load_store(&filename)?);
+    }
+    let store = stores.get_mut(&filename).unwrap();
+    f(store, &filename)
+}
+
+// 升级前所有collection共用工作区的cookie，升级后首次打开工作区时复制至已有的collection并保存，
+// 保持升级前的登录状态，之后新建的collection使用空的cookie
+pub fn migrate_shared_cookie_store(collections: &[String]) -> Result<(), CyberAPIError> {
+    let workspace = get_current_workspace().id;
+    let dir = Path::new(get_app_dir()).join(COOKIE_DIR).join(&workspace);
+    let marker = dir.join(COOKIE_MIGRATED_FILE);
+    if marker.exists() {
+        return Ok(());
+    }
+    let shared = get_cookie_file(&workspace, &CookieScope::default());
+    if shared.exists() {
+        let mut stores = COOKIE_STORES.lock().unwrap();
+        let store = match stores.get(&shared) {
+            Some(store) => store.clone(),
+            None => load_store(&shared)?,
+        };
+        for collection in collections {
+            let filename = get_cookie_file(
+                &workspace,
+                &CookieScope {
+                    collection: collection.clone(),
+                    environment: "".to_string(),
+                },
+            );
+            if filename.exists() || stores.contains_key(&filename) {
+                continue;
+            }
+            let mut scoped = store.clone();
+            save_store(&mut scoped, &filename)?;
+            stores.insert(filename, scoped);
+        }
+    }
+    fs::create_dir_all(&dir)?;
+    write_file_atomic(&marker, &[])
+}
+
+// 删除工作区的cookie文件
+pub fn remove_cookie_file(workspace: &str) -> Result<(), CyberAPIError> {
+    if workspace == DEFAULT_WORKSPACE {
+        return Ok(());
+    }
+    let filename = get_cookie_file(workspace, &CookieScope::default());
+    let dir = Path::new(get_app_dir()).join(COOKIE_DIR).join(workspace);
+    COOKIE_STORES
+        .lock()
+        .unwrap()
+        .retain(|key, _| key != &filename && !key.starts_with(&dir));
+    if filename.exists() {
+        fs::remove_file(filename)?;
+    }
+    if dir.exists() {
+        fs::remove_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+// cookie的标识，用于定位需要更新的cookie
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CookieKey {
+    pub name: String,
+    #[serde(default)]
+    pub domain: String,
+    #[serde(default)]
+    pub path: String,
+}
+
+// RFC 6265定义的cookie属性
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Cookie {
-    name: String,
-    value: String,
-    path: String,
-    domain: String,
-    expires: String,
+    pub name: String,
+    pub value: String,
+    #[serde(default)]
+    pub path: String,
+    #[serde(default)]
+    pub domain: String,
+    // 过期时间，RFC3339或HTTP-date格式，为空则为会话cookie
+    #[serde(default)]
+    pub expires: String,
+    // 有效时长(秒)，优先于expires
+    #[serde(default)]
+    pub max_age: Option<i64>,
+    #[serde(default)]
+    pub secure: bool,
+    #[serde(default)]
+    pub http_only: bool,
+    // Strict、Lax或None，为空则未设置
+    #[serde(default)]
+    pub same_site: String,
+    // 仅发送至domain对应的host，不包括其子域名
+    #[serde(default)]
+    pub host_only: bool,
+    // 是否会持久化（有过期时间）
+    #[serde(default)]
+    pub persistent: bool,
+    // 以下字段仅在list_cookie时返回，添加或更新时忽略
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub last_accessed_at: String,
 }
 
 impl Cookie {
+    fn from_store_cookie(c: &cookie_store::Cookie, times: Option<&CookieTimes>) -> Self {
+        let host_only = matches!(c.domain, cookie_store::CookieDomain::HostOnly(_));
+        let expires = match c.expires {
+            cookie_store::CookieExpiration::AtUtc(ref datetime) => {
+                chrono::DateTime::from_timestamp(datetime.unix_timestamp(), 0)
+                    .map(|value| value.to_rfc3339())
+                    .unwrap_or_default()
+            }
+            cookie_store::CookieExpiration::SessionEnd => "".to_string(),
+        };
+        let times = times.cloned().unwrap_or_default();
+        Cookie {
+            name: c.name().to_string(),
+            value: c.value().to_string(),
+            path: c.path.to_string(),
+            domain: c.domain.as_cow().unwrap_or_default().to_string(),
+            expires,
+            max_age: c.max_age().map(|value| value.whole_seconds()),
+            secure: c.secure().unwrap_or_default(),
+            http_only: c.http_only().unwrap_or_default(),
+            same_site: c
+                .same_site()
+                .map(|value| value.to_string())
+                .unwrap_or_default(),
+            host_only,
+            persistent: c.is_persistent(),
+            created_at: times.created_at,
+            last_accessed_at: times.last_accessed_at,
+        }
+    }
     fn to_set_cookie_string(&self) -> String {
         let mut arr = Vec::new();
         arr.push(format!("{}={}", self.name, self.value));
@@ -46,109 +304,406 @@ impl Cookie {
         if !self.path.is_empty() {
             arr.push(format!("Path={}", self.path));
         }
-        if !self.domain.is_empty() {
+        // host only的cookie不设置Domain属性
+        if !self.domain.is_empty() && !self.host_only {
             arr.push(format!("Domain={}", self.domain));
         }
         if !self.expires.is_empty() {
-            arr.push(format!("Expires={}", self.expires));
+            // list_cookie返回的为RFC3339格式，需转换为HTTP-date
+            let expires = match chrono::DateTime::parse_from_rfc3339(&self.expires) {
+                Ok(value) => value
+                    .with_timezone(&chrono::Utc)
+                    .format("%a, %d %b %Y %H:%M:%S GMT")
+                    .to_string(),
+                Err(_) => self.expires.clone(),
+            };
+            arr.push(format!("Expires={}", expires));
+        }
+        if let Some(max_age) = self.max_age {
+            arr.push(format!("Max-Age={}", max_age));
+        }
+        if self.secure {
+            arr.push("Secure".to_string());
+        }
+        if self.http_only {
+            arr.push("HttpOnly".to_string());
+        }
+        if !self.same_site.is_empty() {
+            arr.push(format!("SameSite={}", self.same_site));
         }
 
-        arr.join(";")
+        arr.join("; ")
     }
     fn get_url(&self) -> String {
         let mut path = self.path.clone();
         if path.is_empty() {
             path = "/".to_string()
         }
+        // secure的cookie需要https才可设置
+        let scheme = if self.secure { "https" } else { "http" };
+        let domain = self.domain.trim_start_matches('.');
 
-        format!("http://{}{}", self.domain, path)
+        format!("{}://{}{}", scheme, domain, path)
     }
 }
 
-pub fn get_cookie_store() -> MutexGuard<'static, CookieStore> {
-    let result = init_store();
-    result.lock().unwrap()
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339()
 }
 
-fn save_store(store: MutexGuard<CookieStore>) -> Result<(), CyberAPIError> {
-    let filename = Path::new(get_app_dir()).join(COOKIE_FILE);
-    let mut writer = File::create(filename).map(BufWriter::new)?;
-    store.save_json(&mut writer)?;
+// 先写入临时文件再重命名，避免写入过程中崩溃导致文件损坏
+fn write_file_atomic(filename: &Path, data: &[u8]) -> Result<(), CyberAPIError> {
+    let mut tmp = filename.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let mut file = File::create(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&tmp, filename)?;
     Ok(())
 }
 
-pub fn delete_cookie_from_store(c: Cookie) -> Result<(), CyberAPIError> {
+fn save_store(scoped: &mut ScopedStore, filename: &Path) -> Result<(), CyberAPIError> {
+    if let Some(dir) = filename.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    sweep_expired(scoped);
+    let mut buf = vec![];
+    scoped.store.save_json(&mut buf)?;
+    write_file_atomic(filename, &buf)?;
+
+    // 清除已不存在的cookie的时间
+    let keys: HashSet<String> = scoped.store.iter_any().map(get_store_cookie_key).collect();
+    scoped.times.retain(|key, _| keys.contains(key));
+    write_file_atomic(
+        &get_times_file(filename),
+        &serde_json::to_vec(&scoped.times)?,
+    )
+}
+
+// 获取请求url对应的Cookie请求头，并更新cookie的最近访问时间
+pub fn get_cookie_header(scope: &CookieScope, url: &Url) -> Result<String, CyberAPIError> {
+    with_cookie_store(scope, |scoped, _| {
+        let accessed_at = now();
+        let mut arr = vec![];
+        for c in scoped.store.matches(url) {
+            arr.push(format!("{}={}", c.name(), c.value()));
+            scoped
+                .times
+                .entry(get_store_cookie_key(c))
+                .or_default()
+                .last_accessed_at = accessed_at.clone();
+        }
+        Ok(arr.join("; "))
+    })
+}
+
+pub fn delete_cookie_from_store(scope: &CookieScope, c: Cookie) -> Result<(), CyberAPIError> {
     let name = c.name;
     if name.is_empty() {
         return Ok(());
     }
-    let mut store = get_cookie_store();
-    let domain = c.domain.as_str();
-    let path = c.path.as_str();
+    with_cookie_store(scope, |scoped, filename| {
+        let domain = c.domain.as_str();
+        let path = c.path.as_str();
 
-    store.remove(domain, path, name.as_str());
-    save_store(store)?;
+        scoped.store.remove(domain, path, name.as_str());
+        save_store(scoped, filename)
+    })
+}
 
-    Ok(())
+pub fn clear_cookie_from_store(scope: &CookieScope) -> Result<(), CyberAPIError> {
+    with_cookie_store(scope, |scoped, filename| {
+        scoped.store.clear();
+        save_store(scoped, filename)
+    })
 }
 
-pub fn clear_cookie_from_store() -> Result<(), CyberAPIError> {
-    let mut store = get_cookie_store();
-    store.clear();
-    save_store(store)?;
+// 插入cookie，若已存在则保留其创建时间
+fn insert_cookie(
+    scoped: &mut ScopedStore,
+    c: cookie_store::Cookie<'static>,
+    url: &Url,
+    created_at: Option<String>,
+) -> Result<(), CyberAPIError> {
+    let key = get_store_cookie_key(&c);
+    scoped.store.insert(c, url)?;
+    let accessed_at = now();
+    let times = scoped.times.entry(key).or_default();
+    if let Some(created_at) = created_at {
+        times.created_at = created_at;
+    } else if times.created_at.is_empty() {
+        times.created_at = accessed_at.clone();
+    }
+    times.last_accessed_at = accessed_at;
     Ok(())
 }
 
-pub fn save_cookie_store(set_cookies: Vec<String>, current_url: &Url) -> Result<(), CyberAPIError> {
-    let mut store = get_cookie_store();
-    let now = chrono::Local::now().timestamp();
-    for ele in set_cookies {
-        let c = cookie::Cookie::parse(&ele)?;
-        let mut expired = false;
-        if let Some(expires) = c.expires() {
-            if let Some(expired_time) = expires.datetime() {
-                expired = expired_time.unix_timestamp() < now;
+pub fn save_cookie_store(
+    scope: &CookieScope,
+    set_cookies: Vec<String>,
+    current_url: &Url,
+) -> Result<(), CyberAPIError> {
+    if set_cookies.is_empty() {
+        return Ok(());
+    }
+    with_cookie_store(scope, |scoped, filename| {
+        // 会话cookie不保存至文件，因此仅持久化的cookie有变化时才重新写入
+        let mut changed = false;
+        for ele in set_cookies {
+            let c = cookie_store::Cookie::parse(ele, current_url)?.into_owned();
+            let domain = c.domain.as_cow().unwrap_or_default().to_string();
+            // 已过期（包括Max-Age<=0）的cookie则删除
+            if c.is_expired() {
+                if let Some(removed) = scoped.store.remove(&domain, &c.path, c.name()) {
+                    changed = changed || removed.is_persistent();
+                }
+                continue;
             }
+            changed = changed
+                || match scoped.store.get_any(&domain, &c.path, c.name()) {
+                    Some(current) => {
+                        (current.is_persistent() || c.is_persistent()) && current != &c
+                    }
+                    None => c.is_persistent(),
+                };
+            insert_cookie(scoped, c, current_url, None)?;
         }
-        if expired {
-            store.remove(
-                c.domain().unwrap_or_default(),
-                c.path().unwrap_or_default(),
-                c.name(),
-            );
-        } else {
-            store.parse(ele.as_str(), current_url)?;
+        if !changed {
+            return Ok(());
         }
-    }
+        save_store(scoped, filename)
+    })
+}
 
-    save_store(store)?;
+// 获取所有未过期的cookie，按domain、path与name排序
+pub fn list_cookie(scope: &CookieScope) -> Result<Vec<Cookie>, CyberAPIError> {
+    with_cookie_store(scope, |scoped, _| {
+        let mut result: Vec<Cookie> = scoped
+            .store
+            .iter_unexpired()
+            .map(|c| Cookie::from_store_cookie(c, scoped.times.get(&get_store_cookie_key(c))))
+            .collect();
+        result.sort_by(|a, b| (&a.domain, &a.path, &a.name).cmp(&(&b.domain, &b.path, &b.name)));
+        Ok(result)
+    })
+}
 
-    Ok(())
+fn parse_cookie(c: &Cookie) -> Result<(cookie_store::Cookie<'static>, Url), CyberAPIError> {
+    let url = Url::parse(&c.get_url())?;
+    let cookie = cookie_store::Cookie::parse(c.to_set_cookie_string(), &url)?.into_owned();
+    Ok((cookie, url))
 }
 
-pub fn list_cookie() -> Result<Vec<String>, CyberAPIError> {
-    let store = get_cookie_store();
-    let mut result: Vec<String> = Vec::new();
+pub fn add_cookie(scope: &CookieScope, c: Cookie) -> Result<(), CyberAPIError> {
+    let (cookie, url) = parse_cookie(&c)?;
+    with_cookie_store(scope, |scoped, filename| {
+        insert_cookie(scoped, cookie, &url, None)?;
+        save_store(scoped, filename)
+    })
+}
 
-    for ele in store.iter_any() {
-        let data = serde_json::to_string(ele)?;
-        if !data.is_empty() {
-            result.push(data);
+// 更新cookie，original为更新前的标识，若name、domain或path有修改则删除原cookie
+// 更新后的cookie保留原cookie的创建时间
+pub fn update_cookie(
+    scope: &CookieScope,
+    original: CookieKey,
+    c: Cookie,
+) -> Result<(), CyberAPIError> {
+    let (cookie, url) = parse_cookie(&c)?;
+    with_cookie_store(scope, |scoped, filename| {
+        let original_key = get_cookie_key(&original.domain, &original.path, &original.name);
+        let created_at = scoped
+            .times
+            .get(&original_key)
+            .map(|times| times.created_at.clone())
+            .filter(|value| !value.is_empty());
+        if original_key != get_store_cookie_key(&cookie) {
+            scoped
+                .store
+                .remove(&original.domain, &original.path, &original.name);
+            scoped.times.remove(&original_key);
         }
+        insert_cookie(scoped, cookie, &url, created_at)?;
+        save_store(scoped, filename)
+    })
+}
+
+const NETSCAPE_HEADER: &str = "# Netscape HTTP Cookie File";
+
+// curl等工具以此前缀标记HttpOnly的cookie
+const NETSCAPE_HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+// cookies.txt中无法导入的行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CookieLineError {
+    // 行号，从1开始
+    pub line: usize,
+    pub content: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CookieImportResult {
+    pub imported: usize,
+    // 已过期而忽略的数量
+    pub expired: usize,
+    pub errors: Vec<CookieLineError>,
+}
+
+fn parse_netscape_flag(value: &str) -> Result<bool, String> {
+    match value.to_uppercase().as_str() {
+        "TRUE" => Ok(true),
+        "FALSE" => Ok(false),
+        _ => Err(format!("{} is not TRUE or FALSE", value)),
     }
-    Ok(result)
 }
 
-pub fn add_cookie(c: Cookie) -> Result<(), CyberAPIError> {
-    let mut store = get_cookie_store();
+// 解析cookies.txt的一行，格式为：
+// domain include_subdomains path secure expires name value（以tab分隔）
+fn parse_netscape_line(line: &str) -> Result<Cookie, String> {
+    let (line, http_only) = match line.strip_prefix(NETSCAPE_HTTP_ONLY_PREFIX) {
+        Some(value) => (value, true),
+        None => (line, false),
+    };
+    let fields: Vec<&str> = line.splitn(7, '\t').collect();
+    if fields.len() != 7 {
+        return Err(format!(
+            "expected 7 tab separated fields, got {}",
+            fields.len()
+        ));
+    }
+    let domain = fields[0].trim_start_matches('.');
+    if domain.is_empty() {
+        return Err("domain is empty".to_string());
+    }
+    let include_subdomains = parse_netscape_flag(fields[1])?;
+    let path = fields[2];
+    if !path.starts_with('/') {
+        return Err(format!("path {} should start with /", path));
+    }
+    let secure = parse_netscape_flag(fields[3])?;
+    let expires: i64 = fields[4]
+        .parse()
+        .map_err(|_| format!("expires {} is not a unix timestamp", fields[4]))?;
+    let name = fields[5];
+    if name.is_empty() {
+        return Err("name is empty".to_string());
+    }
+    // 0表示会话cookie
+    let expires = if expires == 0 {
+        "".to_string()
+    } else {
+        chrono::DateTime::from_timestamp(expires, 0)
+            .map(|value| value.to_rfc3339())
+            .ok_or_else(|| format!("expires {} is out of range", expires))?
+    };
+    Ok(Cookie {
+        name: name.to_string(),
+        value: fields[6].trim_end_matches('\r').to_string(),
+        path: path.to_string(),
+        domain: domain.to_string(),
+        expires,
+        secure,
+        http_only,
+        host_only: !include_subdomains,
+        ..Default::default()
+    })
+}
 
-    let url = c.get_url();
-    let request_url = Url::parse(&url)?;
-    let cookie_str = c.to_set_cookie_string();
+// 将cookies.txt（Netscape/Mozilla格式）导入至作用域，无法解析或添加失败的行均返回
+pub fn import_netscape_cookie(
+    scope: &CookieScope,
+    content: &str,
+) -> Result<CookieImportResult, CyberAPIError> {
+    let mut result = CookieImportResult::default();
+    let mut cookies = vec![];
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty()
+            || (trimmed.starts_with('#') && !trimmed.starts_with(NETSCAPE_HTTP_ONLY_PREFIX))
+        {
+            continue;
+        }
+        let cookie =
+            parse_netscape_line(line).and_then(|c| parse_cookie(&c).map_err(|err| err.message()));
+        match cookie {
+            Ok(value) => cookies.push(value),
+            Err(message) => result.errors.push(CookieLineError {
+                line: index + 1,
+                content: line.to_string(),
+                message,
+            }),
+        }
+    }
+    with_cookie_store(scope, |scoped, filename| {
+        for (cookie, url) in cookies {
+            if cookie.is_expired() {
+                result.expired += 1;
+                continue;
+            }
+            insert_cookie(scoped, cookie, &url, None)?;
+            result.imported += 1;
+        }
+        save_store(scoped, filename)
+    })?;
+    Ok(result)
+}
 
-    store.parse(&cookie_str, &request_url)?;
+pub fn import_cookie_file(
+    scope: &CookieScope,
+    file: &str,
+) -> Result<CookieImportResult, CyberAPIError> {
+    let content = fs::read_to_string(file)?;
+    import_netscape_cookie(scope, &content)
+}
 
-    save_store(store)?;
+// 导出为cookies.txt格式，domain不为空时仅导出该域名（包括子域名）的cookie
+pub fn export_netscape_cookie(scope: &CookieScope, domain: &str) -> Result<String, CyberAPIError> {
+    let domain = domain.trim_start_matches('.').to_lowercase();
+    let suffix = format!(".{}", domain);
+    let mut arr = vec![NETSCAPE_HEADER.to_string(), "".to_string()];
+    for c in list_cookie(scope)? {
+        if !domain.is_empty() && c.domain != domain && !c.domain.ends_with(&suffix) {
+            continue;
+        }
+        let expires = chrono::DateTime::parse_from_rfc3339(&c.expires)
+            .map(|value| value.timestamp())
+            .unwrap_or_default();
+        let flag = |value: bool| if value { "TRUE" } else { "FALSE" };
+        let mut cookie_domain = if c.host_only {
+            c.domain.clone()
+        } else {
+            format!(".{}", c.domain)
+        };
+        if c.http_only {
+            cookie_domain = format!("{}{}", NETSCAPE_HTTP_ONLY_PREFIX, cookie_domain);
+        }
+        arr.push(
+            [
+                cookie_domain,
+                flag(!c.host_only).to_string(),
+                c.path,
+                flag(c.secure).to_string(),
+                expires.to_string(),
+                c.name,
+                c.value,
+            ]
+            .join("\t"),
+        );
+    }
+    arr.push("".to_string());
+    Ok(arr.join("\n"))
+}
 
+pub fn export_cookie_file(
+    scope: &CookieScope,
+    file: &str,
+    domain: &str,
+) -> Result<(), CyberAPIError> {
+    let content = export_netscape_cookie(scope, domain)?;
+    fs::write(file, content)?;
     Ok(())
 }
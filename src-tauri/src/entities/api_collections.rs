@@ -10,11 +10,54 @@ pub struct Model {
     pub id: String,
     pub name: Option<String>,
     pub description: Option<String>,
+    pub setting: Option<String>,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
+    pub deleted_at: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::api_folders::Entity")]
+    ApiFolders,
+    #[sea_orm(has_many = "super::api_settings::Entity")]
+    ApiSettings,
+    #[sea_orm(has_many = "super::environments::Entity")]
+    Environments,
+    #[sea_orm(has_many = "super::file_stores::Entity")]
+    FileStores,
+    #[sea_orm(has_many = "super::variables::Entity")]
+    Variables,
+}
+
+impl Related<super::api_folders::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ApiFolders.def()
+    }
+}
+
+impl Related<super::api_settings::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ApiSettings.def()
+    }
+}
+
+impl Related<super::environments::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Environments.def()
+    }
+}
+
+impl Related<super::file_stores::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::FileStores.def()
+    }
+}
+
+impl Related<super::variables::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Variables.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
@@ -14,9 +14,41 @@ pub struct Model {
     pub setting: Option<String>,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
+    pub deleted_at: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::api_collections::Entity",
+        from = "Column::Collection",
+        to = "super::api_collections::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    ApiCollections,
+    #[sea_orm(has_many = "super::api_examples::Entity")]
+    ApiExamples,
+    #[sea_orm(has_many = "super::api_setting_revisions::Entity")]
+    ApiSettingRevisions,
+}
+
+impl Related<super::api_collections::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ApiCollections.def()
+    }
+}
+
+impl Related<super::api_examples::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ApiExamples.def()
+    }
+}
+
+impl Related<super::api_setting_revisions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ApiSettingRevisions.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
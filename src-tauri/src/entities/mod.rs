@@ -3,7 +3,14 @@
 pub mod prelude;
 
 pub mod api_collections;
+pub mod api_examples;
 pub mod api_folders;
+pub mod api_setting_revisions;
 pub mod api_settings;
+pub mod environments;
+pub mod file_stores;
+pub mod global_variables;
+pub mod oauth_tokens;
+pub mod secret_keys;
 pub mod variables;
 pub mod versions;
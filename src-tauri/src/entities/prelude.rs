@@ -1,7 +1,14 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.2
 
 pub use super::api_collections::Entity as ApiCollections;
+pub use super::api_examples::Entity as ApiExamples;
 pub use super::api_folders::Entity as ApiFolders;
+pub use super::api_setting_revisions::Entity as ApiSettingRevisions;
 pub use super::api_settings::Entity as ApiSettings;
+pub use super::environments::Entity as Environments;
+pub use super::file_stores::Entity as FileStores;
+pub use super::global_variables::Entity as GlobalVariables;
+pub use super::oauth_tokens::Entity as OauthTokens;
+pub use super::secret_keys::Entity as SecretKeys;
 pub use super::variables::Entity as Variables;
 pub use super::versions::Entity as Versions;
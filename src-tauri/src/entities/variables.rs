@@ -10,14 +10,31 @@ pub struct Model {
     pub id: String,
     pub category: String,
     pub collection: String,
+    pub environment: Option<String>,
     pub name: Option<String>,
     pub value: Option<String>,
     pub enabled: Option<String>,
+    pub secret: Option<String>,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::api_collections::Entity",
+        from = "Column::Collection",
+        to = "super::api_collections::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    ApiCollections,
+}
+
+impl Related<super::api_collections::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ApiCollections.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
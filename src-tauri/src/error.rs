@@ -1,137 +1,329 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::io::ErrorKind;
 use zip::result::ZipError;
 
-#[derive(Debug, Clone, Serialize)]
+// 出错的代码，序列化后的值保持稳定，前端可根据其区分处理
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    // 域名解析失败
+    Dns,
+    // 连接被拒绝
+    ConnectRefused,
+    // 连接超时
+    ConnectTimeout,
+    // 其它连接出错
+    Connect,
+    // 读取（或写入）超时
+    ReadTimeout,
+    // 连接被关闭或重置
+    ConnectionClosed,
+    // 证书无效（如自签名、过期或域名不匹配）
+    TlsCertInvalid,
+    // 其它TLS握手出错
+    Tls,
+    // 响应不符合HTTP协议
+    Protocol,
+    // 响应数据解压失败
+    BodyDecode,
+    InvalidRequest,
+    InvalidUri,
+    InvalidHeader,
+    Template,
+    Cookie,
+    // OAuth获取token失败
+    #[serde(rename = "oauth")]
+    OAuth,
+    Database,
+    Io,
+    Serialization,
+    #[default]
+    Unknown,
+}
+
+// 请求出错时所处的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestPhase {
+    // 生成请求（替换变量、设置header等）
+    Prepare,
+    Dns,
+    Connect,
+    Tls,
+    // 发送请求并等待响应
+    Send,
+    // 读取响应数据
+    Receive,
+    // 解压响应数据
+    Decode,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CyberAPIError {
     message: String,
     category: String,
+    #[serde(default)]
+    code: ErrorCode,
+    // 底层的出错信息，由外至内
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    causes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    phase: Option<RequestPhase>,
+    // 是否可重试（如超时、连接被重置等临时性出错）
+    #[serde(default)]
+    retryable: bool,
+    // 结构化的出错信息，如未定义的变量列表
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    details: Option<serde_json::Value>,
 }
 
-impl From<sea_orm::DbErr> for CyberAPIError {
-    fn from(error: sea_orm::DbErr) -> Self {
+impl CyberAPIError {
+    pub fn new(message: String, category: &str) -> Self {
         CyberAPIError {
-            message: error.to_string(),
-            category: "seaOrm".to_string(),
+            message,
+            category: category.to_string(),
+            code: ErrorCode::Unknown,
+            causes: vec![],
+            phase: None,
+            retryable: false,
+            details: None,
         }
     }
+    pub fn with_code(mut self, code: ErrorCode) -> Self {
+        self.code = code;
+        self
+    }
+    pub fn with_phase(mut self, phase: RequestPhase) -> Self {
+        self.phase = Some(phase);
+        self
+    }
+    // 仅在未确定阶段时设置
+    pub fn or_phase(mut self, phase: RequestPhase) -> Self {
+        self.phase = self.phase.or(Some(phase));
+        self
+    }
+    pub fn with_retryable(mut self, retryable: bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+    // 记录底层的出错信息
+    pub fn with_causes(mut self, error: &dyn Error) -> Self {
+        let mut source = error.source();
+        while let Some(err) = source {
+            self.causes.push(err.to_string());
+            source = err.source();
+        }
+        self
+    }
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+    pub fn category(&self) -> String {
+        self.category.clone()
+    }
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+    pub fn phase(&self) -> Option<RequestPhase> {
+        self.phase
+    }
+    pub fn retryable(&self) -> bool {
+        self.retryable
+    }
 }
-impl From<hyper::http::Error> for CyberAPIError {
-    fn from(error: hyper::http::Error) -> Self {
-        CyberAPIError {
-            message: error.to_string(),
-            category: "http".to_string(),
+
+// 查找出错链中最底层的io::Error
+// hyper-rustls会将握手出错的io::Error再次包装，而io::Error的source()会跳过被包装的io::Error
+fn find_io_error<'a>(error: &'a (dyn Error + 'static)) -> Option<&'a std::io::Error> {
+    let mut source = Some(error);
+    let mut found = None;
+    while let Some(err) = source {
+        if let Some(io_err) = err.downcast_ref::<std::io::Error>() {
+            let mut io_err = io_err;
+            while let Some(inner) = io_err
+                .get_ref()
+                .and_then(|inner| inner.downcast_ref::<std::io::Error>())
+            {
+                io_err = inner;
+            }
+            found = Some(io_err);
+        }
+        source = err.source();
+    }
+    found
+}
+
+// 根据hyper的出错类型与底层的io::Error区分出错代码与阶段
+fn classify_hyper_error(error: &hyper::Error) -> (ErrorCode, Option<RequestPhase>, bool) {
+    let io_kind = find_io_error(error).map(|err| err.kind());
+    let text = error.to_string().to_lowercase();
+    if error.is_connect() {
+        // HttpConnector解析域名失败时为dns error
+        if text.contains("dns error") {
+            return (ErrorCode::Dns, Some(RequestPhase::Dns), false);
         }
+        if text.contains("certificate") {
+            return (ErrorCode::TlsCertInvalid, Some(RequestPhase::Tls), false);
+        }
+        return match io_kind {
+            Some(ErrorKind::ConnectionRefused) => {
+                (ErrorCode::ConnectRefused, Some(RequestPhase::Connect), true)
+            }
+            Some(ErrorKind::TimedOut) => {
+                (ErrorCode::ConnectTimeout, Some(RequestPhase::Connect), true)
+            }
+            // tokio-rustls握手出错时为InvalidData或UnexpectedEof
+            Some(ErrorKind::InvalidData) | Some(ErrorKind::UnexpectedEof) => {
+                (ErrorCode::Tls, Some(RequestPhase::Tls), false)
+            }
+            _ => (ErrorCode::Connect, Some(RequestPhase::Connect), true),
+        };
+    }
+    // 连接后的超时，写入超时较少出现，统一为读取超时
+    if error.is_timeout() || io_kind == Some(ErrorKind::TimedOut) {
+        return (ErrorCode::ReadTimeout, None, true);
+    }
+    if error.is_incomplete_message()
+        || error.is_closed()
+        || matches!(
+            io_kind,
+            Some(ErrorKind::ConnectionReset) | Some(ErrorKind::ConnectionAborted)
+        )
+    {
+        return (ErrorCode::ConnectionClosed, None, true);
     }
+    if error.is_parse() {
+        return (ErrorCode::Protocol, None, false);
+    }
+    (ErrorCode::Unknown, None, false)
 }
+
 impl From<hyper::Error> for CyberAPIError {
     fn from(error: hyper::Error) -> Self {
-        CyberAPIError {
-            message: error.to_string(),
-            category: "http".to_string(),
-        }
+        let (code, phase, retryable) = classify_hyper_error(&error);
+        let mut err = CyberAPIError::new(error.to_string(), "http")
+            .with_code(code)
+            .with_retryable(retryable)
+            .with_causes(&error);
+        err.phase = phase;
+        err
+    }
+}
+
+impl From<sea_orm::DbErr> for CyberAPIError {
+    fn from(error: sea_orm::DbErr) -> Self {
+        CyberAPIError::new(error.to_string(), "seaOrm").with_code(ErrorCode::Database)
     }
 }
+
+impl From<hyper::http::Error> for CyberAPIError {
+    fn from(error: hyper::http::Error) -> Self {
+        CyberAPIError::new(error.to_string(), "http").with_code(ErrorCode::InvalidRequest)
+    }
+}
+
 impl From<tauri::http::InvalidUri> for CyberAPIError {
     fn from(error: tauri::http::InvalidUri) -> Self {
-        CyberAPIError {
-            message: error.to_string(),
-            category: "invalidUri".to_string(),
-        }
+        CyberAPIError::new(error.to_string(), "invalidUri").with_code(ErrorCode::InvalidUri)
     }
 }
+
 impl From<hyper::header::InvalidHeaderValue> for CyberAPIError {
     fn from(error: hyper::header::InvalidHeaderValue) -> Self {
-        CyberAPIError {
-            message: error.to_string(),
-            category: "invalidHeader".to_string(),
-        }
+        CyberAPIError::new(error.to_string(), "invalidHeader").with_code(ErrorCode::InvalidHeader)
     }
 }
 
 impl From<hyper::header::InvalidHeaderName> for CyberAPIError {
     fn from(error: hyper::header::InvalidHeaderName) -> Self {
-        CyberAPIError {
-            message: error.to_string(),
-            category: "invalidHeaderName".to_string(),
-        }
+        CyberAPIError::new(error.to_string(), "invalidHeaderName")
+            .with_code(ErrorCode::InvalidHeader)
     }
 }
 
 impl From<hyper::header::ToStrError> for CyberAPIError {
     fn from(error: hyper::header::ToStrError) -> Self {
-        CyberAPIError {
-            message: error.to_string(),
-            category: "toStrError".to_string(),
-        }
+        CyberAPIError::new(error.to_string(), "toStrError").with_code(ErrorCode::InvalidHeader)
+    }
+}
+
+impl From<hyper::http::status::InvalidStatusCode> for CyberAPIError {
+    fn from(error: hyper::http::status::InvalidStatusCode) -> Self {
+        CyberAPIError::new(error.to_string(), "invalidStatusCode")
+            .with_code(ErrorCode::InvalidRequest)
     }
 }
 
 impl From<std::io::Error> for CyberAPIError {
     fn from(error: std::io::Error) -> Self {
-        CyberAPIError {
-            message: error.to_string(),
-            category: "io".to_string(),
-        }
+        CyberAPIError::new(error.to_string(), "io").with_code(ErrorCode::Io)
     }
 }
+
 impl From<cookie_store::Error> for CyberAPIError {
     fn from(error: cookie_store::Error) -> Self {
-        CyberAPIError {
-            message: error.to_string(),
-            category: "cookieStore".to_string(),
-        }
+        CyberAPIError::new(error.to_string(), "cookieStore").with_code(ErrorCode::Cookie)
     }
 }
 
 impl From<url::ParseError> for CyberAPIError {
     fn from(error: url::ParseError) -> Self {
-        CyberAPIError {
-            message: error.to_string(),
-            category: "urlParse".to_string(),
-        }
+        CyberAPIError::new(error.to_string(), "urlParse").with_code(ErrorCode::InvalidUri)
     }
 }
 
 impl From<cookie_store::CookieError> for CyberAPIError {
     fn from(error: cookie_store::CookieError) -> Self {
-        CyberAPIError {
-            message: error.to_string(),
-            category: "cookieStore".to_string(),
-        }
+        CyberAPIError::new(error.to_string(), "cookieStore").with_code(ErrorCode::Cookie)
     }
 }
 
 impl From<serde_json::Error> for CyberAPIError {
     fn from(error: serde_json::Error) -> Self {
-        CyberAPIError {
-            message: error.to_string(),
-            category: "serdeJson".to_string(),
-        }
+        CyberAPIError::new(error.to_string(), "serdeJson").with_code(ErrorCode::Serialization)
+    }
+}
+
+impl From<csv::Error> for CyberAPIError {
+    fn from(error: csv::Error) -> Self {
+        CyberAPIError::new(error.to_string(), "csv").with_code(ErrorCode::Serialization)
     }
 }
 
 impl From<base64::DecodeError> for CyberAPIError {
     fn from(error: base64::DecodeError) -> Self {
-        CyberAPIError {
-            message: error.to_string(),
-            category: "base64".to_string(),
-        }
+        CyberAPIError::new(error.to_string(), "base64").with_code(ErrorCode::Serialization)
     }
 }
 
 impl From<ZipError> for CyberAPIError {
     fn from(error: ZipError) -> Self {
-        CyberAPIError {
-            message: error.to_string(),
-            category: "zip".to_string(),
-        }
+        CyberAPIError::new(error.to_string(), "zip").with_code(ErrorCode::Io)
+    }
+}
+
+impl From<notify::Error> for CyberAPIError {
+    fn from(error: notify::Error) -> Self {
+        CyberAPIError::new(error.to_string(), "notify").with_code(ErrorCode::Io)
     }
 }
+
 impl From<cookie::ParseError> for CyberAPIError {
     fn from(error: cookie::ParseError) -> Self {
+        CyberAPIError::new(error.to_string(), "cookie").with_code(ErrorCode::Cookie)
+    }
+}
+
+impl From<crate::template::TemplateError> for CyberAPIError {
+    fn from(error: crate::template::TemplateError) -> Self {
         CyberAPIError {
-            message: error.to_string(),
-            category: "cookie".to_string(),
+            details: serde_json::to_value(&error).ok(),
+            ..CyberAPIError::new(error.to_string(), "template").with_code(ErrorCode::Template)
         }
     }
 }
@@ -0,0 +1,156 @@
+use jsonpath_rust::JsonPathInst;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::schemas::{self, SECRET_MASK};
+use crate::template::VariableScope;
+
+// 提取值的来源
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ExtractionSource {
+    // 响应数据的JSONPath，如$.data.token
+    JsonPath,
+    // 响应头
+    Header,
+    // 响应设置的cookie
+    Cookie,
+    // 响应数据的正则匹配，有分组时取第一个分组
+    Regex,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+// 请求完成后从响应中提取值并保存为变量，保存在API配置的extractions字段
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractionRule {
+    pub source: ExtractionSource,
+    // JSONPath、响应头名称、cookie名称或正则表达式
+    pub expression: String,
+    // 保存的变量名称
+    pub name: String,
+    // 保存的作用域，local则仅返回不保存
+    pub scope: VariableScope,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractionResult {
+    // 变量名称
+    pub name: String,
+    pub scope: VariableScope,
+    // 提取的值，失败时为null
+    pub value: Option<String>,
+    // 失败时的出错信息
+    pub message: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct ExtractionSetting {
+    #[serde(default)]
+    extractions: Vec<ExtractionRule>,
+}
+
+// 从API配置中获取启用的提取规则，配置无法解析时忽略
+pub fn get_extraction_rules(setting: &str) -> Vec<ExtractionRule> {
+    serde_json::from_str::<ExtractionSetting>(setting)
+        .unwrap_or_default()
+        .extractions
+        .into_iter()
+        .filter(|item| item.enabled && !item.name.is_empty())
+        .collect()
+}
+
+// 字符串直接返回，其它类型则转换为json
+pub fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        _ => value.to_string(),
+    }
+}
+
+// 获取响应数据中JSONPath匹配的第一个值
+pub fn find_json_path(body: &[u8], expression: &str) -> Result<Option<Value>, String> {
+    let path = JsonPathInst::from_str(expression)?;
+    let data: Value = serde_json::from_slice(body).map_err(|err| err.to_string())?;
+    let values = path.find_slice(&data);
+    Ok(values.first().map(|value| (**value).clone()))
+}
+
+// headers的key为小写
+pub fn extract(
+    rule: &ExtractionRule,
+    headers: &HashMap<String, Vec<String>>,
+    body: &[u8],
+) -> Result<String, String> {
+    let not_found = || format!("{} is not found", rule.expression);
+    match rule.source {
+        ExtractionSource::JsonPath => find_json_path(body, &rule.expression)?
+            .map(|value| value_to_string(&value))
+            .ok_or_else(not_found),
+        ExtractionSource::Header => headers
+            .get(&rule.expression.to_lowercase())
+            .and_then(|values| values.first())
+            .cloned()
+            .ok_or_else(not_found),
+        ExtractionSource::Cookie => headers
+            .get("set-cookie")
+            .into_iter()
+            .flatten()
+            .filter_map(|value| cookie::Cookie::parse(value.as_str()).ok())
+            .find(|item| item.name() == rule.expression)
+            .map(|item| item.value().to_string())
+            .ok_or_else(not_found),
+        ExtractionSource::Regex => {
+            let reg = Regex::new(&rule.expression).map_err(|err| err.to_string())?;
+            let text = String::from_utf8_lossy(body);
+            let captures = reg.captures(&text).ok_or_else(not_found)?;
+            captures
+                .get(1)
+                .or_else(|| captures.get(0))
+                .map(|item| item.as_str().to_string())
+                .ok_or_else(not_found)
+        }
+    }
+}
+
+// 提取并保存至对应的变量，单个规则失败不影响其它规则
+pub async fn apply_extractions(
+    collection: &str,
+    rules: &[ExtractionRule],
+    headers: &HashMap<String, Vec<String>>,
+    body: &[u8],
+) -> Vec<ExtractionResult> {
+    let mut result = vec![];
+    for rule in rules {
+        let mut item = ExtractionResult {
+            name: rule.name.clone(),
+            scope: rule.scope,
+            value: None,
+            message: "".to_string(),
+        };
+        match extract(rule, headers, body) {
+            Ok(value) => {
+                match schemas::save_extracted_variable(collection, rule.scope, &rule.name, &value)
+                    .await
+                {
+                    // 加密变量的值以掩码返回
+                    Ok(true) => item.value = Some(SECRET_MASK.to_string()),
+                    Ok(false) => item.value = Some(value),
+                    Err(err) => item.message = err.to_string(),
+                }
+            }
+            Err(message) => item.message = message,
+        }
+        result.push(item);
+    }
+    result
+}
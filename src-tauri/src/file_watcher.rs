@@ -0,0 +1,137 @@
+use crate::error::CyberAPIError;
+use crate::schemas;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::sync::broadcast;
+
+pub const FILE_STORE_CHANGED_EVENT: &str = "file-store-changed";
+
+// 外部修改（如git checkout）会连续触发多个事件，无新事件后再同步
+const DEBOUNCE_DURATION: Duration = Duration::from_millis(500);
+
+static WATCHERS: Lazy<Mutex<HashMap<String, RecommendedWatcher>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileStoreSyncResult {
+    // collection ID
+    pub collection: String,
+    // 是否有外部修改
+    pub changed: bool,
+    // 同步失败时的出错信息
+    pub message: String,
+}
+
+async fn sync(collection: &str) -> FileStoreSyncResult {
+    match schemas::sync_file_store(collection).await {
+        Ok(changed) => FileStoreSyncResult {
+            collection: collection.to_string(),
+            changed,
+            message: "".to_string(),
+        },
+        Err(err) => FileStoreSyncResult {
+            collection: collection.to_string(),
+            changed: false,
+            message: err.to_string(),
+        },
+    }
+}
+
+// 隐藏目录（如.git）中的变化无需处理
+fn is_hidden(root: &Path, path: &Path) -> bool {
+    match path.strip_prefix(root) {
+        Ok(path) => path
+            .components()
+            .any(|item| item.as_os_str().to_string_lossy().starts_with('.')),
+        Err(_) => true,
+    }
+}
+
+// 监听collection目录，有外部修改时同步至数据库并通知前端
+pub fn watch(app: AppHandle, collection: String, dir: String) -> Result<(), CyberAPIError> {
+    let (tx, rx) = mpsc::channel();
+    let root = PathBuf::from(&dir);
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        if let Ok(event) = res {
+            if event.paths.iter().any(|path| !is_hidden(&root, path)) {
+                let _ = tx.send(());
+            }
+        }
+    })?;
+    watcher.watch(Path::new(&dir), RecursiveMode::Recursive)?;
+
+    let name = collection.clone();
+    std::thread::spawn(move || {
+        // watcher被删除时，channel关闭则退出
+        while rx.recv().is_ok() {
+            loop {
+                match rx.recv_timeout(DEBOUNCE_DURATION) {
+                    Ok(_) => continue,
+                    Err(mpsc::RecvTimeoutError::Timeout) => break,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                }
+            }
+            let result = tauri::async_runtime::block_on(sync(&name));
+            // 自身写入的文件也会触发事件，无变化则不通知
+            if result.changed || !result.message.is_empty() {
+                let _ = app.emit_all(FILE_STORE_CHANGED_EVENT, result);
+            }
+        }
+    });
+    WATCHERS.lock().unwrap().insert(collection, watcher);
+    Ok(())
+}
+
+// 数据保存后同步目录失败时，以目录修改的事件通知前端展示出错信息
+pub fn forward_sync_failures(app: AppHandle) {
+    let mut rx = schemas::subscribe_file_store_failures();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(failure) => {
+                    let _ = app.emit_all(
+                        FILE_STORE_CHANGED_EVENT,
+                        FileStoreSyncResult {
+                            collection: failure.collection,
+                            changed: false,
+                            message: failure.message,
+                        },
+                    );
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+pub fn unwatch(collection: &str) {
+    WATCHERS.lock().unwrap().remove(collection);
+}
+
+// 切换工作区时停止所有监听
+pub fn unwatch_all() {
+    WATCHERS.lock().unwrap().clear();
+}
+
+// 同步所有collection目录（应用关闭期间的修改）并开始监听
+pub async fn watch_all(app: AppHandle) -> Result<Vec<FileStoreSyncResult>, CyberAPIError> {
+    let mut result = vec![];
+    for store in schemas::list_file_store().await? {
+        let mut item = sync(&store.collection).await;
+        if item.message.is_empty() {
+            if let Err(err) = watch(app.clone(), store.collection, store.dir) {
+                item.message = err.message();
+            }
+        }
+        result.push(item);
+    }
+    Ok(result)
+}
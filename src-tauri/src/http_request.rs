@@ -1,5 +1,12 @@
-use crate::cookies;
-use crate::error::CyberAPIError;
+use crate::assertion::{self, AssertionResponse, AssertionResult};
+use crate::auth::{self, AuthState, HTTPAuth};
+use crate::cookies::{self, CookieJar, CookieScope};
+use crate::error::{CyberAPIError, ErrorCode, RequestPhase};
+use crate::extraction::{self, ExtractionResult};
+use crate::oauth::{self, TokenScope};
+use crate::retry::{self, RetryAttempt, RetryPolicy};
+use crate::schemas;
+use crate::template::{TemplateContext, TemplateError};
 use base64::{engine::general_purpose, Engine as _};
 use hyper::{
     body::{Buf, Bytes},
@@ -11,16 +18,18 @@ use hyper::{
 use hyper_rustls::HttpsConnectorBuilder;
 use hyper_timeout::TimeoutConnector;
 use libflate::gzip::Decoder;
-use once_cell::sync::OnceCell;
-use tracing_subscriber::Layer;
+use once_cell::sync::Lazy;
+use tracing::Instrument;
+use tracing_subscriber::{layer::Context, Layer};
 
 use serde::{Deserialize, Serialize};
 use std::{
     collections::BTreeMap,
     collections::HashMap,
+    future::Future,
     io::Read,
     sync::atomic::{AtomicBool, AtomicU64, Ordering},
-    sync::Mutex,
+    sync::{Arc, Mutex},
     time::Duration,
     vec,
 };
@@ -43,6 +52,55 @@ pub struct HTTPRequest {
     pub content_type: String,
     pub headers: Vec<HTTPRequestKVParam>,
     pub query: Vec<HTTPRequestKVParam>,
+    // 仅用于该请求的变量，优先级最高
+    #[serde(default)]
+    pub variables: Vec<HTTPRequestKVParam>,
+    // 使用的cookie，默认为collection共用
+    #[serde(default)]
+    pub cookie_jar: CookieJar,
+    // 由API配置与cookie_jar确定的cookie作用域
+    #[serde(skip)]
+    pub cookie_scope: CookieScope,
+    // 重试策略，未设置则使用collection的配置
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    // 认证方式，在url与body确定后生成认证的请求头
+    #[serde(default)]
+    pub auth: Option<HTTPAuth>,
+    // OAuth token缓存的作用域，由API配置与当前环境确定
+    #[serde(skip)]
+    pub token_scope: TokenScope,
+}
+
+impl HTTPRequest {
+    // 替换请求中的变量占位符，所有未定义的变量一并返回
+    pub fn render(mut self, context: &TemplateContext) -> Result<Self, TemplateError> {
+        let mut renderer = context.renderer();
+        self.uri = renderer.render("uri", &self.uri)?;
+        for (name, params) in [("query", &mut self.query), ("headers", &mut self.headers)] {
+            for item in params.iter_mut().filter(|item| item.enabled) {
+                let field = format!("{}.{}", name, item.key);
+                item.key = renderer.render(&field, &item.key)?;
+                item.value = renderer.render(&field, &item.value)?;
+            }
+        }
+        // multipart的数据为base64，不处理
+        if !self.content_type.starts_with("multipart/form-data") {
+            self.body = renderer.render("body", &self.body)?;
+        }
+        if let Some(auth) = self.auth.take() {
+            self.auth = Some(auth.render(&mut renderer)?);
+        }
+        renderer.finish()?;
+        Ok(self)
+    }
+    fn list_local_variables(&self) -> Vec<(String, String)> {
+        self.variables
+            .iter()
+            .filter(|item| item.enabled)
+            .map(|item| (item.key.clone(), item.value.clone()))
+            .collect()
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -131,23 +189,6 @@ impl HTTPTrace {
             ..Default::default()
         }
     }
-    fn reset(&self) {
-        self.set_cipher("".to_string());
-        self.is_tls_value.store(false, Ordering::Relaxed);
-        self.start_value.store(0, Ordering::Relaxed);
-        self.get_conn_value.store(0, Ordering::Relaxed);
-        self.dns_start_value.store(0, Ordering::Relaxed);
-        self.dns_done_value.store(0, Ordering::Relaxed);
-        self.tcp_start_value.store(0, Ordering::Relaxed);
-        self.tcp_done_value.store(0, Ordering::Relaxed);
-        self.tls_start_value.store(0, Ordering::Relaxed);
-        self.tls_done_value.store(0, Ordering::Relaxed);
-        self.http_start_value.store(0, Ordering::Relaxed);
-        self.written_value.store(0, Ordering::Relaxed);
-        self.got_first_response_byte_value
-            .store(0, Ordering::Relaxed);
-        self.done_value.store(0, Ordering::Relaxed);
-    }
     fn set_cipher(&self, value: String) {
         if let Ok(mut cipher) = self.cipher_value.lock() {
             *cipher = value;
@@ -278,10 +319,39 @@ impl HTTPTrace {
     }
 }
 
-static HTTP_TRACE: OnceCell<HTTPTrace> = OnceCell::new();
+const HTTP_TRACE_SPAN: &str = "http_trace";
+static HTTP_TRACE_ID: AtomicU64 = AtomicU64::new(0);
+// 等待关联至span的trace，创建span后即移除
+static PENDING_HTTP_TRACES: Lazy<Mutex<HashMap<u64, Arc<HTTPTrace>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// 保存在span的extensions中
+struct HTTPTraceExtension(Arc<HTTPTrace>);
+
+// 每个请求使用单独的trace，请求中的事件通过所在的span找到对应的trace
+fn new_trace_span(trace: &Arc<HTTPTrace>) -> tracing::Span {
+    let id = HTTP_TRACE_ID.fetch_add(1, Ordering::Relaxed);
+    PENDING_HTTP_TRACES
+        .lock()
+        .unwrap()
+        .insert(id, trace.clone());
+    let span = tracing::info_span!(HTTP_TRACE_SPAN, trace_id = id);
+    PENDING_HTTP_TRACES.lock().unwrap().remove(&id);
+    span
+}
+
+// hyper在单独的任务中处理连接，使其关联当前请求的span
+#[derive(Clone)]
+struct TraceExecutor;
 
-fn get_http_trace() -> &'static HTTPTrace {
-    HTTP_TRACE.get_or_init(HTTPTrace::new)
+impl<F> hyper::rt::Executor<F> for TraceExecutor
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    fn execute(&self, fut: F) {
+        tokio::spawn(fut.in_current_span());
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -294,6 +364,15 @@ pub struct HTTPResponse {
     pub body: String,
     pub stats: HTTPStats,
     pub body_size: u32,
+    // 从响应中提取并保存的变量
+    #[serde(default)]
+    pub extractions: Vec<ExtractionResult>,
+    // 断言的结果
+    #[serde(default)]
+    pub assertions: Vec<AssertionResult>,
+    // 按重试策略发送时每次请求的结果
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attempts: Vec<RetryAttempt>,
 }
 
 struct JsonVisitor<'a>(&'a mut BTreeMap<String, String>);
@@ -305,6 +384,17 @@ impl<'a> tracing::field::Visit for JsonVisitor<'a> {
     }
 }
 
+struct TraceIdVisitor(Option<u64>);
+
+impl tracing::field::Visit for TraceIdVisitor {
+    fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+        if field.name() == "trace_id" {
+            self.0 = Some(value);
+        }
+    }
+    fn record_debug(&mut self, _: &tracing::field::Field, _: &dyn std::fmt::Debug) {}
+}
+
 pub struct HTTPTraceLayer;
 impl<S> Layer<S> for HTTPTraceLayer
 where
@@ -312,8 +402,37 @@ where
     // Scary! But there's no need to even understand it. We just need it.
     S: for<'lookup> tracing_subscriber::registry::LookupSpan<'lookup>,
 {
-    fn on_event(&self, event: &tracing::Event<'_>, _: tracing_subscriber::layer::Context<'_, S>) {
-        let trace = get_http_trace();
+    fn on_new_span(
+        &self,
+        attrs: &tracing::span::Attributes<'_>,
+        id: &tracing::Id,
+        ctx: Context<'_, S>,
+    ) {
+        if attrs.metadata().name() != HTTP_TRACE_SPAN {
+            return;
+        }
+        let mut visitor = TraceIdVisitor(None);
+        attrs.record(&mut visitor);
+        let trace = visitor
+            .0
+            .and_then(|trace_id| PENDING_HTTP_TRACES.lock().unwrap().get(&trace_id).cloned());
+        if let (Some(trace), Some(span)) = (trace, ctx.span(id)) {
+            span.extensions_mut().insert(HTTPTraceExtension(trace));
+        }
+    }
+    fn on_event(&self, event: &tracing::Event<'_>, ctx: Context<'_, S>) {
+        // 不在请求的span中的事件忽略
+        let trace = ctx.event_span(event).and_then(|span| {
+            span.scope().find_map(|item| {
+                item.extensions()
+                    .get::<HTTPTraceExtension>()
+                    .map(|ext| ext.0.clone())
+            })
+        });
+        let trace = match trace {
+            Some(trace) => trace,
+            None => return,
+        };
         let target = event.metadata().target();
         if !target.starts_with("hyper::") && !trace.is_tls() {
             return;
@@ -400,25 +519,82 @@ where
     }
 }
 
-pub async fn request(
-    api: String,
-    http_request: HTTPRequest,
-    timeout: RequestTimeout,
-) -> Result<HTTPResponse, CyberAPIError> {
-    // 暂时使用单个实例，后续调整
-    // 如果多个请求并发，有可能数据不精确，暂时忽略
-    let trace = get_http_trace();
-    trace.reset();
+// 发送请求后的原始响应，数据已解压
+pub struct HTTPRawResponse {
+    pub url: Url,
+    pub status: u16,
+    // key为小写
+    pub headers: HashMap<String, Vec<String>>,
+    pub set_cookies: Vec<String>,
+    pub body: Bytes,
+    // 解压前的数据长度
+    pub body_size: usize,
+    pub stats: HTTPStats,
+}
+
+// 替换请求中的变量，返回API配置（未保存的请求为None）与替换后的请求
+pub async fn resolve_request(
+    api: &str,
+    mut http_request: HTTPRequest,
+) -> Result<(Option<schemas::APISetting>, HTTPRequest), CyberAPIError> {
+    let setting = schemas::find_api_setting(api.to_string()).await?;
+    let collection = setting.as_ref().map(|item| item.collection.clone());
+    let context =
+        schemas::get_template_context(collection.clone(), http_request.list_local_variables())
+            .await?;
+    // 加密的全局请求头不在界面中解密，由此添加
+    if let Some(collection) = &collection {
+        for (key, value) in schemas::list_global_req_headers(collection, true).await? {
+            http_request.headers.push(HTTPRequestKVParam {
+                key,
+                value,
+                enabled: true,
+            });
+        }
+    }
+    let mut http_request = http_request.render(&context)?;
+    // 未保存的请求使用工作区共用的cookie
+    if let Some(collection) = collection {
+        let is_oauth = matches!(http_request.auth, Some(HTTPAuth::OAuth2(_)));
+        let mut active_environment = "".to_string();
+        if http_request.cookie_jar == CookieJar::Environment || is_oauth {
+            if let Some(item) = schemas::get_active_environment(collection.clone()).await? {
+                active_environment = item.id;
+            }
+        }
+        let mut environment = "".to_string();
+        if http_request.cookie_jar == CookieJar::Environment {
+            environment = active_environment.clone();
+        }
+        if http_request.retry.is_none() {
+            http_request.retry = schemas::get_collection_retry_policy(&collection).await?;
+        }
+        // OAuth的token按collection与环境缓存
+        http_request.token_scope = TokenScope {
+            collection: collection.clone(),
+            environment: active_environment,
+        };
+        http_request.cookie_scope = CookieScope {
+            collection,
+            environment,
+        };
+    }
+    Ok((setting, http_request))
+}
 
+// 生成hyper的请求，可多次使用同一请求配置生成
+fn new_request(
+    http_request: &HTTPRequest,
+    auth_state: &AuthState,
+) -> Result<(Request<Body>, Url), CyberAPIError> {
     let body = if http_request.content_type.starts_with("multipart/form-data") {
         // 数据为base64
-        let buf = general_purpose::STANDARD.decode(http_request.body)?;
-        Body::from(buf)
+        general_purpose::STANDARD.decode(&http_request.body)?
     } else {
-        Body::from(http_request.body)
+        http_request.body.clone().into_bytes()
     };
 
-    let mut req = Request::new(body);
+    let mut req = Request::new(Body::from(body.clone()));
 
     match http_request.method.to_uppercase().as_str() {
         "POST" => *req.method_mut() = Method::POST,
@@ -434,7 +610,7 @@ pub async fn request(
 
     // 设置query
     let mut current_url = Url::parse(http_request.uri.as_str())?;
-    for q in http_request.query {
+    for q in http_request.query.iter() {
         if !q.enabled {
             continue;
         }
@@ -448,7 +624,7 @@ pub async fn request(
     let mut set_content_type = false;
     let content_type = "content-type";
     let header = req.headers_mut();
-    for h in http_request.headers {
+    for h in http_request.headers.iter() {
         if !h.enabled {
             continue;
         }
@@ -470,15 +646,9 @@ pub async fn request(
         );
     }
 
-    {
-        // cookie store未实现send，避免与下面的await冲突
-        // 设置Cookie
-        let cookie_store = cookies::get_cookie_store();
-        let cookie_header = cookie_store
-            .get_request_values(&current_url)
-            .map(|(name, value)| format!("{}={}", name, value))
-            .collect::<Vec<_>>()
-            .join("; ");
+    // 设置Cookie
+    if http_request.cookie_jar != CookieJar::Disabled {
+        let cookie_header = cookies::get_cookie_header(&http_request.cookie_scope, &current_url)?;
         if !cookie_header.is_empty() {
             header.insert(
                 "Cookie".parse::<HeaderName>()?,
@@ -486,6 +656,95 @@ pub async fn request(
             );
         }
     }
+
+    // 认证需要在其它header设置后处理（如AWS需要签名content-type）
+    if let Some(auth) = &http_request.auth {
+        auth::apply_auth(
+            auth,
+            &http_request.method,
+            &current_url,
+            header,
+            &body,
+            auth_state,
+        )?;
+    }
+    Ok((req, current_url))
+}
+
+// 响应数据解压失败
+fn body_decode_error(err: std::io::Error) -> CyberAPIError {
+    CyberAPIError::new(err.to_string(), "bodyDecode")
+        .with_code(ErrorCode::BodyDecode)
+        .with_phase(RequestPhase::Decode)
+}
+
+// 发送请求并读取响应数据，响应的cookie不保存
+// 首次请求返回401时：Digest认证根据WWW-Authenticate再次请求，
+// OAuth认证重新获取token后再次请求
+pub async fn send(
+    http_request: &HTTPRequest,
+    timeout: &RequestTimeout,
+) -> Result<HTTPRawResponse, CyberAPIError> {
+    let mut auth_state = AuthState::default();
+    if let Some(HTTPAuth::OAuth2(config)) = &http_request.auth {
+        let token = oauth::get_token(config, &http_request.token_scope, timeout, None)
+            .await
+            .map_err(|err| err.or_phase(RequestPhase::Prepare))?;
+        auth_state.access_token = Some(token.access_token);
+    }
+    let resp = send_once(http_request, timeout, &auth_state).await?;
+    if resp.status != 401 {
+        return Ok(resp);
+    }
+    match &http_request.auth {
+        Some(HTTPAuth::Digest { .. }) => {
+            let challenge = resp
+                .headers
+                .get("www-authenticate")
+                .and_then(|values| auth::parse_digest_challenge(values));
+            match challenge {
+                Some(challenge) => auth_state.digest = Some(challenge),
+                None => return Ok(resp),
+            }
+        }
+        // token可能已被撤销
+        Some(HTTPAuth::OAuth2(config)) => {
+            let token = oauth::get_token(
+                config,
+                &http_request.token_scope,
+                timeout,
+                auth_state.access_token.as_deref(),
+            )
+            .await
+            .map_err(|err| err.or_phase(RequestPhase::Prepare))?;
+            auth_state.access_token = Some(token.access_token);
+        }
+        _ => return Ok(resp),
+    }
+    send_once(http_request, timeout, &auth_state).await
+}
+
+// 发送一次请求，不处理Digest与OAuth的认证流程
+pub async fn send_once(
+    http_request: &HTTPRequest,
+    timeout: &RequestTimeout,
+    auth_state: &AuthState,
+) -> Result<HTTPRawResponse, CyberAPIError> {
+    let trace = Arc::new(HTTPTrace::new());
+    let span = new_trace_span(&trace);
+    send_with_trace(http_request, timeout, &trace, auth_state)
+        .instrument(span)
+        .await
+}
+
+async fn send_with_trace(
+    http_request: &HTTPRequest,
+    timeout: &RequestTimeout,
+    trace: &HTTPTrace,
+    auth_state: &AuthState,
+) -> Result<HTTPRawResponse, CyberAPIError> {
+    let (req, current_url) =
+        new_request(http_request, auth_state).map_err(|err| err.or_phase(RequestPhase::Prepare))?;
     let connect_timeout = Duration::from_secs(timeout.connect);
     let write_timeout = Duration::from_secs(timeout.write);
     let read_timeout = Duration::from_secs(timeout.read);
@@ -503,9 +762,11 @@ pub async fn request(
         connector.set_read_timeout(Some(read_timeout));
         connector.set_write_timeout(Some(write_timeout));
         Client::builder()
+            .executor(TraceExecutor)
             .build::<_, hyper::Body>(connector)
             .request(req)
-            .await?
+            .await
+            .map_err(|err| CyberAPIError::from(err).or_phase(RequestPhase::Send))?
     } else {
         let h = HttpConnector::new();
         let mut connector = TimeoutConnector::new(h);
@@ -513,9 +774,11 @@ pub async fn request(
         connector.set_read_timeout(Some(read_timeout));
         connector.set_write_timeout(Some(write_timeout));
         Client::builder()
+            .executor(TraceExecutor)
             .build::<_, hyper::Body>(connector)
             .request(req)
-            .await?
+            .await
+            .map_err(|err| CyberAPIError::from(err).or_phase(RequestPhase::Send))?
     };
 
     let status = resp.status().as_u16();
@@ -531,7 +794,10 @@ pub async fn request(
         let mut key = name.to_string();
         key = key.to_lowercase();
 
-        let value = value.to_str()?.to_string();
+        let value = value
+            .to_str()
+            .map_err(|err| CyberAPIError::from(err).with_phase(RequestPhase::Receive))?
+            .to_string();
         if key == "set-cookie" {
             set_cookies.push(value.clone());
         }
@@ -556,37 +822,91 @@ pub async fn request(
             }
         }
     }
-    // 如果有更新cookie，则写入
-    if !set_cookies.is_empty() {
-        cookies::save_cookie_store(set_cookies, &current_url)?;
-    }
-
     let mut remote_addr = "".to_string();
     if let Some(info) = resp.extensions().get::<HttpInfo>() {
         remote_addr = info.remote_addr().to_string();
     }
-    let mut buf = hyper::body::to_bytes(resp).await?;
+    let mut buf = hyper::body::to_bytes(resp)
+        .await
+        .map_err(|err| CyberAPIError::from(err).or_phase(RequestPhase::Receive))?;
     // 主动触发done，不计算解压数据耗时
     trace.done();
     let body_size = buf.len();
     // 解压gzip
     if is_gzip {
-        let mut decoder = Decoder::new(&buf[..])?;
         let mut decode_data = Vec::new();
-        let _ = decoder.read_to_end(&mut decode_data)?;
+        Decoder::new(&buf[..])
+            .and_then(|mut decoder| decoder.read_to_end(&mut decode_data))
+            .map_err(body_decode_error)?;
         buf = Bytes::copy_from_slice(&decode_data);
     }
     // 解压br
     if is_br {
         let mut decode_data = Vec::new();
         let mut r = buf.reader();
-        brotli_decompressor::BrotliDecompress(&mut r, &mut decode_data)?;
+        brotli_decompressor::BrotliDecompress(&mut r, &mut decode_data)
+            .map_err(body_decode_error)?;
         buf = Bytes::copy_from_slice(&decode_data);
     }
 
-    let mut stats: HTTPStats = trace.into();
+    let mut stats = HTTPStats::from(trace);
     stats.remote_addr = remote_addr;
 
+    Ok(HTTPRawResponse {
+        url: current_url,
+        status,
+        headers,
+        set_cookies,
+        body: buf,
+        body_size,
+        stats,
+    })
+}
+
+pub async fn request(
+    api: String,
+    http_request: HTTPRequest,
+    timeout: RequestTimeout,
+) -> Result<HTTPResponse, CyberAPIError> {
+    let (setting, http_request) = resolve_request(&api, http_request)
+        .await
+        .map_err(|err| err.or_phase(RequestPhase::Prepare))?;
+    let (
+        HTTPRawResponse {
+            url,
+            status,
+            headers,
+            set_cookies,
+            body: buf,
+            body_size,
+            stats,
+        },
+        attempts,
+    ) = retry::send(&http_request, &timeout).await?;
+    // 如果有更新cookie，则写入
+    if !set_cookies.is_empty() && http_request.cookie_jar != CookieJar::Disabled {
+        cookies::save_cookie_store(&http_request.cookie_scope, set_cookies, &url)?;
+    }
+
+    let mut extractions = vec![];
+    let mut assertions = vec![];
+    if let Some(setting) = setting {
+        let value = setting.setting.unwrap_or_default();
+        let rules = extraction::get_extraction_rules(&value);
+        extractions =
+            extraction::apply_extractions(&setting.collection, &rules, &headers, &buf).await;
+        let rules = assertion::get_assertion_rules(&value);
+        assertions = assertion::evaluate_assertions(
+            &rules,
+            &AssertionResponse {
+                status,
+                headers: &headers,
+                body: &buf,
+                latency: stats.total,
+            },
+        );
+    }
+
     let resp = HTTPResponse {
         api,
         body_size: body_size as u32,
@@ -595,6 +915,9 @@ pub async fn request(
         headers,
         body: general_purpose::STANDARD.encode(buf),
         stats,
+        extractions,
+        assertions,
+        attempts,
     };
 
     Ok(resp)
@@ -0,0 +1,17 @@
+pub mod assertion;
+pub mod auth;
+pub mod cookies;
+pub mod entities;
+pub mod error;
+pub mod extraction;
+pub mod file_watcher;
+pub mod http_request;
+pub mod load_test;
+pub mod mock_server;
+pub mod oauth;
+pub mod retry;
+pub mod runner;
+pub mod schemas;
+pub mod template;
+pub mod util;
+pub mod workspace;
@@ -0,0 +1,376 @@
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::error::CyberAPIError;
+use crate::http_request::{self, HTTPRequest, HTTPStats, RequestTimeout};
+
+// 压测过程中定时触发的事件
+pub const LOAD_TEST_PROGRESS_EVENT: &str = "load-test-progress";
+
+// 进度推送的间隔
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+// 执行中的压测的停止标记，以压测的id为key
+static RUNNING: Lazy<Mutex<HashMap<String, Arc<AtomicBool>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn default_concurrency() -> u32 {
+    1
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadTestOptions {
+    // 并发数
+    #[serde(default = "default_concurrency")]
+    pub concurrency: u32,
+    // 每秒请求数，为0则不限制（由并发数决定）
+    #[serde(default)]
+    pub rps: u32,
+    // 压测时长(秒)，不包括预热时长
+    #[serde(default)]
+    pub duration: u64,
+    // 请求总数，不包括预热时的请求
+    #[serde(default)]
+    pub count: u64,
+    // 预热时长(秒)，预热时的请求不统计
+    #[serde(default)]
+    pub warm_up: u64,
+}
+
+// 耗时的统计(ms)
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencySummary {
+    pub min: u32,
+    pub mean: u32,
+    pub p50: u32,
+    pub p90: u32,
+    pub p99: u32,
+    pub max: u32,
+}
+
+// 各阶段耗时的统计，与HTTPStats对应
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadTestPhases {
+    pub dns_lookup: LatencySummary,
+    pub tcp: LatencySummary,
+    pub tls: LatencySummary,
+    pub send: LatencySummary,
+    pub server_processing: LatencySummary,
+    pub content_transfer: LatencySummary,
+}
+
+// 同一类别的出错
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadTestErrorGroup {
+    pub count: u64,
+    // 首个出错信息
+    pub message: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadTestProgress {
+    pub id: String,
+    // 是否预热中
+    pub warming_up: bool,
+    // 已耗时(ms)
+    pub elapsed: u32,
+    // 已完成（不包括预热）的请求数
+    pub completed: u64,
+    pub failed: u64,
+    // 最近一次推送后的每秒请求数
+    pub rps: f64,
+    // 最近一次推送后的平均耗时(ms)
+    pub latency: u32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadTestReport {
+    pub id: String,
+    pub api: String,
+    pub concurrency: u32,
+    // 目标的每秒请求数
+    pub target_rps: u32,
+    pub started_at: String,
+    pub finished_at: String,
+    // 不包括预热的耗时(ms)
+    pub duration: u32,
+    // 手动停止
+    pub stopped: bool,
+    pub total: u64,
+    // 有响应（不区分状态码）的请求数
+    pub succeeded: u64,
+    // 请求出错的数量
+    pub failed: u64,
+    // 实际的每秒请求数
+    pub rps: f64,
+    pub latency: LatencySummary,
+    pub phases: LoadTestPhases,
+    // 各状态码的请求数
+    pub statuses: BTreeMap<u16, u64>,
+    // 按CyberAPIError的category分组
+    pub errors: BTreeMap<String, LoadTestErrorGroup>,
+}
+
+// 单个请求的结果
+struct Sample {
+    latency: u32,
+    status: Option<u16>,
+    stats: Option<HTTPStats>,
+    error: Option<CyberAPIError>,
+}
+
+// 使用nearest-rank计算百分位，values需已排序
+fn percentile(values: &[u32], p: f64) -> u32 {
+    if values.is_empty() {
+        return 0;
+    }
+    let rank = (p / 100.0 * values.len() as f64).ceil() as usize;
+    values[rank.clamp(1, values.len()) - 1]
+}
+
+fn summarize(mut values: Vec<u32>) -> LatencySummary {
+    if values.is_empty() {
+        return LatencySummary::default();
+    }
+    values.sort_unstable();
+    let sum: u64 = values.iter().map(|value| *value as u64).sum();
+    LatencySummary {
+        min: values[0],
+        mean: (sum / values.len() as u64) as u32,
+        p50: percentile(&values, 50.0),
+        p90: percentile(&values, 90.0),
+        p99: percentile(&values, 99.0),
+        max: values[values.len() - 1],
+    }
+}
+
+fn summarize_phases(samples: &[Sample]) -> LoadTestPhases {
+    let stats: Vec<&HTTPStats> = samples
+        .iter()
+        .filter_map(|item| item.stats.as_ref())
+        .collect();
+    let phase =
+        |get: fn(&HTTPStats) -> u32| summarize(stats.iter().map(|item| get(item)).collect());
+    LoadTestPhases {
+        dns_lookup: phase(|item| item.dns_lookup),
+        tcp: phase(|item| item.tcp),
+        tls: phase(|item| item.tls),
+        send: phase(|item| item.send),
+        server_processing: phase(|item| item.server_processing),
+        content_transfer: phase(|item| item.content_transfer),
+    }
+}
+
+// 多个worker共享的状态
+struct LoadTestState {
+    request: HTTPRequest,
+    timeout: RequestTimeout,
+    options: LoadTestOptions,
+    start: Instant,
+    // 开始统计的时间
+    measure_start: Instant,
+    // 按rps限制时下一个请求的发送时间
+    next_slot: Mutex<Instant>,
+    issued: AtomicU64,
+    samples: Mutex<Vec<Sample>>,
+    finished_workers: AtomicUsize,
+    stopped: Arc<AtomicBool>,
+}
+
+impl LoadTestState {
+    fn is_warming_up(&self) -> bool {
+        Instant::now() < self.measure_start
+    }
+    fn is_done(&self) -> bool {
+        if self.stopped.load(Ordering::Relaxed) {
+            return true;
+        }
+        let duration = self.options.duration;
+        duration > 0 && self.measure_start.elapsed() >= Duration::from_secs(duration)
+    }
+    // 获取发送的时间，rps为0时立即发送
+    fn acquire_slot(&self) -> Option<Instant> {
+        if self.options.rps == 0 {
+            return None;
+        }
+        let interval = Duration::from_secs(1) / self.options.rps;
+        let mut next = self.next_slot.lock().unwrap();
+        let slot = (*next).max(Instant::now());
+        *next = slot + interval;
+        Some(slot)
+    }
+}
+
+async fn run_worker(state: Arc<LoadTestState>) {
+    loop {
+        if let Some(slot) = state.acquire_slot() {
+            tokio::time::sleep_until(tokio::time::Instant::from_std(slot)).await;
+        }
+        if state.is_done() {
+            break;
+        }
+        let warming_up = state.is_warming_up();
+        if !warming_up
+            && state.options.count > 0
+            && state.issued.fetch_add(1, Ordering::Relaxed) >= state.options.count
+        {
+            break;
+        }
+        let start = Instant::now();
+        let result = http_request::send(&state.request, &state.timeout).await;
+        if warming_up {
+            continue;
+        }
+        let latency = start.elapsed().as_millis() as u32;
+        let sample = match result {
+            Ok(resp) => Sample {
+                latency,
+                status: Some(resp.status),
+                stats: Some(resp.stats),
+                error: None,
+            },
+            Err(err) => Sample {
+                latency,
+                status: None,
+                stats: None,
+                error: Some(err),
+            },
+        };
+        state.samples.lock().unwrap().push(sample);
+    }
+    state.finished_workers.fetch_add(1, Ordering::Relaxed);
+}
+
+// 使用相同的请求配置并发发送请求，变量仅在开始前替换一次，响应的cookie不保存
+pub async fn run<F>(
+    api: String,
+    req: HTTPRequest,
+    timeout: RequestTimeout,
+    options: LoadTestOptions,
+    on_progress: F,
+) -> Result<LoadTestReport, CyberAPIError>
+where
+    F: Fn(LoadTestProgress),
+{
+    if options.duration == 0 && options.count == 0 {
+        return Err(CyberAPIError::new(
+            "duration or count should be specified".to_string(),
+            "loadTest",
+        ));
+    }
+    let (_, request) = http_request::resolve_request(&api, req).await?;
+
+    let id = ulid::Ulid::new().to_string();
+    let stopped = Arc::new(AtomicBool::new(false));
+    RUNNING.lock().unwrap().insert(id.clone(), stopped.clone());
+    let started_at = Utc::now().to_rfc3339();
+    let concurrency = options.concurrency.max(1);
+    let start = Instant::now();
+    let state = Arc::new(LoadTestState {
+        request,
+        timeout,
+        measure_start: start + Duration::from_secs(options.warm_up),
+        next_slot: Mutex::new(start),
+        start,
+        options,
+        issued: AtomicU64::new(0),
+        samples: Mutex::new(vec![]),
+        finished_workers: AtomicUsize::new(0),
+        stopped,
+    });
+    let mut handles = vec![];
+    for _ in 0..concurrency {
+        handles.push(tokio::spawn(run_worker(state.clone())));
+    }
+
+    let mut reported = 0;
+    while state.finished_workers.load(Ordering::Relaxed) < concurrency as usize {
+        tokio::time::sleep(PROGRESS_INTERVAL).await;
+        let samples = state.samples.lock().unwrap();
+        let recent = &samples[reported..];
+        let latency = if recent.is_empty() {
+            0
+        } else {
+            (recent.iter().map(|item| item.latency as u64).sum::<u64>() / recent.len() as u64)
+                as u32
+        };
+        let progress = LoadTestProgress {
+            id: id.clone(),
+            warming_up: state.is_warming_up(),
+            elapsed: state.start.elapsed().as_millis() as u32,
+            completed: samples.len() as u64,
+            failed: samples.iter().filter(|item| item.error.is_some()).count() as u64,
+            rps: recent.len() as f64 / PROGRESS_INTERVAL.as_secs_f64(),
+            latency,
+        };
+        reported = samples.len();
+        drop(samples);
+        on_progress(progress);
+    }
+    for handle in handles {
+        let _ = handle.await;
+    }
+    RUNNING.lock().unwrap().remove(&id);
+
+    let duration = state.measure_start.elapsed();
+    let samples = std::mem::take(&mut *state.samples.lock().unwrap());
+    let mut statuses = BTreeMap::new();
+    let mut errors: BTreeMap<String, LoadTestErrorGroup> = BTreeMap::new();
+    for item in samples.iter() {
+        if let Some(status) = item.status {
+            *statuses.entry(status).or_insert(0) += 1;
+        }
+        if let Some(err) = &item.error {
+            errors
+                .entry(err.category())
+                .or_insert_with(|| LoadTestErrorGroup {
+                    count: 0,
+                    message: err.message(),
+                })
+                .count += 1;
+        }
+    }
+    let total = samples.len() as u64;
+    let failed = samples.iter().filter(|item| item.error.is_some()).count() as u64;
+    let rps = if duration.as_secs_f64() > 0.0 {
+        total as f64 / duration.as_secs_f64()
+    } else {
+        0.0
+    };
+    Ok(LoadTestReport {
+        id,
+        api,
+        concurrency,
+        target_rps: state.options.rps,
+        started_at,
+        finished_at: Utc::now().to_rfc3339(),
+        duration: duration.as_millis() as u32,
+        stopped: state.stopped.load(Ordering::Relaxed),
+        total,
+        succeeded: total - failed,
+        failed,
+        rps,
+        latency: summarize(samples.iter().map(|item| item.latency).collect()),
+        phases: summarize_phases(&samples),
+        statuses,
+        errors,
+    })
+}
+
+// 停止指定的压测（id为进度事件中的id），已完成的请求仍统计
+pub fn stop(id: &str) {
+    if let Some(stopped) = RUNNING.lock().unwrap().get(id) {
+        stopped.store(true, Ordering::Relaxed);
+    }
+}
@@ -5,13 +5,12 @@
 
 use tracing_subscriber::prelude::*;
 
+use cyberapi::{
+    cookies, error, file_watcher, http_request, load_test, mock_server, oauth, runner, schemas,
+    template, util, workspace,
+};
+
 mod commands;
-mod cookies;
-mod entities;
-mod error;
-mod http_request;
-mod schemas;
-mod util;
 
 fn main() {
     tracing_subscriber::registry()
@@ -38,6 +37,7 @@ fn main() {
         .setup(|app| {
             let dir = app.path_resolver().app_data_dir().unwrap();
             util::set_app_dir(dir.to_str().unwrap().to_string());
+            file_watcher::forward_sync_failures(app.handle());
             Ok(())
         })
         .menu(menu)
@@ -46,27 +46,86 @@ fn main() {
             commands::init_tables,
             commands::export_tables,
             commands::import_tables,
+            commands::repair_database,
+            commands::list_workspace,
+            commands::get_current_workspace,
+            commands::create_workspace,
+            commands::open_workspace,
+            commands::switch_workspace,
+            commands::close_workspace,
             commands::add_api_setting,
             commands::update_api_setting,
             commands::list_api_setting,
             commands::delete_api_settings,
+            commands::search_api_settings,
+            commands::list_file_store,
+            commands::link_file_store,
+            commands::open_file_store,
+            commands::unlink_file_store,
+            commands::watch_file_stores,
+            commands::list_api_setting_revision,
+            commands::diff_api_setting_revision,
+            commands::restore_api_setting_revision,
+            commands::clean_api_setting_revision,
+            commands::add_api_example,
+            commands::list_api_example,
+            commands::list_collection_api_example,
+            commands::rename_api_example,
+            commands::delete_api_example,
             commands::add_api_folder,
             commands::update_api_folder,
             commands::list_api_folder,
             commands::delete_api_folder,
+            commands::list_trash,
+            commands::restore_trash,
+            commands::purge_trash,
+            commands::clean_trash,
             commands::add_api_collection,
             commands::update_api_collection,
             commands::list_api_collection,
             commands::delete_api_collection,
             commands::do_http_request,
+            commands::run_collection,
+            commands::save_runner_report,
+            commands::load_test,
+            commands::stop_load_test,
+            commands::start_mock_server,
+            commands::stop_mock_server,
+            commands::get_mock_server_status,
+            commands::list_mock_requests,
+            commands::clear_mock_requests,
             commands::list_cookie,
             commands::delete_cookie,
             commands::add_cookie,
+            commands::update_cookie,
+            commands::import_cookie,
+            commands::export_cookie,
             commands::clear_cookie,
+            commands::get_oauth_token,
+            commands::delete_oauth_token,
             commands::add_variable,
             commands::update_variable,
             commands::delete_variable,
             commands::list_variable,
+            commands::reveal_variable,
+            commands::add_environment,
+            commands::update_environment,
+            commands::delete_environment,
+            commands::list_environment,
+            commands::clone_environment,
+            commands::compare_environment,
+            commands::activate_environment,
+            commands::get_active_environment,
+            commands::add_global_variable,
+            commands::update_global_variable,
+            commands::delete_global_variable,
+            commands::list_global_variable,
+            commands::resolve_template,
+            commands::list_resolved_variable,
+            commands::get_secret_status,
+            commands::init_secret_key,
+            commands::unlock_secret,
+            commands::lock_secret,
             commands::get_latest_version,
             commands::add_version,
         ])
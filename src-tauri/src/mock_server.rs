@@ -0,0 +1,510 @@
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use hyper::{
+    body::{self, Bytes},
+    header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE},
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::oneshot;
+
+use crate::error::CyberAPIError;
+use crate::http_request::HTTPRequestKVParam;
+use crate::schemas::{self, APISetting};
+use crate::template::VariableScope;
+
+// 接收到请求后触发的事件
+pub const MOCK_REQUEST_EVENT: &str = "mock-server-request";
+
+// 请求记录保留的最大数量
+const MAX_REQUEST_LOGS: usize = 500;
+
+// 示例中的这些响应头与实际返回的数据不一致，忽略
+const IGNORE_EXAMPLE_HEADERS: [&str; 4] = [
+    "content-length",
+    "content-encoding",
+    "transfer-encoding",
+    "connection",
+];
+
+// 指定请求返回的状态码与延时（覆盖mock的配置）
+const HEADER_MOCK_STATUS: &str = "x-mock-status";
+const HEADER_MOCK_DELAY: &str = "x-mock-delay";
+
+fn default_enabled() -> bool {
+    true
+}
+
+// 保存在API配置中的mock响应
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MockSetting {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    // 路由路径，如/users/:id，为空则使用请求uri的路径
+    #[serde(default)]
+    pub path: String,
+    // 使用的示例ID，为空则使用以下配置的响应
+    #[serde(default)]
+    pub example: String,
+    // 状态码，为0则使用示例的状态码（无示例时为200）
+    #[serde(default)]
+    pub status: u16,
+    // 延时(ms)
+    #[serde(default)]
+    pub delay: u64,
+    #[serde(default)]
+    pub content_type: String,
+    #[serde(default)]
+    pub headers: Vec<HTTPRequestKVParam>,
+    // 响应数据，支持{{params.id}}等占位符，为空则使用示例的响应数据
+    #[serde(default)]
+    pub body: String,
+}
+
+#[derive(Deserialize, Default)]
+struct StoredMock {
+    #[serde(default)]
+    method: String,
+    #[serde(default)]
+    uri: String,
+    mock: Option<MockSetting>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MockServerStatus {
+    pub running: bool,
+    pub collection: String,
+    pub port: u16,
+    pub started_at: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MockRequestLog {
+    pub id: String,
+    pub method: String,
+    pub path: String,
+    pub query: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    // 匹配的API，未匹配时为空
+    pub api: String,
+    pub status: u16,
+    // 延时(ms)
+    pub delay: u64,
+    // 处理耗时(ms)，包括延时
+    pub duration: u32,
+    pub created_at: String,
+}
+
+struct RunningServer {
+    status: MockServerStatus,
+    shutdown: oneshot::Sender<()>,
+}
+
+static SERVER: Lazy<Mutex<Option<RunningServer>>> = Lazy::new(|| Mutex::new(None));
+
+static REQUEST_LOGS: Lazy<Mutex<VecDeque<MockRequestLog>>> =
+    Lazy::new(|| Mutex::new(VecDeque::new()));
+
+struct MockRoute {
+    api: String,
+    method: String,
+    segments: Vec<String>,
+    mock: MockSetting,
+}
+
+impl MockRoute {
+    // 路径参数支持:id、{id}以及{{id}}
+    fn get_param_name(segment: &str) -> Option<&str> {
+        if let Some(name) = segment.strip_prefix(':') {
+            return Some(name);
+        }
+        segment
+            .strip_prefix("{{")
+            .and_then(|value| value.strip_suffix("}}"))
+            .or_else(|| {
+                segment
+                    .strip_prefix('{')
+                    .and_then(|value| value.strip_suffix('}'))
+            })
+            .map(|value| value.trim())
+    }
+    // 匹配成功返回路径参数
+    fn matches(&self, method: &str, segments: &[&str]) -> Option<HashMap<String, String>> {
+        if self.method != method || self.segments.len() != segments.len() {
+            return None;
+        }
+        let mut params = HashMap::new();
+        for (pattern, value) in self.segments.iter().zip(segments.iter()) {
+            if pattern == "*" {
+                continue;
+            }
+            if let Some(name) = MockRoute::get_param_name(pattern) {
+                params.insert(name.to_string(), value.to_string());
+                continue;
+            }
+            if pattern != value {
+                return None;
+            }
+        }
+        Some(params)
+    }
+    // 固定的路径段越多越优先
+    fn specificity(&self) -> usize {
+        self.segments
+            .iter()
+            .filter(|item| *item != "*" && MockRoute::get_param_name(item).is_none())
+            .count()
+    }
+}
+
+fn split_path(path: &str) -> Vec<&str> {
+    path.split('/').filter(|item| !item.is_empty()).collect()
+}
+
+// 获取uri中的路径部分，如{{host}}/users/:id?type=1 => /users/:id
+fn get_route_path(uri: &str) -> String {
+    let mut value = uri.trim();
+    if let Some(index) = value.find(['?', '#']) {
+        value = &value[..index];
+    }
+    if let Some(index) = value.find("://") {
+        value = &value[index + 3..];
+    }
+    if !value.starts_with('/') {
+        value = match value.find('/') {
+            Some(index) => &value[index..],
+            None => "/",
+        };
+    }
+    value.to_string()
+}
+
+async fn list_routes(collection: &str) -> Result<Vec<MockRoute>, CyberAPIError> {
+    let settings = schemas::list_api_setting(collection.to_string()).await?;
+    let context = schemas::get_template_context(Some(collection.to_string()), vec![]).await?;
+    let mut routes = vec![];
+    for APISetting { id, setting, .. } in settings {
+        let value = setting.unwrap_or_default();
+        if value.is_empty() {
+            continue;
+        }
+        let stored: StoredMock = serde_json::from_str(&value).unwrap_or_default();
+        let mock = match stored.mock {
+            Some(mock) if mock.enabled => mock,
+            _ => continue,
+        };
+        let path = if mock.path.is_empty() {
+            // 替换失败（如路径参数使用{{id}}）时使用原始的uri
+            get_route_path(&context.render(&stored.uri).unwrap_or(stored.uri))
+        } else {
+            mock.path.clone()
+        };
+        let method = if stored.method.is_empty() {
+            "GET".to_string()
+        } else {
+            stored.method.to_uppercase()
+        };
+        routes.push(MockRoute {
+            api: id,
+            method,
+            segments: split_path(&path).into_iter().map(String::from).collect(),
+            mock,
+        });
+    }
+    routes.sort_by_key(|item| Reverse(item.specificity()));
+    Ok(routes)
+}
+
+fn new_json_response(status: StatusCode, message: &str) -> Response<Body> {
+    let body = serde_json::json!({ "message": message }).to_string();
+    let mut resp = Response::new(Body::from(body));
+    *resp.status_mut() = status;
+    resp.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("application/json; charset=utf-8"),
+    );
+    resp
+}
+
+fn get_header_value<T: std::str::FromStr>(headers: &[(String, String)], name: &str) -> Option<T> {
+    headers
+        .iter()
+        .find(|(key, _)| key == name)
+        .and_then(|(_, value)| value.trim().parse::<T>().ok())
+}
+
+struct MockRequest {
+    method: String,
+    path: String,
+    query: String,
+    headers: Vec<(String, String)>,
+    body: Bytes,
+}
+
+// 生成mock的响应，返回匹配的API、延时与响应
+async fn respond(
+    collection: &str,
+    req: &MockRequest,
+) -> Result<(String, u64, Response<Body>), CyberAPIError> {
+    let segments = split_path(&req.path);
+    let routes = list_routes(collection).await?;
+    let matched = routes.into_iter().find_map(|route| {
+        route
+            .matches(&req.method, &segments)
+            .map(|params| (route, params))
+    });
+    let (route, params) = match matched {
+        Some(value) => value,
+        None => {
+            let message = format!("mock route not found, {} {}", req.method, req.path);
+            return Ok((
+                "".to_string(),
+                0,
+                new_json_response(StatusCode::NOT_FOUND, &message),
+            ));
+        }
+    };
+
+    // 请求的数据可在响应中引用
+    let mut variables = vec![
+        ("request.method".to_string(), req.method.clone()),
+        ("request.path".to_string(), req.path.clone()),
+        (
+            "request.body".to_string(),
+            std::str::from_utf8(&req.body)
+                .unwrap_or_default()
+                .to_string(),
+        ),
+    ];
+    for (key, value) in params {
+        variables.push((format!("params.{}", key), value));
+    }
+    for (key, value) in url::form_urlencoded::parse(req.query.as_bytes()) {
+        variables.push((format!("query.{}", key), value.to_string()));
+    }
+    for (key, value) in req.headers.iter() {
+        variables.push((format!("headers.{}", key), value.clone()));
+    }
+    let mut context = schemas::get_template_context(Some(collection.to_string()), vec![]).await?;
+    for (name, value) in variables {
+        context.add(VariableScope::Local, name, value);
+    }
+
+    let mock = route.mock;
+    let mut renderer = context.renderer();
+    let mut body = renderer.render("body", &mock.body)?.into_bytes();
+    let mut headers = vec![];
+    for item in mock.headers.iter().filter(|item| item.enabled) {
+        let field = format!("headers.{}", item.key);
+        headers.push((item.key.clone(), renderer.render(&field, &item.value)?));
+    }
+    renderer.finish()?;
+
+    let mut status = mock.status;
+    let mut resp_headers = HeaderMap::new();
+    // 示例的响应数据原样返回，mock中配置的状态码、响应头与数据优先
+    if !mock.example.is_empty() {
+        let example = schemas::find_api_example(mock.example.clone())
+            .await?
+            .ok_or_else(|| {
+                CyberAPIError::new(format!("example not found, {}", mock.example), "mockServer")
+            })?;
+        if status == 0 {
+            status = example.status as u16;
+        }
+        let value = example.headers.unwrap_or_default();
+        if !value.is_empty() {
+            let example_headers: HashMap<String, Vec<String>> = serde_json::from_str(&value)?;
+            for (key, values) in example_headers {
+                if IGNORE_EXAMPLE_HEADERS.contains(&key.to_lowercase().as_str()) {
+                    continue;
+                }
+                let name = HeaderName::from_bytes(key.as_bytes())?;
+                for value in values {
+                    resp_headers.append(name.clone(), HeaderValue::from_str(&value)?);
+                }
+            }
+        }
+        if body.is_empty() {
+            body = general_purpose::STANDARD.decode(example.body.unwrap_or_default())?;
+        }
+    }
+    if status == 0 {
+        status = 200;
+    }
+    if !mock.content_type.is_empty() {
+        resp_headers.insert(CONTENT_TYPE, HeaderValue::from_str(&mock.content_type)?);
+    }
+    for (key, _) in headers.iter() {
+        resp_headers.remove(HeaderName::from_bytes(key.as_bytes())?);
+    }
+    for (key, value) in headers {
+        resp_headers.append(
+            HeaderName::from_bytes(key.as_bytes())?,
+            HeaderValue::from_str(&value)?,
+        );
+    }
+
+    let status = get_header_value(&req.headers, HEADER_MOCK_STATUS).unwrap_or(status);
+    let delay = get_header_value(&req.headers, HEADER_MOCK_DELAY).unwrap_or(mock.delay);
+    let mut resp = Response::new(Body::from(body));
+    *resp.status_mut() = StatusCode::from_u16(status)?;
+    *resp.headers_mut() = resp_headers;
+    Ok((route.api, delay, resp))
+}
+
+async fn handle<F>(
+    collection: Arc<String>,
+    on_request: Arc<F>,
+    req: Request<Body>,
+) -> Result<Response<Body>, Infallible>
+where
+    F: Fn(MockRequestLog),
+{
+    let start = Instant::now();
+    let (parts, body) = req.into_parts();
+    let headers = parts
+        .headers
+        .iter()
+        .map(|(key, value)| {
+            (
+                key.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).to_string(),
+            )
+        })
+        .collect();
+    let req = MockRequest {
+        method: parts.method.to_string(),
+        path: parts.uri.path().to_string(),
+        query: parts.uri.query().unwrap_or_default().to_string(),
+        headers,
+        body: body::to_bytes(body).await.unwrap_or_default(),
+    };
+    let (api, delay, resp) = match respond(&collection, &req).await {
+        Ok(value) => value,
+        Err(err) => (
+            "".to_string(),
+            0,
+            new_json_response(StatusCode::INTERNAL_SERVER_ERROR, &err.message()),
+        ),
+    };
+    if delay > 0 {
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+    }
+
+    let log = MockRequestLog {
+        id: ulid::Ulid::new().to_string(),
+        method: req.method,
+        path: req.path,
+        query: req.query,
+        headers: req.headers,
+        body: String::from_utf8_lossy(&req.body).to_string(),
+        api,
+        status: resp.status().as_u16(),
+        delay,
+        duration: start.elapsed().as_millis() as u32,
+        created_at: Utc::now().to_rfc3339(),
+    };
+    {
+        let mut logs = REQUEST_LOGS.lock().unwrap();
+        if logs.len() >= MAX_REQUEST_LOGS {
+            logs.pop_front();
+        }
+        logs.push_back(log.clone());
+    }
+    on_request(log);
+    Ok(resp)
+}
+
+// 启动mock服务，仅监听本机地址，端口为0时随机选择
+// 每次请求时重新加载collection的API配置，修改后无需重启
+pub async fn start<F>(
+    collection: String,
+    port: u16,
+    on_request: F,
+) -> Result<MockServerStatus, CyberAPIError>
+where
+    F: Fn(MockRequestLog) + Send + Sync + 'static,
+{
+    if let Some(server) = SERVER.lock().unwrap().as_ref() {
+        return Err(CyberAPIError::new(
+            format!(
+                "mock server is running on port {}, please stop it first",
+                server.status.port
+            ),
+            "mockServer",
+        ));
+    }
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let collection = Arc::new(collection);
+    let on_request = Arc::new(on_request);
+    let service_collection = collection.clone();
+    let make_service = make_service_fn(move |_| {
+        let collection = service_collection.clone();
+        let on_request = on_request.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                handle(collection.clone(), on_request.clone(), req)
+            }))
+        }
+    });
+    let server = Server::try_bind(&addr)?.serve(make_service);
+    let status = MockServerStatus {
+        running: true,
+        collection: collection.to_string(),
+        port: server.local_addr().port(),
+        started_at: Utc::now().to_rfc3339(),
+    };
+    let (shutdown, rx) = oneshot::channel::<()>();
+    tokio::spawn(server.with_graceful_shutdown(async {
+        rx.await.ok();
+    }));
+    let mut current = SERVER.lock().unwrap();
+    *current = Some(RunningServer {
+        status: status.clone(),
+        shutdown,
+    });
+    Ok(status)
+}
+
+// 停止mock服务，未启动时忽略
+pub fn stop() {
+    if let Some(server) = SERVER.lock().unwrap().take() {
+        let _ = server.shutdown.send(());
+    }
+}
+
+pub fn get_status() -> MockServerStatus {
+    match SERVER.lock().unwrap().as_ref() {
+        Some(server) => server.status.clone(),
+        None => MockServerStatus {
+            running: false,
+            collection: "".to_string(),
+            port: 0,
+            started_at: "".to_string(),
+        },
+    }
+}
+
+// 最近接收到的请求，按时间倒序
+pub fn list_requests() -> Vec<MockRequestLog> {
+    REQUEST_LOGS.lock().unwrap().iter().rev().cloned().collect()
+}
+
+pub fn clear_requests() {
+    REQUEST_LOGS.lock().unwrap().clear();
+}
@@ -0,0 +1,1015 @@
+use aes_gcm::aead::{rand_core::RngCore, OsRng};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap, convert::Infallible, net::SocketAddr, process::Command, sync::Arc,
+    time::Duration,
+};
+use tokio::sync::{mpsc, oneshot, watch, Mutex};
+use url::Url;
+
+use crate::auth::{AuthState, HTTPAuth};
+use crate::cookies::CookieJar;
+use crate::error::{CyberAPIError, ErrorCode};
+use crate::http_request::{self, HTTPRequest, HTTPRequestKVParam, RequestTimeout};
+use crate::schemas::{self, OAuthToken};
+use crate::template::{TemplateError, TemplateRenderer};
+
+// 等待用户在浏览器中完成授权的时长
+const AUTHORIZE_TIMEOUT: Duration = Duration::from_secs(300);
+const CALLBACK_PATH: &str = "/callback";
+
+// 获取token时按collection、环境与配置加锁，避免并发请求（如压测）重复获取
+static TOKEN_LOCKS: Lazy<std::sync::Mutex<HashMap<String, Arc<Mutex<()>>>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+// 等待浏览器中授权的token，等待时不加锁，授权完成（或失败）后通知其它请求
+static AUTHORIZING: Lazy<std::sync::Mutex<HashMap<String, watch::Receiver<()>>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+// 密钥不存在或未解锁时token无法加密保存，仅缓存于内存中（应用退出后失效）
+static MEMORY_TOKENS: Lazy<std::sync::Mutex<HashMap<String, OAuthToken>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum OAuthGrantType {
+    #[default]
+    ClientCredentials,
+    // resource owner password
+    Password,
+    // 使用PKCE，授权后回调至本机监听的地址
+    AuthorizationCode,
+}
+
+// client的认证方式
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ClientAuthentication {
+    // 使用Basic认证的请求头
+    #[default]
+    Basic,
+    // client_id与client_secret在请求数据中
+    Body,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthConfig {
+    #[serde(default)]
+    pub grant_type: OAuthGrantType,
+    pub token_url: String,
+    // 授权码模式的授权地址
+    #[serde(default)]
+    pub authorize_url: String,
+    pub client_id: String,
+    // 为空则为public client，client_id在请求数据中
+    #[serde(default)]
+    pub client_secret: String,
+    #[serde(default)]
+    pub scope: String,
+    #[serde(default)]
+    pub audience: String,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+    // 授权码回调监听的端口，为0时随机选择
+    #[serde(default)]
+    pub redirect_port: u16,
+    #[serde(default)]
+    pub client_authentication: ClientAuthentication,
+}
+
+// token缓存的作用域，按collection与环境区分
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenScope {
+    pub collection: String,
+    pub environment: String,
+}
+
+impl OAuthConfig {
+    pub fn render(mut self, renderer: &mut TemplateRenderer) -> Result<Self, TemplateError> {
+        for (name, value) in [
+            ("tokenUrl", &mut self.token_url),
+            ("authorizeUrl", &mut self.authorize_url),
+            ("clientId", &mut self.client_id),
+            ("clientSecret", &mut self.client_secret),
+            ("scope", &mut self.scope),
+            ("audience", &mut self.audience),
+            ("username", &mut self.username),
+            ("password", &mut self.password),
+        ] {
+            let text = renderer.render(&format!("auth.{}", name), value)?;
+            *value = text;
+        }
+        Ok(self)
+    }
+    // 缓存的key，配置变化（如scope、用户）后使用新的token
+    fn cache_key(&self) -> String {
+        let value = [
+            format!("{:?}", self.grant_type),
+            self.token_url.clone(),
+            self.client_id.clone(),
+            self.scope.clone(),
+            self.audience.clone(),
+            self.username.clone(),
+        ]
+        .join("\n");
+        hex::encode(Sha256::digest(value.as_bytes()))
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct TokenResponse {
+    #[serde(default)]
+    access_token: String,
+    #[serde(default)]
+    token_type: String,
+    // 部分服务返回的为字符串
+    #[serde(default)]
+    expires_in: Option<serde_json::Value>,
+    #[serde(default)]
+    refresh_token: String,
+    #[serde(default)]
+    scope: String,
+    #[serde(default)]
+    error: String,
+    #[serde(default)]
+    error_description: String,
+}
+
+impl TokenResponse {
+    fn get_expires_in(&self) -> Option<i64> {
+        match self.expires_in.as_ref()? {
+            serde_json::Value::Number(value) => value.as_i64(),
+            serde_json::Value::String(value) => value.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+fn oauth_error(message: String) -> CyberAPIError {
+    CyberAPIError::new(message, "oauth").with_code(ErrorCode::OAuth)
+}
+
+fn random_string(size: usize) -> String {
+    let mut data = vec![0u8; size];
+    OsRng.fill_bytes(&mut data);
+    general_purpose::URL_SAFE_NO_PAD.encode(data)
+}
+
+fn form_encode(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+// 响应数据一般为json，部分服务（如github）默认为form
+fn parse_token_response(body: &[u8]) -> TokenResponse {
+    if let Ok(resp) = serde_json::from_slice::<TokenResponse>(body) {
+        return resp;
+    }
+    let params: HashMap<String, String> = url::form_urlencoded::parse(body).into_owned().collect();
+    serde_json::to_value(params)
+        .and_then(serde_json::from_value)
+        .unwrap_or_default()
+}
+
+// 请求token endpoint，参数为空的忽略
+async fn request_token(
+    config: &OAuthConfig,
+    mut params: Vec<(&str, String)>,
+    timeout: &RequestTimeout,
+) -> Result<TokenResponse, CyberAPIError> {
+    let mut auth = None;
+    if config.client_secret.is_empty() || config.client_authentication == ClientAuthentication::Body
+    {
+        params.push(("client_id", config.client_id.clone()));
+        params.push(("client_secret", config.client_secret.clone()));
+    } else {
+        // client_id与client_secret需要先form编码(RFC 6749 2.3.1)
+        auth = Some(HTTPAuth::Basic {
+            username: form_encode(&config.client_id),
+            password: form_encode(&config.client_secret),
+        });
+    }
+    let body = {
+        let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+        for (key, value) in params.iter().filter(|(_, value)| !value.is_empty()) {
+            serializer.append_pair(key, value);
+        }
+        serializer.finish()
+    };
+    let req = HTTPRequest {
+        method: "POST".to_string(),
+        uri: config.token_url.clone(),
+        body,
+        content_type: "application/x-www-form-urlencoded".to_string(),
+        headers: vec![HTTPRequestKVParam {
+            key: "Accept".to_string(),
+            value: "application/json".to_string(),
+            enabled: true,
+        }],
+        query: vec![],
+        variables: vec![],
+        cookie_jar: CookieJar::Disabled,
+        cookie_scope: Default::default(),
+        retry: None,
+        auth,
+        token_scope: Default::default(),
+    };
+    let resp = http_request::send_once(&req, timeout, &AuthState::default()).await?;
+    let token = parse_token_response(&resp.body);
+    if resp.status >= 300 || token.access_token.is_empty() {
+        let message = if token.error.is_empty() {
+            format!("get oauth token fail, status: {}", resp.status)
+        } else if token.error_description.is_empty() {
+            format!("get oauth token fail, {}", token.error)
+        } else {
+            format!(
+                "get oauth token fail, {}: {}",
+                token.error, token.error_description
+            )
+        };
+        return Err(oauth_error(message).with_details(serde_json::json!({
+            "status": resp.status,
+            "body": String::from_utf8_lossy(&resp.body),
+        })));
+    }
+    Ok(token)
+}
+
+// 使用系统默认浏览器打开授权页面
+fn open_browser(url: &str) -> Result<(), CyberAPIError> {
+    let mut cmd = if cfg!(target_os = "windows") {
+        let mut cmd = Command::new("rundll32");
+        cmd.arg("url.dll,FileProtocolHandler");
+        cmd
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    cmd.arg(url).spawn()?;
+    Ok(())
+}
+
+fn handle_callback(
+    tx: &mpsc::UnboundedSender<HashMap<String, String>>,
+    req: Request<Body>,
+) -> Response<Body> {
+    if req.uri().path() != CALLBACK_PATH {
+        let mut resp = Response::new(Body::empty());
+        *resp.status_mut() = StatusCode::NOT_FOUND;
+        return resp;
+    }
+    let params = url::form_urlencoded::parse(req.uri().query().unwrap_or_default().as_bytes())
+        .into_owned()
+        .collect();
+    let _ = tx.send(params);
+    Response::new(Body::from(
+        "Authorization completed, you can close this page now.",
+    ))
+}
+
+// 授权码模式：本机监听回调地址，使用open（一般为浏览器）打开授权页面，授权后获取code
+// 返回code、redirect_uri与PKCE的code_verifier
+async fn authorize<F>(
+    config: &OAuthConfig,
+    open: F,
+) -> Result<(String, String, String), CyberAPIError>
+where
+    F: FnOnce(&str) -> Result<(), CyberAPIError>,
+{
+    if config.authorize_url.is_empty() {
+        return Err(oauth_error("authorize url can not be empty".to_string()));
+    }
+    let verifier = random_string(32);
+    let challenge = general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+    let state = random_string(16);
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let make_service = make_service_fn(move |_| {
+        let tx = tx.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let resp = handle_callback(&tx, req);
+                async move { Ok::<_, Infallible>(resp) }
+            }))
+        }
+    });
+    let addr = SocketAddr::from(([127, 0, 0, 1], config.redirect_port));
+    let server = Server::try_bind(&addr)?.serve(make_service);
+    let redirect_uri = format!(
+        "http://127.0.0.1:{}{}",
+        server.local_addr().port(),
+        CALLBACK_PATH
+    );
+    let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+    tokio::spawn(server.with_graceful_shutdown(async {
+        shutdown_rx.await.ok();
+    }));
+
+    let mut url = Url::parse(&config.authorize_url)?;
+    {
+        let mut query = url.query_pairs_mut();
+        query
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &config.client_id)
+            .append_pair("redirect_uri", &redirect_uri)
+            .append_pair("state", &state)
+            .append_pair("code_challenge", &challenge)
+            .append_pair("code_challenge_method", "S256");
+        if !config.scope.is_empty() {
+            query.append_pair("scope", &config.scope);
+        }
+        if !config.audience.is_empty() {
+            query.append_pair("audience", &config.audience);
+        }
+    }
+    let result = match open(url.as_str()) {
+        Ok(()) => tokio::time::timeout(AUTHORIZE_TIMEOUT, rx.recv()).await,
+        Err(err) => {
+            let _ = shutdown.send(());
+            return Err(err);
+        }
+    };
+    let _ = shutdown.send(());
+
+    let params = match result {
+        Ok(Some(params)) => params,
+        _ => return Err(oauth_error("wait for authorization timeout".to_string())),
+    };
+    if params.get("state") != Some(&state) {
+        return Err(oauth_error("state of authorization is invalid".to_string()));
+    }
+    if let Some(error) = params.get("error") {
+        return Err(oauth_error(format!(
+            "authorization fail, {} {}",
+            error,
+            params.get("error_description").cloned().unwrap_or_default()
+        )));
+    }
+    let code = params
+        .get("code")
+        .cloned()
+        .ok_or_else(|| oauth_error("code of authorization is empty".to_string()))?;
+    Ok((code, redirect_uri, verifier))
+}
+
+fn get_token_lock(lock_key: &str) -> Arc<Mutex<()>> {
+    TOKEN_LOCKS
+        .lock()
+        .unwrap()
+        .entry(lock_key.to_string())
+        .or_default()
+        .clone()
+}
+
+// 浏览器中授权的标记，drop时移除并通知等待的请求
+struct PendingAuthorization {
+    lock_key: String,
+    _done: watch::Sender<()>,
+}
+
+impl PendingAuthorization {
+    fn new(lock_key: &str) -> Self {
+        let (done, rx) = watch::channel(());
+        AUTHORIZING.lock().unwrap().insert(lock_key.to_string(), rx);
+        PendingAuthorization {
+            lock_key: lock_key.to_string(),
+            _done: done,
+        }
+    }
+}
+
+impl Drop for PendingAuthorization {
+    fn drop(&mut self) {
+        AUTHORIZING.lock().unwrap().remove(&self.lock_key);
+    }
+}
+
+fn new_token(
+    config: &OAuthConfig,
+    scope: &TokenScope,
+    key: String,
+    resp: TokenResponse,
+) -> OAuthToken {
+    let expires_at = resp
+        .get_expires_in()
+        .map(|value| (Utc::now() + chrono::Duration::seconds(value)).to_rfc3339())
+        .unwrap_or_default();
+    OAuthToken {
+        collection: scope.collection.clone(),
+        environment: scope.environment.clone(),
+        key,
+        access_token: resp.access_token,
+        token_type: resp.token_type,
+        refresh_token: resp.refresh_token,
+        scope: if resp.scope.is_empty() {
+            config.scope.clone()
+        } else {
+            resp.scope
+        },
+        expires_at,
+        ..Default::default()
+    }
+}
+
+async fn save_token(lock_key: &str, token: OAuthToken) -> Result<OAuthToken, CyberAPIError> {
+    // 未保存的请求（collection为空）不缓存token
+    if token.collection.is_empty() {
+        return Ok(token);
+    }
+    let token = schemas::save_oauth_token(token).await?;
+    let mut memory_tokens = MEMORY_TOKENS.lock().unwrap();
+    // 未保存至数据库时id为空
+    if token.id.is_empty() {
+        memory_tokens.insert(lock_key.to_string(), token.clone());
+    } else {
+        memory_tokens.remove(lock_key);
+    }
+    Ok(token)
+}
+
+// 删除collection缓存于内存中的token，返回删除的数量
+pub fn delete_memory_tokens(collection: &str) -> u64 {
+    let mut memory_tokens = MEMORY_TOKENS.lock().unwrap();
+    let count = memory_tokens.len();
+    memory_tokens.retain(|_, token| token.collection != collection);
+    (count - memory_tokens.len()) as u64
+}
+
+// 获取token，优先使用缓存，过期时使用refresh token刷新，刷新失败则重新获取
+// rejected为服务端拒绝（响应401）的token，缓存的token与其一致时不再使用
+// 授权码模式等待浏览器中授权时不加锁，相同token的其它请求等待授权完成后使用其结果
+pub async fn get_token(
+    config: &OAuthConfig,
+    scope: &TokenScope,
+    timeout: &RequestTimeout,
+    rejected: Option<&str>,
+) -> Result<OAuthToken, CyberAPIError> {
+    let key = config.cache_key();
+    let lock_key = format!("{}:{}:{}", scope.collection, scope.environment, key);
+    let lock = get_token_lock(&lock_key);
+    let guard = loop {
+        let pending = AUTHORIZING.lock().unwrap().get(&lock_key).cloned();
+        if let Some(mut pending) = pending {
+            // 授权完成时发送方drop，返回出错
+            let _ = pending.changed().await;
+        }
+        let guard = lock.lock().await;
+        // 等待锁的过程中可能已开始授权
+        if !AUTHORIZING.lock().unwrap().contains_key(&lock_key) {
+            break guard;
+        }
+    };
+    let cached = if scope.collection.is_empty() {
+        None
+    } else {
+        schemas::find_oauth_token(&scope.collection, &scope.environment, &key)
+            .await?
+            .or_else(|| MEMORY_TOKENS.lock().unwrap().get(&lock_key).cloned())
+    };
+    let mut refresh_token = "".to_string();
+    if let Some(token) = cached {
+        if !token.is_expired() && rejected != Some(token.access_token.as_str()) {
+            return Ok(token);
+        }
+        refresh_token = token.refresh_token;
+    }
+
+    if !refresh_token.is_empty() {
+        let params = vec![
+            ("grant_type", "refresh_token".to_string()),
+            ("refresh_token", refresh_token.clone()),
+        ];
+        // refresh token失效时重新获取
+        if let Ok(mut resp) = request_token(config, params, timeout).await {
+            // 刷新时未返回新的refresh token，则继续使用原有的
+            if resp.refresh_token.is_empty() {
+                resp.refresh_token = refresh_token;
+            }
+            return save_token(&lock_key, new_token(config, scope, key, resp)).await;
+        }
+    }
+
+    let params = match config.grant_type {
+        OAuthGrantType::ClientCredentials => vec![
+            ("grant_type", "client_credentials".to_string()),
+            ("scope", config.scope.clone()),
+            ("audience", config.audience.clone()),
+        ],
+        OAuthGrantType::Password => vec![
+            ("grant_type", "password".to_string()),
+            ("username", config.username.clone()),
+            ("password", config.password.clone()),
+            ("scope", config.scope.clone()),
+            ("audience", config.audience.clone()),
+        ],
+        OAuthGrantType::AuthorizationCode => {
+            let _pending = PendingAuthorization::new(&lock_key);
+            drop(guard);
+            let (code, redirect_uri, verifier) = authorize(config, open_browser).await?;
+            let params = vec![
+                ("grant_type", "authorization_code".to_string()),
+                ("code", code),
+                ("redirect_uri", redirect_uri),
+                ("code_verifier", verifier),
+            ];
+            let resp = request_token(config, params, timeout).await?;
+            return save_token(&lock_key, new_token(config, scope, key, resp)).await;
+        }
+    };
+    let resp = request_token(config, params, timeout).await?;
+    save_token(&lock_key, new_token(config, scope, key, resp)).await
+}
+
+// 获取collection当前环境的token，配置中的变量使用collection的变量替换
+pub async fn get_collection_token(
+    collection: String,
+    config: OAuthConfig,
+) -> Result<OAuthToken, CyberAPIError> {
+    let context = schemas::get_template_context(Some(collection.clone()), vec![]).await?;
+    let mut renderer = context.renderer();
+    let config = config.render(&mut renderer)?;
+    renderer.finish()?;
+    let environment = schemas::get_active_environment(collection.clone())
+        .await?
+        .map(|item| item.id)
+        .unwrap_or_default();
+    let timeout = RequestTimeout {
+        connect: 10,
+        write: 30,
+        read: 30,
+    };
+    get_token(
+        &config,
+        &TokenScope {
+            collection,
+            environment,
+        },
+        &timeout,
+        None,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
+    use serde_json::json;
+    use std::collections::HashSet;
+
+    const CLIENT_ID: &str = "cyberapi";
+    const CLIENT_SECRET: &str = "secret";
+
+    // 模拟的授权服务，token与refresh token按次序生成
+    struct AuthServerState {
+        issued: u32,
+        expires_in: i64,
+        grants: Vec<String>,
+        access_tokens: HashSet<String>,
+        refresh_tokens: HashSet<String>,
+        // 授权码对应的code_challenge与redirect_uri
+        codes: HashMap<String, (String, String)>,
+    }
+
+    struct AuthServer {
+        url: String,
+        state: Arc<std::sync::Mutex<AuthServerState>>,
+    }
+
+    impl AuthServer {
+        fn grants(&self) -> Vec<String> {
+            self.state.lock().unwrap().grants.clone()
+        }
+        fn set_expires_in(&self, expires_in: i64) {
+            self.state.lock().unwrap().expires_in = expires_in;
+        }
+        fn config(&self, grant_type: OAuthGrantType) -> OAuthConfig {
+            OAuthConfig {
+                grant_type,
+                token_url: format!("{}/token", self.url),
+                authorize_url: format!("{}/authorize", self.url),
+                client_id: CLIENT_ID.to_string(),
+                client_secret: CLIENT_SECRET.to_string(),
+                username: "alice".to_string(),
+                password: "wonderland".to_string(),
+                ..Default::default()
+            }
+        }
+    }
+
+    fn json_response(status: StatusCode, value: serde_json::Value) -> Response<Body> {
+        let mut resp = Response::new(Body::from(value.to_string()));
+        *resp.status_mut() = status;
+        resp.headers_mut()
+            .insert(CONTENT_TYPE, "application/json".parse().unwrap());
+        resp
+    }
+
+    async fn handle_request(
+        state: Arc<std::sync::Mutex<AuthServerState>>,
+        req: Request<Body>,
+    ) -> Response<Body> {
+        let authorization = req
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        // 受保护的接口，token无效时返回401
+        if req.uri().path() == "/api" {
+            let valid = authorization
+                .strip_prefix("Bearer ")
+                .map(|token| state.lock().unwrap().access_tokens.contains(token))
+                .unwrap_or_default();
+            let status = if valid {
+                StatusCode::OK
+            } else {
+                StatusCode::UNAUTHORIZED
+            };
+            return json_response(status, json!({}));
+        }
+
+        let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+        let params: HashMap<String, String> =
+            url::form_urlencoded::parse(&body).into_owned().collect();
+        let param = |name: &str| params.get(name).cloned().unwrap_or_default();
+        let client = match authorization.strip_prefix("Basic ") {
+            Some(value) => {
+                String::from_utf8(general_purpose::STANDARD.decode(value).unwrap()).unwrap()
+            }
+            None => format!("{}:{}", param("client_id"), param("client_secret")),
+        };
+        let confidential = client == format!("{}:{}", CLIENT_ID, CLIENT_SECRET);
+        let grant_type = param("grant_type");
+
+        let mut state = state.lock().unwrap();
+        state.grants.push(grant_type.clone());
+        let valid = match grant_type.as_str() {
+            "client_credentials" => confidential,
+            "password" => {
+                confidential && param("username") == "alice" && param("password") == "wonderland"
+            }
+            // refresh token仅能使用一次
+            "refresh_token" => confidential && state.refresh_tokens.remove(&param("refresh_token")),
+            "authorization_code" => match state.codes.remove(&param("code")) {
+                Some((challenge, redirect_uri)) => {
+                    let verifier = param("code_verifier");
+                    client == format!("{}:", CLIENT_ID)
+                        && redirect_uri == param("redirect_uri")
+                        && challenge
+                            == general_purpose::URL_SAFE_NO_PAD
+                                .encode(Sha256::digest(verifier.as_bytes()))
+                }
+                None => false,
+            },
+            _ => false,
+        };
+        if !valid {
+            return json_response(
+                StatusCode::BAD_REQUEST,
+                json!({
+                    "error": "invalid_grant",
+                    "error_description": grant_type,
+                }),
+            );
+        }
+        state.issued += 1;
+        let access_token = format!("access-{}", state.issued);
+        state.access_tokens.insert(access_token.clone());
+        let mut resp = json!({
+            "access_token": access_token,
+            "token_type": "Bearer",
+            "expires_in": state.expires_in,
+        });
+        if grant_type != "client_credentials" {
+            let refresh_token = format!("refresh-{}", state.issued);
+            state.refresh_tokens.insert(refresh_token.clone());
+            resp["refresh_token"] = json!(refresh_token);
+        }
+        json_response(StatusCode::OK, resp)
+    }
+
+    async fn start_server() -> AuthServer {
+        let state = Arc::new(std::sync::Mutex::new(AuthServerState {
+            issued: 0,
+            expires_in: 3600,
+            grants: vec![],
+            access_tokens: HashSet::new(),
+            refresh_tokens: HashSet::new(),
+            codes: HashMap::new(),
+        }));
+        let server_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = server_state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    let state = state.clone();
+                    async move { Ok::<_, Infallible>(handle_request(state, req).await) }
+                }))
+            }
+        });
+        let server = Server::try_bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .unwrap()
+            .serve(make_service);
+        let url = format!("http://127.0.0.1:{}", server.local_addr().port());
+        tokio::spawn(server);
+        AuthServer { url, state }
+    }
+
+    // 新建collection用于缓存token（外键约束），返回的读锁保证测试期间密钥已解锁
+    async fn new_scope(
+        collection: &str,
+    ) -> (TokenScope, tokio::sync::RwLockReadGuard<'static, ()>) {
+        let secret = schemas::init_test_database().await;
+        schemas::add_api_collection(schemas::APICollection {
+            id: collection.to_string(),
+            name: Some(collection.to_string()),
+            description: None,
+            setting: None,
+            created_at: None,
+            updated_at: None,
+        })
+        .await
+        .unwrap();
+        (
+            TokenScope {
+                collection: collection.to_string(),
+                environment: "".to_string(),
+            },
+            secret,
+        )
+    }
+
+    fn new_timeout() -> RequestTimeout {
+        RequestTimeout {
+            connect: 5,
+            write: 5,
+            read: 5,
+        }
+    }
+
+    // 模拟浏览器中授权：记录code_challenge后直接请求回调地址
+    fn approve(server: &AuthServer, url: &str, state: Option<&str>) -> Result<(), CyberAPIError> {
+        let url = Url::parse(url)?;
+        let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert_eq!(params["response_type"], "code");
+        assert_eq!(params["code_challenge_method"], "S256");
+        let redirect_uri = params["redirect_uri"].clone();
+        let code = format!("code-{}", params["state"]);
+        server.state.lock().unwrap().codes.insert(
+            code.clone(),
+            (params["code_challenge"].clone(), redirect_uri.clone()),
+        );
+        let callback = format!(
+            "{}?code={}&state={}",
+            redirect_uri,
+            form_encode(&code),
+            form_encode(state.unwrap_or(&params["state"]))
+        );
+        tokio::spawn(async move {
+            hyper::Client::new()
+                .get(callback.parse().unwrap())
+                .await
+                .unwrap();
+        });
+        Ok(())
+    }
+
+    #[test]
+    fn client_credentials_token_is_cached() {
+        tauri::async_runtime::block_on(async {
+            let server = start_server().await;
+            let (scope, _secret) = new_scope("oauth-client-credentials").await;
+            let config = server.config(OAuthGrantType::ClientCredentials);
+
+            let token = get_token(&config, &scope, &new_timeout(), None)
+                .await
+                .unwrap();
+            assert_eq!(token.access_token, "access-1");
+            assert!(token.refresh_token.is_empty());
+            assert!(!token.is_expired());
+
+            let token = get_token(&config, &scope, &new_timeout(), None)
+                .await
+                .unwrap();
+            assert_eq!(token.access_token, "access-1");
+            assert_eq!(server.grants(), vec!["client_credentials"]);
+        });
+    }
+
+    #[test]
+    fn token_is_cached_in_memory_without_secret_key() {
+        tauri::async_runtime::block_on(async {
+            let server = start_server().await;
+            drop(new_scope("oauth-memory-token").await);
+            schemas::with_secret_locked(|| async {
+                let scope = TokenScope {
+                    collection: "oauth-memory-token".to_string(),
+                    environment: "".to_string(),
+                };
+                let config = server.config(OAuthGrantType::ClientCredentials);
+
+                let token = get_token(&config, &scope, &new_timeout(), None)
+                    .await
+                    .unwrap();
+                assert_eq!(token.access_token, "access-1");
+                assert!(token.id.is_empty());
+
+                let token = get_token(&config, &scope, &new_timeout(), None)
+                    .await
+                    .unwrap();
+                assert_eq!(token.access_token, "access-1");
+                assert_eq!(server.grants(), vec!["client_credentials"]);
+
+                assert_eq!(delete_memory_tokens(&scope.collection), 1);
+            })
+            .await;
+        });
+    }
+
+    #[test]
+    fn client_secret_in_body() {
+        tauri::async_runtime::block_on(async {
+            let server = start_server().await;
+            let mut config = server.config(OAuthGrantType::ClientCredentials);
+            config.client_authentication = ClientAuthentication::Body;
+
+            // 未保存的请求不缓存
+            let scope = TokenScope::default();
+            let token = get_token(&config, &scope, &new_timeout(), None)
+                .await
+                .unwrap();
+            assert_eq!(token.access_token, "access-1");
+            let token = get_token(&config, &scope, &new_timeout(), None)
+                .await
+                .unwrap();
+            assert_eq!(token.access_token, "access-2");
+        });
+    }
+
+    #[test]
+    fn password_token_with_refresh_rotation() {
+        tauri::async_runtime::block_on(async {
+            let server = start_server().await;
+            let (scope, _secret) = new_scope("oauth-password").await;
+            let config = server.config(OAuthGrantType::Password);
+
+            server.set_expires_in(0);
+            let token = get_token(&config, &scope, &new_timeout(), None)
+                .await
+                .unwrap();
+            assert_eq!(token.access_token, "access-1");
+            assert_eq!(token.refresh_token, "refresh-1");
+            assert!(token.is_expired());
+
+            server.set_expires_in(3600);
+            let token = get_token(&config, &scope, &new_timeout(), None)
+                .await
+                .unwrap();
+            assert_eq!(token.access_token, "access-2");
+            assert_eq!(token.refresh_token, "refresh-2");
+            assert!(!server
+                .state
+                .lock()
+                .unwrap()
+                .refresh_tokens
+                .contains("refresh-1"));
+
+            // 新的refresh token已缓存
+            let cached = schemas::find_oauth_token(&scope.collection, "", &config.cache_key())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(cached.access_token, "access-2");
+            assert_eq!(cached.refresh_token, "refresh-2");
+            assert_eq!(server.grants(), vec!["password", "refresh_token"]);
+        });
+    }
+
+    #[test]
+    fn refresh_failure_falls_back_to_full_fetch() {
+        tauri::async_runtime::block_on(async {
+            let server = start_server().await;
+            let (scope, _secret) = new_scope("oauth-refresh-failure").await;
+            let config = server.config(OAuthGrantType::Password);
+
+            server.set_expires_in(0);
+            get_token(&config, &scope, &new_timeout(), None)
+                .await
+                .unwrap();
+            server.state.lock().unwrap().refresh_tokens.clear();
+
+            server.set_expires_in(3600);
+            let token = get_token(&config, &scope, &new_timeout(), None)
+                .await
+                .unwrap();
+            assert_eq!(token.access_token, "access-2");
+            assert_eq!(token.refresh_token, "refresh-2");
+            assert_eq!(
+                server.grants(),
+                vec!["password", "refresh_token", "password"]
+            );
+        });
+    }
+
+    #[test]
+    fn authorization_code_with_pkce() {
+        tauri::async_runtime::block_on(async {
+            let server = start_server().await;
+            let mut config = server.config(OAuthGrantType::AuthorizationCode);
+            config.client_secret = "".to_string();
+
+            let (code, redirect_uri, verifier) =
+                authorize(&config, |url| approve(&server, url, None))
+                    .await
+                    .unwrap();
+            assert!(redirect_uri.ends_with(CALLBACK_PATH));
+            let params = vec![
+                ("grant_type", "authorization_code".to_string()),
+                ("code", code.clone()),
+                ("redirect_uri", redirect_uri.clone()),
+                ("code_verifier", "invalid".to_string()),
+            ];
+            // code_verifier与code_challenge不一致
+            let err = request_token(&config, params, &new_timeout())
+                .await
+                .err()
+                .unwrap();
+            assert!(err.message().contains("invalid_grant"));
+
+            let (code, redirect_uri, verifier_next) =
+                authorize(&config, |url| approve(&server, url, None))
+                    .await
+                    .unwrap();
+            assert_ne!(verifier, verifier_next);
+            let params = vec![
+                ("grant_type", "authorization_code".to_string()),
+                ("code", code),
+                ("redirect_uri", redirect_uri),
+                ("code_verifier", verifier_next),
+            ];
+            let resp = request_token(&config, params, &new_timeout())
+                .await
+                .unwrap();
+            assert_eq!(resp.access_token, "access-1");
+            assert_eq!(resp.refresh_token, "refresh-1");
+        });
+    }
+
+    #[test]
+    fn authorization_state_mismatch_is_rejected() {
+        tauri::async_runtime::block_on(async {
+            let server = start_server().await;
+            let config = server.config(OAuthGrantType::AuthorizationCode);
+
+            let err = authorize(&config, |url| approve(&server, url, Some("forged")))
+                .await
+                .err()
+                .unwrap();
+            assert_eq!(err.message(), "state of authorization is invalid");
+        });
+    }
+
+    #[test]
+    fn rejected_token_is_renewed_on_401() {
+        tauri::async_runtime::block_on(async {
+            let server = start_server().await;
+            let (scope, _secret) = new_scope("oauth-unauthorized").await;
+            let req = HTTPRequest {
+                method: "GET".to_string(),
+                uri: format!("{}/api", server.url),
+                body: "".to_string(),
+                content_type: "".to_string(),
+                headers: vec![],
+                query: vec![],
+                variables: vec![],
+                cookie_jar: CookieJar::Disabled,
+                cookie_scope: Default::default(),
+                retry: None,
+                auth: Some(HTTPAuth::OAuth2(server.config(OAuthGrantType::Password))),
+                token_scope: scope,
+            };
+
+            let resp = http_request::send(&req, &new_timeout()).await.unwrap();
+            assert_eq!(resp.status, 200);
+            assert_eq!(server.grants(), vec!["password"]);
+
+            // 服务端撤销token后，使用refresh token重新获取并再次请求
+            server.state.lock().unwrap().access_tokens.clear();
+            let resp = http_request::send(&req, &new_timeout()).await.unwrap();
+            assert_eq!(resp.status, 200);
+            assert_eq!(server.grants(), vec!["password", "refresh_token"]);
+        });
+    }
+}
@@ -0,0 +1,190 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::error::{CyberAPIError, ErrorCode, RequestPhase};
+use crate::http_request::{self, HTTPRawResponse, HTTPRequest, RequestTimeout};
+
+fn default_retry_statuses() -> Vec<u16> {
+    vec![429, 502, 503, 504]
+}
+
+fn default_base_delay() -> u64 {
+    200
+}
+
+fn default_max_delay() -> u64 {
+    10_000
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// 请求的重试策略，可在请求或collection的配置中设置，请求的优先
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    // 最大尝试次数（包括首次请求），小于等于1则不重试
+    #[serde(default)]
+    pub max_attempts: u32,
+    // 需要重试的出错代码，为空则按出错是否可重试（retryable）判断
+    #[serde(default)]
+    pub error_codes: Vec<ErrorCode>,
+    // 需要重试的响应状态码
+    #[serde(default = "default_retry_statuses")]
+    pub statuses: Vec<u16>,
+    // 首次重试前的等待时长(ms)，之后每次翻倍
+    #[serde(default = "default_base_delay")]
+    pub base_delay: u64,
+    // 最大等待时长(ms)，Retry-After超过此值则不再重试
+    #[serde(default = "default_max_delay")]
+    pub max_delay: u64,
+    // 是否按响应的Retry-After等待
+    #[serde(default = "default_true")]
+    pub respect_retry_after: bool,
+    // 非幂等的请求（如POST）在请求已发出后出错或返回重试状态码时是否重试
+    #[serde(default)]
+    pub non_idempotent: bool,
+}
+
+// 每次请求的结果
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryAttempt {
+    // 第几次请求，从1开始
+    pub attempt: u32,
+    pub started_at: String,
+    // 请求耗时(ms)
+    pub latency: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<CyberAPIError>,
+    // 重试前的等待时长(ms)，不再重试则为0
+    pub delay: u32,
+}
+
+fn is_idempotent(method: &str) -> bool {
+    !matches!(method.to_uppercase().as_str(), "POST" | "PATCH" | "CONNECT")
+}
+
+impl RetryPolicy {
+    fn should_retry_error(&self, method: &str, err: &CyberAPIError) -> bool {
+        let matched = if self.error_codes.is_empty() {
+            err.retryable()
+        } else {
+            self.error_codes.contains(&err.code())
+        };
+        if !matched {
+            return false;
+        }
+        if self.non_idempotent || is_idempotent(method) {
+            return true;
+        }
+        // 未建立连接时请求还未发出，非幂等的请求也可重试
+        matches!(
+            err.phase(),
+            Some(RequestPhase::Dns) | Some(RequestPhase::Connect) | Some(RequestPhase::Tls)
+        )
+    }
+    // 指数退避，等待时长在[delay/2, delay]之间随机
+    fn backoff(&self, retry: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << retry.min(20))
+            .min(self.max_delay);
+        let half = delay / 2;
+        let jitter = if half == 0 {
+            0
+        } else {
+            (ulid::Ulid::new().random() as u64) % (half + 1)
+        };
+        Duration::from_millis(half + jitter)
+    }
+}
+
+// 解析Retry-After，支持秒数与HTTP-date
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let millis = (date.with_timezone(&Utc) - Utc::now()).num_milliseconds();
+    Some(Duration::from_millis(millis.max(0) as u64))
+}
+
+// 按重试策略发送请求，返回最后一次的响应与每次请求的结果
+// 最后一次仍出错时，每次请求的结果记录在出错的details.attempts中
+pub async fn send(
+    http_request: &HTTPRequest,
+    timeout: &RequestTimeout,
+) -> Result<(HTTPRawResponse, Vec<RetryAttempt>), CyberAPIError> {
+    let policy = match &http_request.retry {
+        Some(policy) if policy.max_attempts > 1 => policy,
+        _ => {
+            let resp = http_request::send(http_request, timeout).await?;
+            return Ok((resp, vec![]));
+        }
+    };
+    let mut attempts = vec![];
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let started_at = Utc::now().to_rfc3339();
+        let start = Instant::now();
+        let result = http_request::send(http_request, timeout).await;
+        let latency = start.elapsed().as_millis() as u32;
+        let last = attempt >= policy.max_attempts;
+
+        let delay = match &result {
+            Ok(resp) if !last && policy.statuses.contains(&resp.status) => {
+                let retry_after = resp
+                    .headers
+                    .get("retry-after")
+                    .and_then(|values| values.first())
+                    .and_then(|value| parse_retry_after(value))
+                    .filter(|_| policy.respect_retry_after);
+                match retry_after {
+                    // 等待时长过长则不再重试
+                    Some(value) if value > Duration::from_millis(policy.max_delay) => None,
+                    // 429与503带有Retry-After表示请求未被处理，非幂等的请求也可重试
+                    Some(value) if matches!(resp.status, 429 | 503) => Some(value),
+                    _ if !policy.non_idempotent && !is_idempotent(&http_request.method) => None,
+                    Some(value) => Some(value),
+                    None => Some(policy.backoff(attempt - 1)),
+                }
+            }
+            Err(err) if !last && policy.should_retry_error(&http_request.method, err) => {
+                Some(policy.backoff(attempt - 1))
+            }
+            _ => None,
+        };
+        let (status, error) = match &result {
+            Ok(resp) => (Some(resp.status), None),
+            Err(err) => (None, Some(err.clone())),
+        };
+        attempts.push(RetryAttempt {
+            attempt,
+            started_at,
+            latency,
+            status,
+            error,
+            delay: delay
+                .map(|value| value.as_millis() as u32)
+                .unwrap_or_default(),
+        });
+        match delay {
+            Some(delay) => tokio::time::sleep(delay).await,
+            None => {
+                return match result {
+                    Ok(resp) => Ok((resp, attempts)),
+                    Err(err) => Err(err.with_details(serde_json::json!({
+                        "attempts": attempts,
+                    }))),
+                };
+            }
+        }
+    }
+}
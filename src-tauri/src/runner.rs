@@ -0,0 +1,672 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use crate::assertion::AssertionResult;
+use crate::auth::HTTPAuth;
+use crate::cookies::{CookieJar, CookieScope};
+use crate::error::CyberAPIError;
+use crate::extraction::{value_to_string, ExtractionResult};
+use crate::http_request::{self, HTTPRequest, HTTPRequestKVParam, RequestTimeout};
+use crate::oauth::TokenScope;
+use crate::retry::RetryPolicy;
+use crate::schemas::{self, APISetting};
+use crate::template::VariableScope;
+
+// 每个请求执行完成后触发的事件
+pub const RUNNER_PROGRESS_EVENT: &str = "runner-progress";
+
+fn default_iterations() -> u32 {
+    1
+}
+
+fn default_timeout() -> RequestTimeout {
+    RequestTimeout {
+        connect: 10,
+        write: 120,
+        read: 300,
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RunnerOptions {
+    pub collection: String,
+    // 执行的目录，为空则执行整个collection
+    #[serde(default)]
+    pub folder: String,
+    // 顶层目录与API的顺序，由前端保存，未指定的排在前面
+    #[serde(default)]
+    pub top_tree_items: Vec<String>,
+    // 执行的轮数，指定数据文件时按数据的行数执行
+    #[serde(default = "default_iterations")]
+    pub iterations: u32,
+    // 每个请求之间的间隔(ms)
+    #[serde(default)]
+    pub delay: u64,
+    // 失败时停止执行
+    #[serde(default)]
+    pub stop_on_failure: bool,
+    #[serde(default = "default_timeout")]
+    pub timeout: RequestTimeout,
+    // 数据文件(csv或json)，每行的值作为该轮的变量
+    #[serde(default)]
+    pub data_file: String,
+    // 仅执行数据文件中的这些行（从1开始），为空则执行所有行
+    #[serde(default)]
+    pub rows: Vec<usize>,
+}
+
+// 单个请求的执行结果
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RunnerStep {
+    // 第几轮，从1开始
+    pub iteration: u32,
+    // 在该轮中的序号，从1开始
+    pub index: usize,
+    // 数据文件的行号，从1开始，无数据文件时为0
+    #[serde(default)]
+    pub row: usize,
+    // 该行的数据
+    #[serde(default)]
+    pub data: HashMap<String, String>,
+    pub api: String,
+    pub name: String,
+    pub method: String,
+    pub uri: String,
+    // 请求失败时为0
+    pub status: u16,
+    // 耗时(ms)
+    pub duration: u32,
+    pub body_size: u32,
+    // 请求成功且断言均通过
+    pub passed: bool,
+    // 请求的出错信息
+    pub error: String,
+    pub extractions: Vec<ExtractionResult>,
+    pub assertions: Vec<AssertionResult>,
+}
+
+// 执行进度，每个请求完成后推送
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RunnerProgress {
+    pub id: String,
+    // 已完成的请求数
+    pub completed: usize,
+    // 所有轮次的请求总数
+    pub total: usize,
+    pub step: RunnerStep,
+}
+
+// 执行的汇总报告
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RunnerReport {
+    pub id: String,
+    pub collection: String,
+    pub folder: String,
+    #[serde(default)]
+    pub data_file: String,
+    // 数据文件的列
+    #[serde(default)]
+    pub columns: Vec<String>,
+    pub iterations: u32,
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    // 因失败而提前停止
+    pub stopped: bool,
+    // 执行失败的数据行，用于仅重新执行失败的行
+    #[serde(default)]
+    pub failed_rows: Vec<usize>,
+    // 总耗时(ms)
+    pub duration: u32,
+    pub started_at: String,
+    pub finished_at: String,
+    pub steps: Vec<RunnerStep>,
+}
+
+// 保存在API配置中的请求，字段均可能缺失
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct StoredRequest {
+    #[serde(default)]
+    method: String,
+    #[serde(default)]
+    uri: String,
+    #[serde(default)]
+    body: String,
+    #[serde(default)]
+    content_type: String,
+    #[serde(default)]
+    headers: Vec<HTTPRequestKVParam>,
+    #[serde(default)]
+    query: Vec<HTTPRequestKVParam>,
+    #[serde(default)]
+    auth: Vec<HTTPRequestKVParam>,
+    #[serde(default)]
+    variables: Vec<HTTPRequestKVParam>,
+    #[serde(default)]
+    cookie_jar: CookieJar,
+    #[serde(default)]
+    retry: Option<RetryPolicy>,
+    // 认证配置，未设置则使用auth中的Basic认证
+    #[serde(default)]
+    auth_config: Option<HTTPAuth>,
+}
+
+fn new_kv_param(key: &str, value: &str) -> HTTPRequestKVParam {
+    HTTPRequestKVParam {
+        key: key.to_string(),
+        value: value.to_string(),
+        enabled: true,
+    }
+}
+
+fn is_json(value: &str) -> bool {
+    let value = value.trim();
+    (value.starts_with('{') && value.ends_with('}'))
+        || (value.starts_with('[') && value.ends_with(']'))
+}
+
+// 与前端发送请求时的处理一致，body函数（如{{md5(...)}}）不支持
+fn convert_request(
+    setting: &str,
+    global_headers: &[(String, String)],
+) -> Result<HTTPRequest, CyberAPIError> {
+    let req: StoredRequest = if setting.is_empty() {
+        StoredRequest::default()
+    } else {
+        serde_json::from_str(setting)?
+    };
+    if req.uri.is_empty() {
+        return Err(CyberAPIError::new(
+            "uri of request is empty".to_string(),
+            "runner",
+        ));
+    }
+    let method = if req.method.is_empty() {
+        "GET".to_string()
+    } else {
+        req.method.to_uppercase()
+    };
+    let mut body = req.body;
+    let mut content_type = req.content_type;
+    // 非此类请求，将body设置为空
+    if !["POST", "PATCH", "PUT"].contains(&method.as_str()) {
+        body = "".to_string();
+        content_type = "".to_string();
+    }
+    // 删除json中的注释行
+    if is_json(&body) {
+        body = body
+            .lines()
+            .filter(|line| !line.trim().starts_with("//"))
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+    if !body.is_empty() && content_type == "application/x-www-form-urlencoded" {
+        let params: Vec<HTTPRequestKVParam> = serde_json::from_str(&body)?;
+        let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+        for item in params.iter().filter(|item| item.enabled) {
+            serializer.append_pair(&item.key, &item.value);
+        }
+        body = serializer.finish();
+    }
+    if !body.is_empty() && content_type == "multipart/form-data" {
+        return Err(CyberAPIError::new(
+            "multipart form is not supported by runner".to_string(),
+            "runner",
+        ));
+    }
+
+    let mut headers = req.headers;
+    for (name, value) in global_headers {
+        headers.push(new_kv_param(name, value));
+    }
+    headers.push(new_kv_param(
+        "User-Agent",
+        &format!("CyberAPI/{} (runner)", env!("CARGO_PKG_VERSION")),
+    ));
+    let auth = req.auth_config.or_else(|| {
+        req.auth
+            .iter()
+            .find(|item| item.enabled)
+            .map(|item| HTTPAuth::Basic {
+                username: item.key.clone(),
+                password: item.value.clone(),
+            })
+    });
+
+    Ok(HTTPRequest {
+        method,
+        uri: req.uri,
+        body,
+        content_type,
+        headers,
+        query: req.query,
+        variables: req.variables,
+        cookie_jar: req.cookie_jar,
+        cookie_scope: CookieScope::default(),
+        retry: req.retry,
+        auth,
+        token_scope: TokenScope::default(),
+    })
+}
+
+// 数据文件的一行，行号从1开始
+struct DataRow {
+    row: usize,
+    values: Vec<(String, String)>,
+}
+
+// json为对象数组，其它则以首行为列名的csv
+fn load_data_file(file: &str) -> Result<(Vec<String>, Vec<DataRow>), CyberAPIError> {
+    let mut columns: Vec<String> = vec![];
+    let mut rows = vec![];
+    if file.to_lowercase().ends_with(".json") {
+        let data: Vec<serde_json::Map<String, serde_json::Value>> =
+            serde_json::from_slice(&std::fs::read(file)?)?;
+        for (index, item) in data.into_iter().enumerate() {
+            let mut values = vec![];
+            for (key, value) in item {
+                if !columns.contains(&key) {
+                    columns.push(key.clone());
+                }
+                values.push((key, value_to_string(&value)));
+            }
+            rows.push(DataRow {
+                row: index + 1,
+                values,
+            });
+        }
+    } else {
+        let mut reader = csv::ReaderBuilder::new().flexible(true).from_path(file)?;
+        columns = reader
+            .headers()?
+            .iter()
+            .map(|item| item.trim().to_string())
+            .collect();
+        for (index, record) in reader.records().enumerate() {
+            let values = columns
+                .iter()
+                .zip(record?.iter())
+                .filter(|(name, _)| !name.is_empty())
+                .map(|(name, value)| (name.clone(), value.to_string()))
+                .collect();
+            rows.push(DataRow {
+                row: index + 1,
+                values,
+            });
+        }
+    }
+    Ok((columns, rows))
+}
+
+fn collect_folder_settings(
+    id: &str,
+    folders: &HashMap<String, Vec<String>>,
+    settings: &HashMap<String, APISetting>,
+    visited: &mut HashSet<String>,
+    result: &mut Vec<String>,
+) {
+    // 避免异常数据导致死循环
+    if !visited.insert(id.to_string()) {
+        return;
+    }
+    if settings.contains_key(id) {
+        result.push(id.to_string());
+        return;
+    }
+    if let Some(children) = folders.get(id) {
+        for child in children {
+            collect_folder_settings(child, folders, settings, visited, result);
+        }
+    }
+}
+
+// 按树的顺序获取需要执行的API配置
+async fn list_runner_settings(options: &RunnerOptions) -> Result<Vec<APISetting>, CyberAPIError> {
+    let mut folders = HashMap::new();
+    let mut children_ids = HashSet::new();
+    for folder in schemas::list_api_folder(options.collection.clone()).await? {
+        let children: Vec<String> = folder
+            .children
+            .unwrap_or_default()
+            .split(',')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect();
+        children_ids.extend(children.iter().cloned());
+        folders.insert(folder.id, children);
+    }
+    let mut settings = HashMap::new();
+    for setting in schemas::list_api_setting(options.collection.clone()).await? {
+        settings.insert(setting.id.clone(), setting);
+    }
+
+    let roots = if options.folder.is_empty() {
+        let mut roots: Vec<String> = folders
+            .keys()
+            .chain(settings.keys())
+            .filter(|id| !children_ids.contains(*id))
+            .cloned()
+            .collect();
+        roots.sort();
+        // 与前端一致，不在顺序列表中的排在前面
+        roots.sort_by_key(|id| {
+            options
+                .top_tree_items
+                .iter()
+                .position(|item| item == id)
+                .map(|index| index as i64)
+                .unwrap_or(-1)
+        });
+        roots
+    } else {
+        if !folders.contains_key(&options.folder) {
+            return Err(CyberAPIError::new(
+                format!("folder {} is not found", options.folder),
+                "runner",
+            ));
+        }
+        vec![options.folder.clone()]
+    };
+
+    let mut visited = HashSet::new();
+    let mut ids = vec![];
+    for id in roots {
+        collect_folder_settings(&id, &folders, &settings, &mut visited, &mut ids);
+    }
+    Ok(ids
+        .into_iter()
+        .filter_map(|id| settings.remove(&id))
+        .collect())
+}
+
+async fn run_step(
+    setting: &APISetting,
+    global_headers: &[(String, String)],
+    timeout: &RequestTimeout,
+    local: &mut Vec<(String, String)>,
+    step: &mut RunnerStep,
+) -> Result<(), CyberAPIError> {
+    let mut req = convert_request(&setting.setting.clone().unwrap_or_default(), global_headers)?;
+    step.method = req.method.clone();
+    step.uri = req.uri.clone();
+    // 之前请求提取的请求范围变量，优先级低于请求自身的变量
+    let mut variables: Vec<HTTPRequestKVParam> = local
+        .iter()
+        .filter(|(name, _)| !req.variables.iter().any(|item| &item.key == name))
+        .map(|(name, value)| new_kv_param(name, value))
+        .collect();
+    variables.append(&mut req.variables);
+    req.variables = variables;
+
+    let resp = http_request::request(
+        setting.id.clone(),
+        req,
+        RequestTimeout {
+            connect: timeout.connect,
+            write: timeout.write,
+            read: timeout.read,
+        },
+    )
+    .await?;
+    step.status = resp.status;
+    step.body_size = resp.body_size;
+    for item in resp.extractions.iter() {
+        if item.scope != VariableScope::Local {
+            continue;
+        }
+        if let Some(value) = &item.value {
+            local.retain(|(name, _)| name != &item.name);
+            local.push((item.name.clone(), value.clone()));
+        }
+    }
+    step.extractions = resp.extractions;
+    step.assertions = resp.assertions;
+    Ok(())
+}
+
+// 按顺序执行collection或目录下的所有请求，每个请求完成后回调进度
+pub async fn run<F>(options: RunnerOptions, on_progress: F) -> Result<RunnerReport, CyberAPIError>
+where
+    F: Fn(RunnerProgress),
+{
+    let started_at = Utc::now().to_rfc3339();
+    let start = Instant::now();
+    let settings = list_runner_settings(&options).await?;
+    let global_headers = schemas::list_global_req_headers(&options.collection, false).await?;
+    let mut columns = vec![];
+    let mut data_rows: Vec<Option<DataRow>> = vec![];
+    if options.data_file.is_empty() {
+        for _ in 0..options.iterations.max(1) {
+            data_rows.push(None);
+        }
+    } else {
+        let (data_columns, rows) = load_data_file(&options.data_file)?;
+        columns = data_columns;
+        for item in rows {
+            if options.rows.is_empty() || options.rows.contains(&item.row) {
+                data_rows.push(Some(item));
+            }
+        }
+    }
+    let iterations = data_rows.len() as u32;
+    let total = settings.len() * data_rows.len();
+
+    let mut report = RunnerReport {
+        id: ulid::Ulid::new().to_string(),
+        collection: options.collection.clone(),
+        folder: options.folder.clone(),
+        data_file: options.data_file.clone(),
+        columns,
+        iterations,
+        total,
+        passed: 0,
+        failed: 0,
+        stopped: false,
+        failed_rows: vec![],
+        duration: 0,
+        started_at,
+        finished_at: "".to_string(),
+        steps: vec![],
+    };
+
+    'iterations: for (iteration, data_row) in data_rows.iter().enumerate() {
+        // 请求范围的变量仅在同一轮中传递，数据行的值也作为请求范围的变量
+        let mut local = data_row
+            .as_ref()
+            .map(|item| item.values.clone())
+            .unwrap_or_default();
+        for (index, setting) in settings.iter().enumerate() {
+            if options.delay > 0 && !report.steps.is_empty() {
+                tokio::time::sleep(Duration::from_millis(options.delay)).await;
+            }
+            let mut step = RunnerStep {
+                iteration: iteration as u32 + 1,
+                index: index + 1,
+                row: data_row.as_ref().map(|item| item.row).unwrap_or_default(),
+                data: data_row
+                    .as_ref()
+                    .map(|item| item.values.iter().cloned().collect())
+                    .unwrap_or_default(),
+                api: setting.id.clone(),
+                name: setting.name.clone().unwrap_or_default(),
+                method: "".to_string(),
+                uri: "".to_string(),
+                status: 0,
+                duration: 0,
+                body_size: 0,
+                passed: false,
+                error: "".to_string(),
+                extractions: vec![],
+                assertions: vec![],
+            };
+            let step_start = Instant::now();
+            let result = run_step(
+                setting,
+                &global_headers,
+                &options.timeout,
+                &mut local,
+                &mut step,
+            )
+            .await;
+            step.duration = step_start.elapsed().as_millis() as u32;
+            match result {
+                Ok(()) => step.passed = step.assertions.iter().all(|item| item.passed),
+                Err(err) => step.error = err.message(),
+            }
+            if step.passed {
+                report.passed += 1;
+            } else {
+                report.failed += 1;
+                if step.row != 0 && !report.failed_rows.contains(&step.row) {
+                    report.failed_rows.push(step.row);
+                }
+            }
+            let passed = step.passed;
+            report.steps.push(step.clone());
+            on_progress(RunnerProgress {
+                id: report.id.clone(),
+                completed: report.steps.len(),
+                total,
+                step,
+            });
+            if !passed && options.stop_on_failure {
+                report.stopped = true;
+                break 'iterations;
+            }
+        }
+    }
+
+    report.duration = start.elapsed().as_millis() as u32;
+    report.finished_at = Utc::now().to_rfc3339();
+    Ok(report)
+}
+
+// 保存报告为json文件
+pub fn save_report(file: &str, report: &RunnerReport) -> Result<(), CyberAPIError> {
+    let data = serde_json::to_vec_pretty(report)?;
+    std::fs::write(file, data)?;
+    Ok(())
+}
+
+// 枚举序列化后的名称，如jsonPath
+fn enum_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(|value| value.to_string()))
+        .unwrap_or_default()
+}
+
+// 断言的描述，如：status equal 200, actual: 500
+pub fn describe_assertion(item: &AssertionResult) -> String {
+    let mut desc = enum_name(&item.target);
+    for value in [
+        item.property.clone(),
+        enum_name(&item.operator),
+        item.expected.clone(),
+    ] {
+        if !value.is_empty() {
+            desc = format!("{} {}", desc, value);
+        }
+    }
+    if let Some(actual) = &item.actual {
+        desc = format!("{}, actual: {}", desc, actual);
+    }
+    if !item.passed && !item.message.is_empty() {
+        desc = format!("{} ({})", desc, item.message);
+    }
+    desc
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn format_seconds(ms: u32) -> String {
+    format!("{:.3}", ms as f64 / 1000.0)
+}
+
+// 转换为JUnit XML，每轮为一个testsuite，每个请求为一个testcase
+pub fn to_junit_report(name: &str, report: &RunnerReport) -> String {
+    let errors = report
+        .steps
+        .iter()
+        .filter(|item| !item.error.is_empty())
+        .count();
+    let mut xml = vec![
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#.to_string(),
+        format!(
+            r#"<testsuites name="{}" tests="{}" failures="{}" errors="{}" time="{}">"#,
+            escape_xml(name),
+            report.steps.len(),
+            report.failed - errors,
+            errors,
+            format_seconds(report.duration),
+        ),
+    ];
+    let mut iteration = 0;
+    while let Some(first) = report.steps.iter().find(|item| item.iteration > iteration) {
+        iteration = first.iteration;
+        let steps: Vec<&RunnerStep> = report
+            .steps
+            .iter()
+            .filter(|item| item.iteration == iteration)
+            .collect();
+        let mut suite_name = format!("{} #{}", name, iteration);
+        if first.row != 0 {
+            suite_name = format!("{} (row {})", suite_name, first.row);
+        }
+        let suite_errors = steps.iter().filter(|item| !item.error.is_empty()).count();
+        let suite_failures = steps.iter().filter(|item| !item.passed).count() - suite_errors;
+        xml.push(format!(
+            r#"  <testsuite name="{}" tests="{}" failures="{}" errors="{}" time="{}">"#,
+            escape_xml(&suite_name),
+            steps.len(),
+            suite_failures,
+            suite_errors,
+            format_seconds(steps.iter().map(|item| item.duration).sum()),
+        ));
+        for step in steps {
+            xml.push(format!(
+                r#"    <testcase name="{}" classname="{}" time="{}">"#,
+                escape_xml(&step.name),
+                escape_xml(&format!("{} {}", step.method, step.uri)),
+                format_seconds(step.duration),
+            ));
+            if !step.error.is_empty() {
+                xml.push(format!(
+                    r#"      <error message="{}"></error>"#,
+                    escape_xml(&step.error)
+                ));
+            } else if !step.passed {
+                let failures: Vec<String> = step
+                    .assertions
+                    .iter()
+                    .filter(|item| !item.passed)
+                    .map(describe_assertion)
+                    .collect();
+                xml.push(format!(
+                    r#"      <failure message="{} assertion(s) failed">{}</failure>"#,
+                    failures.len(),
+                    escape_xml(&failures.join("\n"))
+                ));
+            }
+            xml.push("    </testcase>".to_string());
+        }
+        xml.push("  </testsuite>".to_string());
+    }
+    xml.push("</testsuites>".to_string());
+    xml.join("\n")
+}
@@ -1,12 +1,16 @@
 use crate::{
     entities::{api_collections, prelude::*},
     error::CyberAPIError,
+    retry::RetryPolicy,
 };
 use chrono::Utc;
-use sea_orm::{ActiveModelTrait, DbErr, EntityTrait, Set};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, DbErr, EntityTrait, NotSet, QueryFilter, Set,
+};
 use serde::{Deserialize, Serialize};
 
 use super::database::{get_database, ExportData};
+use super::file_store::sync_file_store_after_commit;
 
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -17,6 +21,9 @@ pub struct APICollection {
     pub name: Option<String>,
     // 描述
     pub description: Option<String>,
+    // collection的配置，json格式，如重试策略
+    #[serde(default)]
+    pub setting: Option<String>,
     // 创建时间
     pub created_at: Option<String>,
     // 更新时间
@@ -29,6 +36,7 @@ impl From<api_collections::Model> for APICollection {
             id: model.id,
             name: model.name,
             description: model.description,
+            setting: model.setting,
             created_at: model.created_at,
             updated_at: model.updated_at,
         }
@@ -43,8 +51,14 @@ impl APICollection {
             id: Set(self.id),
             name: Set(self.name),
             description: Set(self.description),
+            // 未传入时不修改
+            setting: match self.setting {
+                Some(value) => Set(Some(value)),
+                None => NotSet,
+            },
             created_at: Set(created_at),
             updated_at: Set(updated_at),
+            deleted_at: NotSet,
         }
     }
 }
@@ -54,52 +68,87 @@ pub fn get_api_collections_create_sql() -> String {
         id TEXT PRIMARY KEY NOT NULL check (id != ''),
         name TEXT DEFAULT '',
         description TEXT DEFAULT '',
+        setting TEXT DEFAULT '',
         created_at TEXT DEFAULT '',
-        updated_at TEXT DEFAULT ''
+        updated_at TEXT DEFAULT '',
+        deleted_at TEXT DEFAULT ''
     )"
     .to_string()
 }
 
 pub async fn add_api_collection(collection: APICollection) -> Result<APICollection, DbErr> {
     let model: api_collections::ActiveModel = collection.into_active_model();
-    let db = get_database().await;
+    let db = get_database().await?;
 
     let result = model.insert(&db).await?;
     Ok(result.into())
 }
 pub async fn update_api_collection(collection: APICollection) -> Result<APICollection, DbErr> {
     let model: api_collections::ActiveModel = collection.into_active_model();
-    let db = get_database().await;
+    let db = get_database().await?;
 
     let result = model.update(&db).await?;
+    sync_file_store_after_commit(&result.id).await;
     Ok(result.into())
 }
 
 pub async fn list_api_collection() -> Result<Vec<APICollection>, DbErr> {
-    let db = get_database().await;
-    let result = ApiCollections::find().all(&db).await?;
+    let db = get_database().await?;
+    let result = ApiCollections::find()
+        .filter(api_collections::Column::DeletedAt.eq(""))
+        .all(&db)
+        .await?;
 
     Ok(result.into_iter().map(APICollection::from).collect())
 }
 
+// 仅标记删除，可从回收站恢复
 pub async fn delete_api_collection(id: String) -> Result<u64, DbErr> {
-    let db = get_database().await;
-    let result = ApiCollections::delete_by_id(id).exec(&db).await?;
+    let db = get_database().await?;
+    let result = ApiCollections::update_many()
+        .col_expr(
+            api_collections::Column::DeletedAt,
+            Expr::value(Utc::now().to_rfc3339()),
+        )
+        .filter(api_collections::Column::Id.eq(id))
+        .filter(api_collections::Column::DeletedAt.eq(""))
+        .exec(&db)
+        .await?;
     Ok(result.rows_affected)
 }
 
+// collection配置的重试策略
+pub async fn get_collection_retry_policy(
+    collection: &str,
+) -> Result<Option<RetryPolicy>, CyberAPIError> {
+    let db = get_database().await?;
+    let setting = ApiCollections::find_by_id(collection)
+        .one(&db)
+        .await?
+        .and_then(|model| model.setting)
+        .unwrap_or_default();
+    if setting.is_empty() {
+        return Ok(None);
+    }
+    let value: serde_json::Value = serde_json::from_str(&setting)?;
+    match value.get("retry") {
+        Some(retry) if !retry.is_null() => Ok(Some(serde_json::from_value(retry.clone())?)),
+        _ => Ok(None),
+    }
+}
+
 pub fn get_table_name_api_collection() -> String {
     "api_collections".to_string()
 }
 
 pub async fn delete_all_api_collection() -> Result<(), DbErr> {
-    let db = get_database().await;
+    let db = get_database().await?;
     ApiCollections::delete_many().exec(&db).await?;
     Ok(())
 }
 
 pub async fn export_api_collection() -> Result<ExportData, DbErr> {
-    let db = get_database().await;
+    let db = get_database().await?;
     let data = ApiCollections::find().into_json().all(&db).await?;
     Ok(ExportData {
         name: get_table_name_api_collection(),
@@ -108,7 +157,7 @@ pub async fn export_api_collection() -> Result<ExportData, DbErr> {
 }
 
 pub async fn import_api_collection(data: Vec<serde_json::Value>) -> Result<(), CyberAPIError> {
-    let db = get_database().await;
+    let db = get_database().await?;
 
     let mut records = Vec::new();
     for ele in data {
@@ -0,0 +1,168 @@
+use crate::{
+    entities::{api_examples, api_settings, prelude::*},
+    error::CyberAPIError,
+};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DbErr, EntityTrait, QueryFilter, QueryOrder, Set};
+use serde::{Deserialize, Serialize};
+
+use super::database::{get_database, ExportData};
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct APIExample {
+    // id
+    pub id: String,
+    // API配置ID
+    pub api_setting: String,
+    // 示例名称
+    pub name: Option<String>,
+    // 响应状态码
+    pub status: i32,
+    // 响应头，json格式，与HTTPResponse的headers一致
+    pub headers: Option<String>,
+    // 响应数据，base64格式，与HTTPResponse的body一致
+    pub body: Option<String>,
+    // 创建时间
+    pub created_at: Option<String>,
+    // 更新时间
+    pub updated_at: Option<String>,
+}
+
+impl From<api_examples::Model> for APIExample {
+    fn from(model: api_examples::Model) -> Self {
+        APIExample {
+            id: model.id,
+            api_setting: model.api_setting,
+            name: model.name,
+            status: model.status,
+            headers: model.headers,
+            body: model.body,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+        }
+    }
+}
+impl APIExample {
+    fn into_active_model(self) -> api_examples::ActiveModel {
+        let created_at = self.created_at.or_else(|| Some(Utc::now().to_rfc3339()));
+        let updated_at = self.updated_at.or_else(|| Some(Utc::now().to_rfc3339()));
+        api_examples::ActiveModel {
+            id: Set(self.id),
+            api_setting: Set(self.api_setting),
+            name: Set(self.name),
+            status: Set(self.status),
+            headers: Set(self.headers),
+            body: Set(self.body),
+            created_at: Set(created_at),
+            updated_at: Set(updated_at),
+        }
+    }
+}
+
+pub fn get_api_examples_create_sql() -> String {
+    "CREATE TABLE IF NOT EXISTS api_examples (
+        id TEXT PRIMARY KEY NOT NULL check (id != ''),
+        api_setting TEXT NOT NULL check (api_setting != '')
+            REFERENCES api_settings(id) ON DELETE CASCADE,
+        name TEXT DEFAULT '',
+        status INTEGER NOT NULL DEFAULT 0,
+        headers TEXT DEFAULT '',
+        body TEXT DEFAULT '',
+        created_at TEXT DEFAULT '',
+        updated_at TEXT DEFAULT ''
+    )"
+    .to_string()
+}
+
+pub fn get_api_examples_index_sql() -> String {
+    "CREATE INDEX IF NOT EXISTS api_examples_api_setting
+        ON api_examples(api_setting, created_at)"
+        .to_string()
+}
+
+pub async fn add_api_example(example: APIExample) -> Result<APIExample, DbErr> {
+    let db = get_database().await?;
+    let result = example.into_active_model().insert(&db).await?;
+    Ok(result.into())
+}
+
+// 获取API配置的所有示例，按创建时间排序
+pub async fn list_api_example(api_setting: String) -> Result<Vec<APIExample>, DbErr> {
+    let db = get_database().await?;
+    let result = ApiExamples::find()
+        .filter(api_examples::Column::ApiSetting.eq(api_setting))
+        .order_by_asc(api_examples::Column::CreatedAt)
+        .all(&db)
+        .await?;
+    Ok(result.into_iter().map(APIExample::from).collect())
+}
+
+// 获取collection下所有API配置（不包括已删除）的示例，用于导出
+pub async fn list_collection_api_example(collection: String) -> Result<Vec<APIExample>, DbErr> {
+    let db = get_database().await?;
+    let result = ApiExamples::find()
+        .inner_join(ApiSettings)
+        .filter(api_settings::Column::Collection.eq(collection))
+        .filter(api_settings::Column::DeletedAt.eq(""))
+        .order_by_asc(api_examples::Column::CreatedAt)
+        .all(&db)
+        .await?;
+    Ok(result.into_iter().map(APIExample::from).collect())
+}
+
+pub async fn find_api_example(id: String) -> Result<Option<APIExample>, DbErr> {
+    let db = get_database().await?;
+    let result = ApiExamples::find_by_id(id).one(&db).await?;
+    Ok(result.map(APIExample::from))
+}
+
+pub async fn rename_api_example(id: String, name: String) -> Result<APIExample, DbErr> {
+    let db = get_database().await?;
+    let current = ApiExamples::find_by_id(id.clone())
+        .one(&db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound(format!("api example: {}", id)))?;
+    let mut model: api_examples::ActiveModel = current.into();
+    model.name = Set(Some(name));
+    model.updated_at = Set(Some(Utc::now().to_rfc3339()));
+    let result = model.update(&db).await?;
+    Ok(result.into())
+}
+
+pub async fn delete_api_example(id: String) -> Result<u64, DbErr> {
+    let db = get_database().await?;
+    let result = ApiExamples::delete_by_id(id).exec(&db).await?;
+    Ok(result.rows_affected)
+}
+
+pub fn get_table_name_api_example() -> String {
+    "api_examples".to_string()
+}
+
+pub async fn delete_all_api_example() -> Result<(), CyberAPIError> {
+    let db = get_database().await?;
+    ApiExamples::delete_many().exec(&db).await?;
+    Ok(())
+}
+
+pub async fn export_api_example() -> Result<ExportData, DbErr> {
+    let db = get_database().await?;
+    let data = ApiExamples::find().into_json().all(&db).await?;
+    Ok(ExportData {
+        name: get_table_name_api_example(),
+        data,
+    })
+}
+
+pub async fn import_api_example(data: Vec<serde_json::Value>) -> Result<(), CyberAPIError> {
+    let db = get_database().await?;
+
+    let mut records = Vec::new();
+    for ele in data {
+        let model = api_examples::ActiveModel::from_json(ele)?;
+        records.push(model);
+    }
+    ApiExamples::insert_many(records).exec(&db).await?;
+    Ok(())
+}
@@ -1,13 +1,18 @@
 use crate::{
-    entities::{api_folders, prelude::*},
+    entities::{api_folders, api_settings, prelude::*},
     error::CyberAPIError,
 };
 use chrono::Utc;
-use sea_orm::{ActiveModelTrait, ColumnTrait, DbErr, EntityTrait, QueryFilter, Set};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, NotSet,
+    QueryFilter, Set, TransactionTrait,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use super::database::{get_database, ExportData};
+use super::file_store::sync_file_store_after_commit;
+use super::search::index_api_settings;
 
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -49,6 +54,7 @@ impl APIFolder {
             name: Set(self.name),
             created_at: Set(created_at),
             updated_at: Set(updated_at),
+            deleted_at: NotSet,
         }
     }
 }
@@ -61,91 +67,110 @@ pub struct APIFolderChildren {
 }
 
 pub fn get_api_folders_create_sql() -> String {
-    "CREATE TABLE IF NOT EXISTS api_folders (
+    new_api_folders_create_sql(&get_table_name_api_folder())
+}
+
+// 指定表名的建表语句，重建表时用于创建新表
+pub fn new_api_folders_create_sql(table: &str) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} (
             id TEXT PRIMARY KEY NOT NULL check (id != ''),
-            collection TEXT NOT NULL check (collection != ''),
+            collection TEXT NOT NULL check (collection != '')
+                REFERENCES api_collections(id) ON DELETE CASCADE,
             children TEXT DEFAULT '',
             name TEXT DEFAULT '',
             created_at TEXT DEFAULT '',
-            updated_at TEXT DEFAULT ''
-        )"
-    .to_string()
+            updated_at TEXT DEFAULT '',
+            deleted_at TEXT DEFAULT ''
+        )",
+        table
+    )
 }
 
 pub async fn add_api_folder(folder: APIFolder) -> Result<APIFolder, DbErr> {
     let model = folder.into_active_model();
-    let db = get_database().await;
+    let db = get_database().await?;
     let result = model.insert(&db).await?;
+    sync_file_store_after_commit(&result.collection).await;
     Ok(result.into())
 }
 
 pub async fn update_api_folder(folder: APIFolder) -> Result<APIFolder, DbErr> {
     let model = folder.into_active_model();
-    let db = get_database().await;
+    let db = get_database().await?;
     let result = model.update(&db).await?;
+    // 目录名称或子元素变化均影响API的目录路径
+    index_api_settings(&db, &result.collection, None).await?;
+    sync_file_store_after_commit(&result.collection).await;
     Ok(result.into())
 }
 
 pub async fn list_api_folder(collection: String) -> Result<Vec<APIFolder>, DbErr> {
-    let db = get_database().await;
+    let db = get_database().await?;
     let result = ApiFolders::find()
         .filter(api_folders::Column::Collection.eq(collection))
+        .filter(api_folders::Column::DeletedAt.eq(""))
         .all(&db)
         .await?;
     Ok(result.into_iter().map(APIFolder::from).collect())
 }
 
-pub async fn delete_api_folder_by_collection(collection: String) -> Result<u64, DbErr> {
-    let db = get_database().await;
-    let result = ApiFolders::delete_many()
-        .filter(api_folders::Column::Collection.eq(collection))
-        .exec(&db)
-        .await?;
+// 标记删除目录及其所有子元素，使用相同的删除时间以便整体恢复
+pub async fn delete_api_folder(id: String) -> Result<APIFolderChildren, DbErr> {
+    let db = get_database().await?;
+    let mut result = list_api_folder_all_children(id.clone()).await?;
+    result.folders.push(id.clone());
 
-    Ok(result.rows_affected)
-}
-
-pub async fn delete_api_folders(ids: Vec<String>) -> Result<u64, DbErr> {
-    let db = get_database().await;
-
-    let result = ApiFolders::delete_many()
-        .filter(api_folders::Column::Id.is_in(ids))
-        .exec(&db)
+    let deleted_at = Utc::now().to_rfc3339();
+    let txn = db.begin().await?;
+    ApiFolders::update_many()
+        .col_expr(
+            api_folders::Column::DeletedAt,
+            Expr::value(deleted_at.clone()),
+        )
+        .filter(api_folders::Column::Id.is_in(result.folders.clone()))
+        .filter(api_folders::Column::DeletedAt.eq(""))
+        .exec(&txn)
+        .await?;
+    ApiSettings::update_many()
+        .col_expr(
+            api_settings::Column::DeletedAt,
+            Expr::value(deleted_at.clone()),
+        )
+        .filter(api_settings::Column::Id.is_in(result.settings.clone()))
+        .filter(api_settings::Column::DeletedAt.eq(""))
+        .exec(&txn)
         .await?;
+    txn.commit().await?;
+    if let Some(folder) = ApiFolders::find_by_id(id).one(&db).await? {
+        sync_file_store_after_commit(&folder.collection).await;
+    }
 
-    Ok(result.rows_affected)
+    Ok(result)
 }
 
-// 获取该目录的所有子元素（包括子元素以及子目录、子目录的子元素）
-pub async fn list_api_folder_all_children(id: String) -> Result<APIFolderChildren, DbErr> {
-    // 使用偷懒的方式，直接查询所有api folder再过滤
-    let mut folder_children = HashMap::new();
+// 根据目录与其子元素的对应关系，获取所有子元素（包括子目录的子元素）
+pub fn find_all_children(
+    folder_children: &HashMap<String, Option<String>>,
+    id: &str,
+) -> APIFolderChildren {
     let mut folders = Vec::new();
     let mut settings = Vec::new();
-    let mut children = "".to_string();
+    let mut children = folder_children
+        .get(id)
+        .cloned()
+        .flatten()
+        .unwrap_or_default();
 
-    let db = get_database().await;
-    let current_folder = ApiFolders::find()
-        .filter(api_folders::Column::Id.eq(id.clone()))
-        .one(&db)
-        .await?;
-    if let Some(folder) = current_folder {
-        // 记录所有folder与它的子目录
-        for ele in list_api_folder(folder.collection).await? {
-            if ele.id == id {
-                if let Some(data) = ele.children.clone() {
-                    children = data
-                }
-            }
-            folder_children.insert(ele.id, ele.children.clone());
-        }
-    }
     while !children.is_empty() {
         let arr = children.split(',');
         let mut current_children = Vec::new();
 
         for ele in arr {
             let id = ele.trim();
+            if id.is_empty() {
+                continue;
+            }
             // 是folder
             match folder_children.get(id) {
                 // 目录
@@ -167,7 +192,40 @@ pub async fn list_api_folder_all_children(id: String) -> Result<APIFolderChildre
         // 记录新的children
         children = current_children.join(",")
     }
-    Ok(APIFolderChildren { folders, settings })
+    APIFolderChildren { folders, settings }
+}
+
+// 获取collection下所有目录（包括已删除）与其子元素的对应关系
+pub async fn get_folder_children_map<C>(
+    db: &C,
+    collection: String,
+) -> Result<HashMap<String, Option<String>>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let result = ApiFolders::find()
+        .filter(api_folders::Column::Collection.eq(collection))
+        .all(db)
+        .await?;
+    Ok(result
+        .into_iter()
+        .map(|item| (item.id, item.children))
+        .collect())
+}
+
+// 获取该目录的所有子元素（包括子元素以及子目录、子目录的子元素）
+pub async fn list_api_folder_all_children(id: String) -> Result<APIFolderChildren, DbErr> {
+    // 使用偷懒的方式，直接查询所有api folder再过滤
+    let db = get_database().await?;
+    let current_folder = ApiFolders::find()
+        .filter(api_folders::Column::Id.eq(id.clone()))
+        .one(&db)
+        .await?;
+    let mut folder_children = HashMap::new();
+    if let Some(folder) = current_folder {
+        folder_children = get_folder_children_map(&db, folder.collection).await?;
+    }
+    Ok(find_all_children(&folder_children, &id))
 }
 
 pub fn get_table_name_api_folder() -> String {
@@ -175,13 +233,13 @@ pub fn get_table_name_api_folder() -> String {
 }
 
 pub async fn delete_all_api_folder() -> Result<(), CyberAPIError> {
-    let db = get_database().await;
+    let db = get_database().await?;
     ApiFolders::delete_many().exec(&db).await?;
     Ok(())
 }
 
 pub async fn export_api_folder() -> Result<ExportData, DbErr> {
-    let db = get_database().await;
+    let db = get_database().await?;
     let data = ApiFolders::find().into_json().all(&db).await?;
     Ok(ExportData {
         name: get_table_name_api_folder(),
@@ -190,7 +248,7 @@ pub async fn export_api_folder() -> Result<ExportData, DbErr> {
 }
 
 pub async fn import_api_folder(data: Vec<serde_json::Value>) -> Result<(), CyberAPIError> {
-    let db = get_database().await;
+    let db = get_database().await?;
 
     let mut records = Vec::new();
     for ele in data {
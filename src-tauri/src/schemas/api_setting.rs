@@ -3,10 +3,17 @@ use crate::{
     error::CyberAPIError,
 };
 use chrono::Utc;
-use sea_orm::{ActiveModelTrait, ColumnTrait, DbErr, EntityTrait, QueryFilter, Set};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, DbErr, EntityTrait, NotSet, QueryFilter, Set,
+    TransactionTrait,
+};
 use serde::{Deserialize, Serialize};
 
+use super::api_setting_revision::{get_api_setting_revision, save_api_setting_revision};
 use super::database::{get_database, ExportData};
+use super::file_store::{sync_file_store_after_commit, sync_file_stores};
+use super::search::index_api_settings;
+
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct APISetting {
@@ -51,62 +58,123 @@ impl APISetting {
             setting: Set(self.setting),
             created_at: Set(created_at),
             updated_at: Set(updated_at),
+            deleted_at: NotSet,
         }
     }
 }
 
 pub fn get_api_settings_create_sql() -> String {
-    "CREATE TABLE IF NOT EXISTS api_settings (
+    new_api_settings_create_sql(&get_table_name_api_setting())
+}
+
+// table为重建时使用的临时表名
+pub fn new_api_settings_create_sql(table: &str) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} (
         id TEXT PRIMARY KEY NOT NULL check (id != ''),
-        collection TEXT NOT NULL check (collection != ''),
+        collection TEXT NOT NULL check (collection != '')
+            REFERENCES api_collections(id) ON DELETE CASCADE,
         name TEXT DEFAULT '',
         category TEXT DEFAULT '',
         setting TEXT DEFAULT '',
         created_at TEXT DEFAULT '',
-        updated_at TEXT DEFAULT ''
-    )"
-    .to_string()
+        updated_at TEXT DEFAULT '',
+        deleted_at TEXT DEFAULT ''
+    )",
+        table
+    )
 }
 
 pub async fn add_api_setting(setting: APISetting) -> Result<APISetting, DbErr> {
     let model = setting.into_active_model();
-    let db = get_database().await;
+    let db = get_database().await?;
     let result = model.insert(&db).await?;
+    index_api_settings(&db, &result.collection, Some(vec![result.id.clone()])).await?;
+    sync_file_store_after_commit(&result.collection).await;
     Ok(result.into())
 }
-pub async fn update_api_setting(setting: APISetting) -> Result<APISetting, DbErr> {
+
+// 更新前保存当前版本至历史记录
+async fn save_api_setting(setting: APISetting, force_revision: bool) -> Result<APISetting, DbErr> {
     let model = setting.into_active_model();
-    let db = get_database().await;
-    let result = model.update(&db).await?;
+    let db = get_database().await?;
+    let txn = db.begin().await?;
+    if let Some(current) = ApiSettings::find_by_id(model.id.clone().unwrap())
+        .one(&txn)
+        .await?
+    {
+        save_api_setting_revision(&txn, &current, &model, force_revision).await?;
+    }
+    let result = model.update(&txn).await?;
+    index_api_settings(&txn, &result.collection, Some(vec![result.id.clone()])).await?;
+    txn.commit().await?;
+    sync_file_store_after_commit(&result.collection).await;
     Ok(result.into())
 }
 
+pub async fn update_api_setting(setting: APISetting) -> Result<APISetting, DbErr> {
+    save_api_setting(setting, false).await
+}
+
+// 恢复至历史版本，恢复前的配置也会保存为新的版本
+pub async fn restore_api_setting_revision(id: String) -> Result<APISetting, DbErr> {
+    let revision = get_api_setting_revision(id).await?;
+    let db = get_database().await?;
+    let current = ApiSettings::find_by_id(revision.api_setting.clone())
+        .one(&db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound(format!("api setting: {}", revision.api_setting)))?;
+    let setting = APISetting {
+        id: current.id,
+        collection: current.collection,
+        name: revision.name,
+        category: revision.category,
+        setting: revision.setting,
+        created_at: current.created_at,
+        updated_at: None,
+    };
+    save_api_setting(setting, true).await
+}
+
 pub async fn list_api_setting(collection: String) -> Result<Vec<APISetting>, DbErr> {
-    let db = get_database().await;
+    let db = get_database().await?;
     let result = ApiSettings::find()
         .filter(api_settings::Column::Collection.eq(collection))
+        .filter(api_settings::Column::DeletedAt.eq(""))
         .all(&db)
         .await?;
 
     Ok(result.into_iter().map(APISetting::from).collect())
 }
 
-pub async fn delete_api_setting_by_collection(collection: String) -> Result<u64, DbErr> {
-    let db = get_database().await;
-    let result = ApiSettings::delete_many()
-        .filter(api_settings::Column::Collection.eq(collection))
-        .exec(&db)
-        .await?;
-    Ok(result.rows_affected)
+// 获取API配置，不存在（如未保存的请求）时返回None
+pub async fn find_api_setting(id: String) -> Result<Option<APISetting>, DbErr> {
+    let db = get_database().await?;
+    let result = ApiSettings::find_by_id(id).one(&db).await?;
+    Ok(result.map(APISetting::from))
 }
 
+// 仅标记删除，可从回收站恢复
 pub async fn delete_api_settings(ids: Vec<String>) -> Result<u64, DbErr> {
-    let db = get_database().await;
+    let db = get_database().await?;
+    let collections = ApiSettings::find()
+        .filter(api_settings::Column::Id.is_in(ids.clone()))
+        .all(&db)
+        .await?
+        .into_iter()
+        .map(|item| item.collection)
+        .collect();
 
-    let result = ApiSettings::delete_many()
+    let result = ApiSettings::update_many()
+        .col_expr(
+            api_settings::Column::DeletedAt,
+            Expr::value(Utc::now().to_rfc3339()),
+        )
         .filter(api_settings::Column::Id.is_in(ids))
+        .filter(api_settings::Column::DeletedAt.eq(""))
         .exec(&db)
         .await?;
+    sync_file_stores(collections).await;
     Ok(result.rows_affected)
 }
 
@@ -115,14 +183,14 @@ pub fn get_table_name_api_setting() -> String {
 }
 
 pub async fn delete_all_api_setting() -> Result<(), CyberAPIError> {
-    let db = get_database().await;
+    let db = get_database().await?;
 
     ApiSettings::delete_many().exec(&db).await?;
     Ok(())
 }
 
 pub async fn export_api_setting() -> Result<ExportData, DbErr> {
-    let db = get_database().await;
+    let db = get_database().await?;
     let data = ApiSettings::find().into_json().all(&db).await?;
     Ok(ExportData {
         name: get_table_name_api_setting(),
@@ -131,7 +199,7 @@ pub async fn export_api_setting() -> Result<ExportData, DbErr> {
 }
 
 pub async fn import_api_setting(data: Vec<serde_json::Value>) -> Result<(), CyberAPIError> {
-    let db = get_database().await;
+    let db = get_database().await?;
 
     let mut records = Vec::new();
     for ele in data {
@@ -0,0 +1,370 @@
+use crate::entities::{api_setting_revisions, api_settings, prelude::*};
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    Set, Statement,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::database::get_database;
+
+// 连续编辑时，该时间内的修改合并为同一个版本
+const REVISION_MERGE_SECONDS: i64 = 60;
+
+const REVISION_SUMMARY_SEPARATOR: &str = ", ";
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct APISettingRevision {
+    // id
+    pub id: String,
+    // API配置ID
+    pub api_setting: String,
+    // 配置名称
+    pub name: Option<String>,
+    // 类型(http, graphQL)
+    pub category: Option<String>,
+    // 配置信息
+    pub setting: Option<String>,
+    // 与下一版本相比修改的字段
+    pub summary: Option<String>,
+    // 创建时间
+    pub created_at: Option<String>,
+}
+
+impl From<api_setting_revisions::Model> for APISettingRevision {
+    fn from(model: api_setting_revisions::Model) -> Self {
+        APISettingRevision {
+            id: model.id,
+            api_setting: model.api_setting,
+            name: model.name,
+            category: model.category,
+            setting: model.setting,
+            summary: model.summary,
+            created_at: model.created_at,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct APISettingChange {
+    // 修改的字段，如name、body、headers.Content-Type
+    pub field: String,
+    // 修改前的值，新增时为null
+    pub before: Value,
+    // 修改后的值，删除时为null
+    pub after: Value,
+}
+
+pub fn get_api_setting_revisions_create_sql() -> String {
+    "CREATE TABLE IF NOT EXISTS api_setting_revisions (
+        id TEXT PRIMARY KEY NOT NULL check (id != ''),
+        api_setting TEXT NOT NULL check (api_setting != '')
+            REFERENCES api_settings(id) ON DELETE CASCADE,
+        name TEXT DEFAULT '',
+        category TEXT DEFAULT '',
+        setting TEXT DEFAULT '',
+        summary TEXT DEFAULT '',
+        created_at TEXT DEFAULT ''
+    )"
+    .to_string()
+}
+
+pub fn get_api_setting_revisions_index_sql() -> String {
+    "CREATE INDEX IF NOT EXISTS api_setting_revisions_api_setting
+        ON api_setting_revisions(api_setting, created_at)"
+        .to_string()
+}
+
+// 用于对比的配置快照
+struct Snapshot {
+    name: Option<String>,
+    category: Option<String>,
+    setting: Option<String>,
+}
+
+impl From<&api_settings::Model> for Snapshot {
+    fn from(model: &api_settings::Model) -> Self {
+        Snapshot {
+            name: model.name.clone(),
+            category: model.category.clone(),
+            setting: model.setting.clone(),
+        }
+    }
+}
+
+impl From<&api_setting_revisions::Model> for Snapshot {
+    fn from(model: &api_setting_revisions::Model) -> Self {
+        Snapshot {
+            name: model.name.clone(),
+            category: model.category.clone(),
+            setting: model.setting.clone(),
+        }
+    }
+}
+
+fn to_value(value: &Option<String>) -> Value {
+    match value {
+        Some(value) if !value.is_empty() => Value::String(value.clone()),
+        _ => Value::Null,
+    }
+}
+
+// 以key/value形式保存的数组（headers、query等），按key分组对比
+fn group_kv_list(value: &Value) -> Option<BTreeMap<String, Value>> {
+    let items = value.as_array()?;
+    let mut result: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    for item in items {
+        let key = item.get("key")?.as_str()?;
+        result
+            .entry(key.to_string())
+            .or_default()
+            .push(item.clone());
+    }
+    Some(
+        result
+            .into_iter()
+            .map(|(key, mut values)| {
+                let value = if values.len() == 1 {
+                    values.remove(0)
+                } else {
+                    Value::Array(values)
+                };
+                (key, value)
+            })
+            .collect(),
+    )
+}
+
+fn diff_value(field: &str, before: &Value, after: &Value, changes: &mut Vec<APISettingChange>) {
+    if before == after {
+        return;
+    }
+    let before_list = if before.is_null() {
+        Some(BTreeMap::new())
+    } else {
+        group_kv_list(before)
+    };
+    let after_list = if after.is_null() {
+        Some(BTreeMap::new())
+    } else {
+        group_kv_list(after)
+    };
+    if let (Some(before_list), Some(after_list)) = (before_list, after_list) {
+        // 两者均为空时，无法按key对比
+        if !before_list.is_empty() || !after_list.is_empty() {
+            let mut keys: Vec<&String> = before_list.keys().chain(after_list.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let before = before_list.get(key).cloned().unwrap_or_default();
+                let after = after_list.get(key).cloned().unwrap_or_default();
+                if before != after {
+                    changes.push(APISettingChange {
+                        field: format!("{}.{}", field, key),
+                        before,
+                        after,
+                    });
+                }
+            }
+            return;
+        }
+    }
+    changes.push(APISettingChange {
+        field: field.to_string(),
+        before: before.clone(),
+        after: after.clone(),
+    });
+}
+
+fn diff_snapshot(before: &Snapshot, after: &Snapshot) -> Vec<APISettingChange> {
+    let mut changes = vec![];
+    diff_value(
+        "name",
+        &to_value(&before.name),
+        &to_value(&after.name),
+        &mut changes,
+    );
+    diff_value(
+        "category",
+        &to_value(&before.category),
+        &to_value(&after.category),
+        &mut changes,
+    );
+
+    let parse = |value: &Option<String>| -> Option<serde_json::Map<String, Value>> {
+        match value.as_deref() {
+            None | Some("") => Some(serde_json::Map::new()),
+            Some(value) => serde_json::from_str(value).ok(),
+        }
+    };
+    match (parse(&before.setting), parse(&after.setting)) {
+        (Some(before_setting), Some(after_setting)) => {
+            let mut keys: Vec<&String> =
+                before_setting.keys().chain(after_setting.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                diff_value(
+                    key,
+                    before_setting.get(key).unwrap_or(&Value::Null),
+                    after_setting.get(key).unwrap_or(&Value::Null),
+                    &mut changes,
+                );
+            }
+        }
+        // 非json格式的配置直接对比字符串
+        _ => diff_value(
+            "setting",
+            &to_value(&before.setting),
+            &to_value(&after.setting),
+            &mut changes,
+        ),
+    }
+    changes
+}
+
+fn get_summary(changes: &[APISettingChange]) -> String {
+    changes
+        .iter()
+        .map(|item| item.field.clone())
+        .collect::<Vec<_>>()
+        .join(REVISION_SUMMARY_SEPARATOR)
+}
+
+async fn find_latest_revision<C>(
+    db: &C,
+    api_setting: &str,
+) -> Result<Option<api_setting_revisions::Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    ApiSettingRevisions::find()
+        .filter(api_setting_revisions::Column::ApiSetting.eq(api_setting))
+        .order_by_desc(api_setting_revisions::Column::CreatedAt)
+        .one(db)
+        .await
+}
+
+// 在API配置更新前保存当前版本，force为false时短时间内的修改合并至最近的版本
+pub async fn save_api_setting_revision<C>(
+    db: &C,
+    current: &api_settings::Model,
+    next: &api_settings::ActiveModel,
+    force: bool,
+) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let next = Snapshot {
+        name: next.name.clone().unwrap(),
+        category: next.category.clone().unwrap(),
+        setting: next.setting.clone().unwrap(),
+    };
+    let now = Utc::now();
+
+    if !force {
+        if let Some(latest) = find_latest_revision(db, &current.id).await? {
+            let recent =
+                DateTime::parse_from_rfc3339(latest.created_at.as_deref().unwrap_or_default())
+                    .map(|created_at| now - Duration::seconds(REVISION_MERGE_SECONDS) < created_at)
+                    .unwrap_or_default();
+            if recent {
+                let changes = diff_snapshot(&Snapshot::from(&latest), &next);
+                // 修改后与最近版本一致，则该版本已无意义
+                if changes.is_empty() {
+                    ApiSettingRevisions::delete_by_id(latest.id)
+                        .exec(db)
+                        .await?;
+                    return Ok(());
+                }
+                let mut model: api_setting_revisions::ActiveModel = latest.into();
+                model.summary = Set(Some(get_summary(&changes)));
+                model.update(db).await?;
+                return Ok(());
+            }
+        }
+    }
+
+    let changes = diff_snapshot(&Snapshot::from(current), &next);
+    if changes.is_empty() {
+        return Ok(());
+    }
+    api_setting_revisions::ActiveModel {
+        id: Set(ulid::Ulid::new().to_string()),
+        api_setting: Set(current.id.clone()),
+        name: Set(current.name.clone()),
+        category: Set(current.category.clone()),
+        setting: Set(current.setting.clone()),
+        summary: Set(Some(get_summary(&changes))),
+        created_at: Set(Some(now.to_rfc3339())),
+    }
+    .insert(db)
+    .await?;
+    Ok(())
+}
+
+// 获取API配置的所有历史版本，按时间倒序
+pub async fn list_api_setting_revision(
+    api_setting: String,
+) -> Result<Vec<APISettingRevision>, DbErr> {
+    let db = get_database().await?;
+    let result = ApiSettingRevisions::find()
+        .filter(api_setting_revisions::Column::ApiSetting.eq(api_setting))
+        .order_by_desc(api_setting_revisions::Column::CreatedAt)
+        .all(&db)
+        .await?;
+    Ok(result.into_iter().map(APISettingRevision::from).collect())
+}
+
+pub async fn get_api_setting_revision(id: String) -> Result<api_setting_revisions::Model, DbErr> {
+    let db = get_database().await?;
+    ApiSettingRevisions::find_by_id(id.clone())
+        .one(&db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound(format!("api setting revision: {}", id)))
+}
+
+// 对比两个版本，to为空则与当前配置对比
+pub async fn diff_api_setting_revision(
+    from: String,
+    to: Option<String>,
+) -> Result<Vec<APISettingChange>, DbErr> {
+    let db = get_database().await?;
+    let from = get_api_setting_revision(from).await?;
+    let to = match to {
+        Some(to) => Snapshot::from(&get_api_setting_revision(to).await?),
+        None => {
+            let current = ApiSettings::find_by_id(from.api_setting.clone())
+                .one(&db)
+                .await?
+                .ok_or_else(|| {
+                    DbErr::RecordNotFound(format!("api setting: {}", from.api_setting))
+                })?;
+            Snapshot::from(&current)
+        }
+    };
+    Ok(diff_snapshot(&Snapshot::from(&from), &to))
+}
+
+// 删除超出数量或者已过期的历史版本
+pub async fn clean_api_setting_revision(max_count: u32, expired_days: u32) -> Result<u64, DbErr> {
+    let db = get_database().await?;
+    let expired_at = Utc::now() - Duration::days(expired_days as i64);
+    let result = db
+        .execute(Statement::from_sql_and_values(
+            db.get_database_backend(),
+            "DELETE FROM api_setting_revisions WHERE created_at < ? OR id IN (
+                SELECT id FROM (
+                    SELECT id, ROW_NUMBER() OVER (PARTITION BY api_setting ORDER BY created_at DESC) AS num
+                    FROM api_setting_revisions
+                ) WHERE num > ?
+            )",
+            [expired_at.to_rfc3339().into(), max_count.into()],
+        ))
+        .await?;
+    Ok(result.rows_affected())
+}
@@ -1,54 +1,83 @@
 use crate::error::CyberAPIError;
 use chrono::Local;
-use sea_orm::{ConnectOptions, ConnectionTrait, Database, DatabaseConnection, DbErr, Statement};
+use once_cell::sync::Lazy;
+use sea_orm::{
+    ConnectOptions, ConnectionTrait, Database, DatabaseConnection, DbErr, Statement,
+    TransactionTrait,
+};
+use std::collections::HashSet;
 use std::fs::OpenOptions;
 use std::io::{Read, Write};
 use std::time::Duration;
 use std::vec;
 use std::{fs, fs::File, path::Path};
 use tauri::api::path::download_dir;
-use tokio::sync::OnceCell;
+use tokio::sync::Mutex;
+use zip::result::ZipError;
 use zip::write::FileOptions;
 
-use crate::util;
+use crate::workspace::get_current_workspace;
 
 use super::api_collection::{
     delete_all_api_collection, export_api_collection, get_api_collections_create_sql,
     get_table_name_api_collection, import_api_collection,
 };
+use super::api_example::{
+    delete_all_api_example, export_api_example, get_api_examples_create_sql,
+    get_api_examples_index_sql, get_table_name_api_example, import_api_example,
+};
 use super::api_folder::{
     delete_all_api_folder, export_api_folder, get_api_folders_create_sql,
-    get_table_name_api_folder, import_api_folder,
+    get_table_name_api_folder, import_api_folder, new_api_folders_create_sql,
 };
 use super::api_setting::{
     delete_all_api_setting, export_api_setting, get_api_settings_create_sql,
-    get_table_name_api_setting, import_api_setting,
+    get_table_name_api_setting, import_api_setting, new_api_settings_create_sql,
+};
+use super::api_setting_revision::{
+    get_api_setting_revisions_create_sql, get_api_setting_revisions_index_sql,
+};
+use super::environment::{
+    delete_all_environment, export_environment, get_environments_create_sql,
+    get_table_name_environment, import_environment,
 };
+use super::file_store::get_file_stores_create_sql;
+use super::global_variable::{
+    delete_all_global_variable, export_global_variable, get_global_variables_create_sql,
+    get_table_name_global_variable, import_global_variable,
+};
+use super::maintenance::find_orphans;
+use super::oauth_token::get_oauth_tokens_create_sql;
+use super::search::{get_api_setting_search_create_sql, init_search_index, rebuild_search_index};
+use super::secret::{get_secret_keys_create_sql, lock_secret};
 use super::variable::{
     delete_all_variable, export_variable, get_table_name_variable, get_variables_create_sql,
-    import_variable,
+    import_variable, new_variables_create_sql,
 };
 use super::version::get_versions_table_create_sql;
 
-static DB: OnceCell<DatabaseConnection> = OnceCell::const_new();
+// 当前工作区的数据库连接，切换工作区时替换
+static DB: Lazy<Mutex<Option<DatabaseConnection>>> = Lazy::new(|| Mutex::new(None));
 
 pub struct ExportData {
     pub name: String,
     pub data: Vec<serde_json::Value>,
 }
 
-async fn get_conn() -> DatabaseConnection {
-    let dir = Path::new(util::get_app_dir());
-    let file = dir.join("my_db.db");
-    fs::create_dir_all(dir).unwrap();
+async fn get_conn(file: &Path) -> Result<DatabaseConnection, DbErr> {
+    let err_handle = |err: std::io::Error| DbErr::Custom(format!("{}, {}", err, file.display()));
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir).map_err(err_handle)?;
+    }
     OpenOptions::new()
         .read(true)
         .write(true)
         .create(true)
-        .open(file.clone())
-        .unwrap();
+        .open(file)
+        .map_err(err_handle)?;
 
-    let conn_uri = format!("sqlite://{}", file.into_os_string().into_string().unwrap());
+    // sqlx在建立每个连接时均会设置 PRAGMA foreign_keys = ON
+    let conn_uri = format!("sqlite://{}", file.to_string_lossy());
 
     let mut opt = ConnectOptions::new(conn_uri);
     opt.max_connections(10)
@@ -56,28 +85,290 @@ async fn get_conn() -> DatabaseConnection {
         .connect_timeout(Duration::from_secs(5))
         .idle_timeout(Duration::from_secs(60));
 
-    let result = Database::connect(opt).await;
-    result.unwrap()
+    let db = Database::connect(opt).await?;
+    // 数据库可能位于网络盘，WAL模式依赖共享内存无法在网络文件系统中使用
+    db.execute(Statement::from_string(
+        db.get_database_backend(),
+        "PRAGMA journal_mode = DELETE".to_string(),
+    ))
+    .await?;
+    Ok(db)
 }
 
-pub async fn get_database() -> DatabaseConnection {
-    let db = DB.get_or_init(get_conn).await;
-    db.to_owned()
+// 仅一个连接的数据库连接，用于需要设置连接级别PRAGMA的操作
+async fn get_single_conn(file: &str) -> Result<DatabaseConnection, DbErr> {
+    let mut opt = ConnectOptions::new(format!("sqlite://{}", file));
+    opt.max_connections(1)
+        .min_connections(1)
+        .connect_timeout(Duration::from_secs(5));
+    Database::connect(opt).await
+}
+
+// 获取当前数据库连接，打开失败时返回出错信息（下次调用时重试）
+pub async fn get_database() -> Result<DatabaseConnection, DbErr> {
+    let mut db = DB.lock().await;
+    if let Some(conn) = db.as_ref() {
+        return Ok(conn.clone());
+    }
+    let file = get_current_workspace().path;
+    let conn = get_conn(Path::new(&file)).await?;
+    *db = Some(conn.clone());
+    Ok(conn)
+}
+
+// 密钥为全局共享，锁定密钥的测试需获取写锁，避免影响并行的其它测试
+#[cfg(test)]
+static TEST_SECRET: tokio::sync::RwLock<()> = tokio::sync::RwLock::const_new(());
+
+// 测试使用临时目录中的数据库，并初始化变量的密钥，所有测试仅初始化一次
+// 返回的读锁需在测试期间持有，保证密钥已解锁
+#[cfg(test)]
+pub async fn init_test_database() -> tokio::sync::RwLockReadGuard<'static, ()> {
+    static INIT: tokio::sync::OnceCell<()> = tokio::sync::OnceCell::const_new();
+    INIT.get_or_init(|| async {
+        let dir = std::env::temp_dir().join(format!("cyberapi-test-{}", ulid::Ulid::new()));
+        fs::create_dir_all(&dir).unwrap();
+        crate::util::set_app_dir(dir.to_string_lossy().to_string());
+        init_tables().await.unwrap();
+        super::secret::init_secret_key(Some("test".to_string()))
+            .await
+            .unwrap();
+    })
+    .await;
+    TEST_SECRET.read().await
+}
+
+// 锁定密钥执行测试，完成后重新解锁
+#[cfg(test)]
+pub async fn with_secret_locked<F, Fut>(test: F)
+where
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    drop(init_test_database().await);
+    let _guard = TEST_SECRET.write().await;
+    lock_secret();
+    test().await;
+    super::secret::unlock_secret(Some("test".to_string()))
+        .await
+        .unwrap();
+}
+
+// 切换至其它数据库文件，新数据库初始化失败时仍使用原数据库
+pub async fn switch_database(file: &str) -> Result<(), DbErr> {
+    let conn = get_conn(Path::new(file)).await?;
+    let previous = DB.lock().await.replace(conn.clone());
+    if let Err(err) = init_tables().await {
+        *DB.lock().await = previous;
+        conn.close().await?;
+        return Err(err);
+    }
+    // 密钥与数据库对应
+    lock_secret();
+    if let Some(previous) = previous {
+        previous.close().await?;
+    }
+    Ok(())
 }
 
 pub async fn init_tables() -> Result<(), DbErr> {
-    let db = get_database().await;
+    let db = get_database().await?;
     let init_sql_list = vec![
         get_versions_table_create_sql(),
         get_api_collections_create_sql(),
         get_api_folders_create_sql(),
         get_api_settings_create_sql(),
         get_variables_create_sql(),
+        get_global_variables_create_sql(),
+        get_environments_create_sql(),
+        get_api_setting_revisions_create_sql(),
+        get_api_setting_revisions_index_sql(),
+        get_api_examples_create_sql(),
+        get_api_examples_index_sql(),
+        get_file_stores_create_sql(),
+        get_secret_keys_create_sql(),
+        get_oauth_tokens_create_sql(),
+        get_api_setting_search_create_sql(),
     ];
     for sql in init_sql_list {
         db.execute(Statement::from_string(db.get_database_backend(), sql))
             .await?;
     }
+    // 旧版本创建的表新增字段
+    let add_column_list = vec![
+        (
+            get_table_name_api_collection(),
+            "deleted_at TEXT DEFAULT ''",
+        ),
+        (get_table_name_api_folder(), "deleted_at TEXT DEFAULT ''"),
+        (get_table_name_api_setting(), "deleted_at TEXT DEFAULT ''"),
+        (get_table_name_variable(), "secret TEXT DEFAULT ''"),
+        (get_table_name_variable(), "environment TEXT DEFAULT ''"),
+        (get_table_name_api_collection(), "setting TEXT DEFAULT ''"),
+    ];
+    for (table, definition) in add_column_list {
+        add_column_if_not_exists(&db, &table, definition).await?;
+    }
+    migrate_foreign_keys(&db).await?;
+    init_search_index().await?;
+    Ok(())
+}
+
+async fn add_column_if_not_exists<C>(db: &C, table: &str, definition: &str) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let column = definition.split(' ').next().unwrap_or_default();
+    if list_table_columns(db, table)
+        .await?
+        .iter()
+        .any(|item| item == column)
+    {
+        return Ok(());
+    }
+    let sql = format!("ALTER TABLE {} ADD COLUMN {}", table, definition);
+    db.execute(Statement::from_string(db.get_database_backend(), sql))
+        .await?;
+    Ok(())
+}
+
+async fn list_table_columns<C>(db: &C, table: &str) -> Result<Vec<String>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let sql = format!("PRAGMA table_info({})", table);
+    let rows = db
+        .query_all(Statement::from_string(db.get_database_backend(), sql))
+        .await?;
+    let mut columns = vec![];
+    for row in rows {
+        columns.push(row.try_get::<String>("", "name")?);
+    }
+    Ok(columns)
+}
+
+async fn has_foreign_key<C>(db: &C, table: &str) -> Result<bool, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let sql = format!("PRAGMA foreign_key_list({})", table);
+    let rows = db
+        .query_all(Statement::from_string(db.get_database_backend(), sql))
+        .await?;
+    Ok(!rows.is_empty())
+}
+
+// sqlite无法为已存在的表添加外键，只能以新的建表语句创建新表，复制数据后替换原表
+// 需在关闭外键约束的连接中执行，否则删除原表时会级联删除子表的数据
+async fn rebuild_table<C>(
+    db: &C,
+    table: &str,
+    new_table: &str,
+    create_sql: &str,
+) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let backend = db.get_database_backend();
+    db.execute(Statement::from_string(backend, create_sql.to_string()))
+        .await?;
+
+    // 只复制新旧表均存在的字段
+    let current_columns = list_table_columns(db, table).await?;
+    let columns = list_table_columns(db, new_table)
+        .await?
+        .into_iter()
+        .filter(|column| current_columns.contains(column))
+        .collect::<Vec<_>>()
+        .join(",");
+    let sql_list = vec![
+        format!(
+            "INSERT INTO {} ({}) SELECT {} FROM {}",
+            new_table, columns, columns, table
+        ),
+        format!("DROP TABLE {}", table),
+        format!("ALTER TABLE {} RENAME TO {}", new_table, table),
+    ];
+    for sql in sql_list {
+        db.execute(Statement::from_string(backend, sql)).await?;
+    }
+    Ok(())
+}
+
+// 表名与指定表名生成建表语句的函数
+type RebuildTable = (String, fn(&str) -> String);
+
+// 旧版本创建的表无外键约束，清除孤立数据后重建
+// 按sqlite文档的重建流程：事务外关闭外键约束，重建后检查外键再重新开启
+// PRAGMA foreign_keys仅对当前连接生效，因此使用单独的单连接执行
+async fn migrate_foreign_keys(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let tables: Vec<RebuildTable> = vec![
+        (get_table_name_api_folder(), new_api_folders_create_sql),
+        (get_table_name_api_setting(), new_api_settings_create_sql),
+        (get_table_name_variable(), new_variables_create_sql),
+    ];
+    let mut rebuild_tables = vec![];
+    for (name, create_sql) in tables {
+        if !has_foreign_key(db, &name).await? {
+            rebuild_tables.push((name, create_sql));
+        }
+    }
+    if rebuild_tables.is_empty() {
+        return Ok(());
+    }
+
+    let backend = db.get_database_backend();
+    let file = db
+        .query_one(Statement::from_string(
+            backend,
+            "SELECT file FROM pragma_database_list WHERE name = 'main'".to_string(),
+        ))
+        .await?
+        .map(|row| row.try_get::<String>("", "file"))
+        .transpose()?
+        .unwrap_or_default();
+    let conn = get_single_conn(&file).await?;
+    let result = rebuild_tables_without_foreign_keys(&conn, rebuild_tables).await;
+    conn.close().await?;
+    result
+}
+
+async fn rebuild_tables_without_foreign_keys(
+    conn: &DatabaseConnection,
+    tables: Vec<RebuildTable>,
+) -> Result<(), DbErr> {
+    let backend = conn.get_database_backend();
+    conn.execute(Statement::from_string(
+        backend,
+        "PRAGMA foreign_keys = OFF".to_string(),
+    ))
+    .await?;
+    let txn = conn.begin().await?;
+    find_orphans(&txn, false).await?;
+    for (name, create_sql) in tables {
+        let new_table = format!("{}_new", name);
+        rebuild_table(&txn, &name, &new_table, &create_sql(&new_table)).await?;
+    }
+    // 已清除孤立数据，如仍有违反外键约束的数据则回滚
+    let violations = txn
+        .query_all(Statement::from_string(
+            backend,
+            "PRAGMA foreign_key_check".to_string(),
+        ))
+        .await?;
+    if !violations.is_empty() {
+        txn.rollback().await?;
+        return Err(DbErr::Custom(format!(
+            "migrate foreign keys fail, {} rows violate foreign key constraints",
+            violations.len()
+        )));
+    }
+    txn.commit().await?;
+    conn.execute(Statement::from_string(
+        backend,
+        "PRAGMA foreign_keys = ON".to_string(),
+    ))
+    .await?;
     Ok(())
 }
 
@@ -96,6 +387,9 @@ pub async fn export_tables() -> Result<String, CyberAPIError> {
         export_api_folder().await?,
         export_api_setting().await?,
         export_variable().await?,
+        export_global_variable().await?,
+        export_environment().await?,
+        export_api_example().await?,
     ];
     let options = FileOptions::default()
         .compression_method(zip::CompressionMethod::Deflated)
@@ -121,33 +415,84 @@ pub async fn export_tables() -> Result<String, CyberAPIError> {
 pub async fn import_tables(filename: String) -> Result<(), CyberAPIError> {
     let mut r = zip::ZipArchive::new(File::open(filename)?)?;
 
+    delete_all_api_example().await?;
     delete_all_api_collection().await?;
     delete_all_api_folder().await?;
     delete_all_api_setting().await?;
     delete_all_variable().await?;
+    delete_all_global_variable().await?;
+    delete_all_environment().await?;
 
+    let mut collections = HashSet::new();
+    let mut settings = HashSet::new();
     let names = vec![
         get_table_name_api_collection(),
         get_table_name_api_folder(),
         get_table_name_api_setting(),
         get_table_name_variable(),
+        get_table_name_global_variable(),
+        get_table_name_environment(),
+        get_table_name_api_example(),
     ];
     for i in 0..names.len() {
         let name = names.get(i).unwrap();
         let mut buf = Vec::new();
         {
-            let mut file = r.by_name((name.to_owned() + ".json").as_str())?;
+            let mut file = match r.by_name((name.to_owned() + ".json").as_str()) {
+                Ok(file) => file,
+                // 旧版本的备份无全局变量、环境与示例
+                Err(ZipError::FileNotFound) if i >= 4 => continue,
+                Err(err) => return Err(err.into()),
+            };
             file.read_to_end(&mut buf)?;
         }
-        let data: Vec<serde_json::Value> = serde_json::from_slice(&buf)?;
+        let mut data: Vec<serde_json::Value> = serde_json::from_slice(&buf)?;
+        if i == 0 {
+            for ele in data.iter() {
+                if let Some(id) = ele.get("id").and_then(|id| id.as_str()) {
+                    collections.insert(id.to_string());
+                }
+            }
+        } else if i == 6 {
+            // 忽略API配置已不存在的示例
+            data.retain(|ele| {
+                ele.get("api_setting")
+                    .and_then(|api_setting| api_setting.as_str())
+                    .map(|api_setting| settings.contains(api_setting))
+                    .unwrap_or_default()
+            });
+        } else if i != 4 {
+            // 忽略collection已不存在的数据，否则违反外键约束
+            data.retain(|ele| {
+                ele.get("collection")
+                    .and_then(|collection| collection.as_str())
+                    .map(|collection| collections.contains(collection))
+                    .unwrap_or_default()
+            });
+        }
+        if i == 2 {
+            for ele in data.iter() {
+                if let Some(id) = ele.get("id").and_then(|id| id.as_str()) {
+                    settings.insert(id.to_string());
+                }
+            }
+        }
+        // 空数据无需导入，批量插入空数据会出错
+        if data.is_empty() {
+            continue;
+        }
         match i {
             0 => import_api_collection(data).await?,
             1 => import_api_folder(data).await?,
             2 => import_api_setting(data).await?,
             3 => import_variable(data).await?,
+            4 => import_global_variable(data).await?,
+            5 => import_environment(data).await?,
+            6 => import_api_example(data).await?,
             _ => (),
         }
     }
+    rebuild_search_index().await?;
 
     Ok(())
 }
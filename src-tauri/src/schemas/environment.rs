@@ -0,0 +1,455 @@
+use crate::{
+    entities::{environments, prelude::*, variables},
+    error::CyberAPIError,
+};
+use chrono::Utc;
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DbErr, EntityTrait,
+    NotSet, QueryFilter, Set, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use super::database::{get_database, ExportData};
+use super::file_store::sync_file_store_after_commit;
+use super::secret::{decrypt_secret, SECRET_MASK};
+use super::variable::{is_secret, VARIABLE_CATEGORY_ENV, VARIABLE_ENABLED};
+
+// 当前使用的环境
+pub const ENVIRONMENT_ACTIVE: &str = "1";
+
+// collection的环境（如dev、staging、prod），环境变量为category=env且environment为该环境ID的变量
+// 未指定environment的环境变量为基础环境，所有环境均继承基础环境
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Environment {
+    // id
+    pub id: String,
+    // collection ID
+    pub collection: String,
+    // 名称
+    pub name: Option<String>,
+    // 继承的环境ID，为空则仅继承基础环境
+    pub base: Option<String>,
+    // 是否当前使用的环境(1:是)
+    pub active: Option<String>,
+    // 创建时间
+    pub created_at: Option<String>,
+    // 更新时间
+    pub updated_at: Option<String>,
+}
+
+impl From<environments::Model> for Environment {
+    fn from(model: environments::Model) -> Self {
+        Environment {
+            id: model.id,
+            collection: model.collection,
+            name: model.name,
+            base: model.base,
+            active: model.active,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+        }
+    }
+}
+
+impl Environment {
+    // 是否使用只能通过activate_environment修改
+    fn into_active_model(self) -> environments::ActiveModel {
+        let created_at = self.created_at.or_else(|| Some(Utc::now().to_rfc3339()));
+        let updated_at = self.updated_at.or_else(|| Some(Utc::now().to_rfc3339()));
+        environments::ActiveModel {
+            id: Set(self.id),
+            collection: Set(self.collection),
+            name: Set(self.name),
+            base: Set(Some(self.base.unwrap_or_default())),
+            active: NotSet,
+            created_at: Set(created_at),
+            updated_at: Set(updated_at),
+        }
+    }
+}
+
+// 两个环境中值不同的变量
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentChange {
+    // 变量名称
+    pub name: String,
+    // from环境中的值，未定义时为null
+    pub before: Option<String>,
+    // to环境中的值，未定义时为null
+    pub after: Option<String>,
+}
+
+pub fn get_environments_create_sql() -> String {
+    "CREATE TABLE IF NOT EXISTS environments (
+        id TEXT PRIMARY KEY NOT NULL check (id != ''),
+        collection TEXT NOT NULL check (collection != '')
+            REFERENCES api_collections(id) ON DELETE CASCADE,
+        name TEXT DEFAULT '',
+        base TEXT DEFAULT '',
+        active TEXT DEFAULT '',
+        created_at TEXT DEFAULT '',
+        updated_at TEXT DEFAULT ''
+    )"
+    .to_string()
+}
+
+fn new_error(message: &str) -> DbErr {
+    DbErr::Custom(format!("environment: {}", message))
+}
+
+// 变量所属的环境，基础环境的变量environment为空（旧版本的数据可能为null）
+pub fn environment_condition(environment: &str) -> Condition {
+    if environment.is_empty() {
+        return Condition::any()
+            .add(variables::Column::Environment.eq(""))
+            .add(variables::Column::Environment.is_null());
+    }
+    Condition::all().add(variables::Column::Environment.eq(environment))
+}
+
+async fn find_environment<C>(db: &C, id: &str) -> Result<environments::Model, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Environments::find_by_id(id.to_string())
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound(format!("environment: {}", id)))
+}
+
+// 获取环境的继承链（不包括基础环境），由最上层的环境至当前环境
+async fn get_environment_chain<C>(db: &C, collection: &str, id: &str) -> Result<Vec<String>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let bases: HashMap<String, String> = Environments::find()
+        .filter(environments::Column::Collection.eq(collection))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|item| (item.id, item.base.unwrap_or_default()))
+        .collect();
+    let mut chain: Vec<String> = vec![];
+    let mut current = id.to_string();
+    while !current.is_empty() {
+        if chain.contains(&current) {
+            return Err(new_error("circular inheritance"));
+        }
+        let base = bases
+            .get(&current)
+            .ok_or_else(|| new_error(&format!("{} is not found in the collection", current)))?
+            .clone();
+        chain.push(current);
+        current = base;
+    }
+    chain.reverse();
+    Ok(chain)
+}
+
+// 继承的环境需属于同一collection且不能循环继承
+async fn check_base<C>(db: &C, value: &Environment) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let base = value.base.clone().unwrap_or_default();
+    if base.is_empty() {
+        return Ok(());
+    }
+    let chain = get_environment_chain(db, &value.collection, &base).await?;
+    if chain.contains(&value.id) {
+        return Err(new_error("circular inheritance"));
+    }
+    Ok(())
+}
+
+pub async fn add_environment(value: Environment) -> Result<Environment, DbErr> {
+    let db = get_database().await?;
+    check_base(&db, &value).await?;
+    let mut model = value.into_active_model();
+    model.active = Set(Some("".to_string()));
+    let result = model.insert(&db).await?;
+    sync_file_store_after_commit(&result.collection).await;
+    Ok(result.into())
+}
+
+pub async fn update_environment(value: Environment) -> Result<Environment, DbErr> {
+    let db = get_database().await?;
+    check_base(&db, &value).await?;
+    let result = value.into_active_model().update(&db).await?;
+    sync_file_store_after_commit(&result.collection).await;
+    Ok(result.into())
+}
+
+pub async fn list_environment(collection: String) -> Result<Vec<Environment>, DbErr> {
+    let db = get_database().await?;
+    let mut result = Environments::find()
+        .filter(environments::Column::Collection.eq(collection))
+        .all(&db)
+        .await?;
+    result.sort_by(|a, b| (&a.name, &a.id).cmp(&(&b.name, &b.id)));
+    Ok(result.into_iter().map(Environment::from).collect())
+}
+
+// 删除环境及其变量，继承该环境的改为继承其上层环境
+pub async fn delete_environment(id: String) -> Result<(), DbErr> {
+    let db = get_database().await?;
+    let model = find_environment(&db, &id).await?;
+    let txn = db.begin().await?;
+    Environments::update_many()
+        .col_expr(
+            environments::Column::Base,
+            Expr::value(model.base.clone().unwrap_or_default()),
+        )
+        .filter(environments::Column::Collection.eq(model.collection.clone()))
+        .filter(environments::Column::Base.eq(id.clone()))
+        .exec(&txn)
+        .await?;
+    Variables::delete_many()
+        .filter(variables::Column::Collection.eq(model.collection.clone()))
+        .filter(variables::Column::Environment.eq(id.clone()))
+        .exec(&txn)
+        .await?;
+    Environments::delete_by_id(id).exec(&txn).await?;
+    txn.commit().await?;
+    sync_file_store_after_commit(&model.collection).await;
+    Ok(())
+}
+
+// 复制环境及其变量，新环境继承相同的上层环境
+pub async fn clone_environment(id: String, name: String) -> Result<Environment, DbErr> {
+    let db = get_database().await?;
+    let model = find_environment(&db, &id).await?;
+    let now = Utc::now().to_rfc3339();
+    let new_id = ulid::Ulid::new().to_string();
+    let txn = db.begin().await?;
+    let result = environments::ActiveModel {
+        id: Set(new_id.clone()),
+        collection: Set(model.collection.clone()),
+        name: Set(Some(name)),
+        base: Set(model.base),
+        active: Set(Some("".to_string())),
+        created_at: Set(Some(now.clone())),
+        updated_at: Set(Some(now.clone())),
+    }
+    .insert(&txn)
+    .await?;
+    let items = Variables::find()
+        .filter(variables::Column::Collection.eq(model.collection.clone()))
+        .filter(variables::Column::Environment.eq(id))
+        .all(&txn)
+        .await?;
+    for item in items {
+        // 加密变量使用同一密钥，直接复制加密后的值
+        variables::ActiveModel {
+            id: Set(ulid::Ulid::new().to_string()),
+            environment: Set(Some(new_id.clone())),
+            created_at: Set(Some(now.clone())),
+            updated_at: Set(Some(now.clone())),
+            category: Set(item.category),
+            collection: Set(item.collection),
+            name: Set(item.name),
+            value: Set(item.value),
+            enabled: Set(item.enabled),
+            secret: Set(item.secret),
+        }
+        .insert(&txn)
+        .await?;
+    }
+    txn.commit().await?;
+    sync_file_store_after_commit(&result.collection).await;
+    Ok(result.into())
+}
+
+// 设置collection当前使用的环境，为空则仅使用基础环境
+pub async fn activate_environment(collection: String, id: Option<String>) -> Result<(), DbErr> {
+    let db = get_database().await?;
+    let id = id.unwrap_or_default();
+    if !id.is_empty() && find_environment(&db, &id).await?.collection != collection {
+        return Err(new_error(&format!("{} is not found in the collection", id)));
+    }
+    let txn = db.begin().await?;
+    Environments::update_many()
+        .col_expr(environments::Column::Active, Expr::value(""))
+        .filter(environments::Column::Collection.eq(collection))
+        .exec(&txn)
+        .await?;
+    if !id.is_empty() {
+        Environments::update_many()
+            .col_expr(
+                environments::Column::Active,
+                Expr::value(ENVIRONMENT_ACTIVE),
+            )
+            .filter(environments::Column::Id.eq(id))
+            .exec(&txn)
+            .await?;
+    }
+    txn.commit().await?;
+    Ok(())
+}
+
+pub async fn get_active_environment(collection: String) -> Result<Option<Environment>, DbErr> {
+    let db = get_database().await?;
+    let result = find_active_environment(&db, &collection).await?;
+    Ok(result.map(Environment::from))
+}
+
+pub async fn find_active_environment<C>(
+    db: &C,
+    collection: &str,
+) -> Result<Option<environments::Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Environments::find()
+        .filter(environments::Column::Collection.eq(collection))
+        .filter(environments::Column::Active.eq(ENVIRONMENT_ACTIVE))
+        .one(db)
+        .await
+}
+
+// 获取环境生效的变量（包括继承的），同名变量以下层环境的为准
+// id为空时为基础环境
+pub async fn list_environment_variables<C>(
+    db: &C,
+    collection: &str,
+    id: &str,
+) -> Result<Vec<variables::Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let mut chain = vec!["".to_string()];
+    if !id.is_empty() {
+        chain.extend(get_environment_chain(db, collection, id).await?);
+    }
+    let mut condition = Condition::any();
+    for item in chain.iter() {
+        condition = condition.add(environment_condition(item));
+    }
+    let mut items = Variables::find()
+        .filter(variables::Column::Collection.eq(collection))
+        .filter(variables::Column::Category.eq(VARIABLE_CATEGORY_ENV))
+        .filter(variables::Column::Enabled.eq(VARIABLE_ENABLED))
+        .filter(condition)
+        .all(db)
+        .await?;
+    items.sort_by_key(|item| {
+        let environment = item.environment.clone().unwrap_or_default();
+        chain.iter().position(|id| id == &environment)
+    });
+    let mut result: BTreeMap<String, variables::Model> = BTreeMap::new();
+    for item in items {
+        let name = item.name.clone().unwrap_or_default();
+        if !name.is_empty() {
+            result.insert(name, item);
+        }
+    }
+    Ok(result.into_values().collect())
+}
+
+// 获取collection当前使用的环境的变量
+pub async fn list_active_environment_variables<C>(
+    db: &C,
+    collection: &str,
+) -> Result<Vec<variables::Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let id = find_active_environment(db, collection)
+        .await?
+        .map(|item| item.id)
+        .unwrap_or_default();
+    list_environment_variables(db, collection, &id).await
+}
+
+// 对比时加密变量以解密后的值对比，展示时仍为掩码
+// 密文的nonce随机，无法以密文对比，因此密钥未解锁时返回出错
+async fn get_compare_values<C>(
+    db: &C,
+    collection: &str,
+    id: &str,
+) -> Result<HashMap<String, (String, bool)>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let mut result = HashMap::new();
+    for item in list_environment_variables(db, collection, id).await? {
+        let secret = is_secret(&item.secret);
+        let mut value = item.value.unwrap_or_default();
+        if secret {
+            value = decrypt_secret(db, &value).await?;
+        }
+        result.insert(item.name.unwrap_or_default(), (value, secret));
+    }
+    Ok(result)
+}
+
+// 对比两个环境（包括继承的变量），为空则为基础环境
+pub async fn compare_environment(
+    collection: String,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Vec<EnvironmentChange>, DbErr> {
+    let db = get_database().await?;
+    let before = get_compare_values(&db, &collection, &from.unwrap_or_default()).await?;
+    let after = get_compare_values(&db, &collection, &to.unwrap_or_default()).await?;
+    let names: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    let display = |value: Option<&(String, bool)>| {
+        value.map(|(value, secret)| {
+            if *secret && !value.is_empty() {
+                SECRET_MASK.to_string()
+            } else {
+                value.clone()
+            }
+        })
+    };
+    let mut changes = vec![];
+    for name in names {
+        let before = before.get(name);
+        let after = after.get(name);
+        if before.map(|item| &item.0) == after.map(|item| &item.0) {
+            continue;
+        }
+        changes.push(EnvironmentChange {
+            name: name.clone(),
+            before: display(before),
+            after: display(after),
+        });
+    }
+    Ok(changes)
+}
+
+pub fn get_table_name_environment() -> String {
+    "environments".to_string()
+}
+
+pub async fn delete_all_environment() -> Result<(), CyberAPIError> {
+    let db = get_database().await?;
+    Environments::delete_many().exec(&db).await?;
+    Ok(())
+}
+
+pub async fn export_environment() -> Result<ExportData, DbErr> {
+    let db = get_database().await?;
+    let data = Environments::find().into_json().all(&db).await?;
+    Ok(ExportData {
+        name: get_table_name_environment(),
+        data,
+    })
+}
+
+pub async fn import_environment(data: Vec<serde_json::Value>) -> Result<(), CyberAPIError> {
+    let db = get_database().await?;
+    let mut records = Vec::new();
+    for ele in data {
+        let model = environments::ActiveModel::from_json(ele)?;
+        records.push(model);
+    }
+    if records.is_empty() {
+        return Ok(());
+    }
+    Environments::insert_many(records).exec(&db).await?;
+    Ok(())
+}
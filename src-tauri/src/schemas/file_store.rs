@@ -0,0 +1,1158 @@
+use crate::entities::{
+    api_collections, api_folders, api_settings, environments, file_stores, prelude::*, variables,
+};
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, NotSet,
+    QueryFilter, Set, TransactionTrait,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use tokio::sync::{broadcast, Mutex};
+
+use super::api_collection::APICollection;
+use super::api_setting_revision::save_api_setting_revision;
+use super::database::get_database;
+use super::search::index_api_settings;
+use super::secret::encrypt_secret;
+use super::variable::is_secret;
+
+// collection目录的文件结构：
+// collection.json            collection信息
+// variables.json             collection的变量
+// environments.json          collection的环境
+// <目录名>/folder.json       目录信息，子元素的顺序
+// <名称>.request.json        API配置
+const COLLECTION_FILE: &str = "collection.json";
+const VARIABLES_FILE: &str = "variables.json";
+const ENVIRONMENTS_FILE: &str = "environments.json";
+const FOLDER_FILE: &str = "folder.json";
+const REQUEST_FILE_SUFFIX: &str = ".request.json";
+
+const PATH_SEPARATOR: &str = "/";
+
+// 相对路径（以/分隔）与文件内容
+type FileMap = BTreeMap<String, String>;
+
+// 数据保存后同步目录失败的通知，由file_watcher转发至前端
+static SYNC_FAILURES: Lazy<broadcast::Sender<FileStoreSyncFailure>> =
+    Lazy::new(|| broadcast::channel(16).0);
+
+#[derive(Debug, Clone)]
+pub struct FileStoreSyncFailure {
+    pub collection: String,
+    pub message: String,
+}
+
+// 最近一次同步后的文件内容，用于判断哪些文件被外部修改
+static FILE_SNAPSHOTS: Lazy<Mutex<HashMap<String, FileMap>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileStore {
+    // collection ID
+    pub collection: String,
+    // 保存的目录
+    pub dir: String,
+    // 创建时间
+    pub created_at: Option<String>,
+    // 更新时间
+    pub updated_at: Option<String>,
+}
+
+impl From<file_stores::Model> for FileStore {
+    fn from(model: file_stores::Model) -> Self {
+        FileStore {
+            collection: model.collection,
+            dir: model.dir,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+        }
+    }
+}
+
+pub fn get_file_stores_create_sql() -> String {
+    "CREATE TABLE IF NOT EXISTS file_stores (
+        collection TEXT PRIMARY KEY NOT NULL check (collection != '')
+            REFERENCES api_collections(id) ON DELETE CASCADE,
+        dir TEXT NOT NULL check (dir != ''),
+        created_at TEXT DEFAULT '',
+        updated_at TEXT DEFAULT ''
+    )"
+    .to_string()
+}
+
+// 文件中的字段顺序固定，便于版本管理时对比
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+struct CollectionFile {
+    id: String,
+    name: String,
+    description: String,
+    #[serde(skip_serializing_if = "Value::is_null")]
+    setting: Value,
+    created_at: String,
+    updated_at: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+struct FolderFile {
+    id: String,
+    name: String,
+    // 子目录与API配置的顺序
+    children: Vec<String>,
+    created_at: String,
+    updated_at: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+struct RequestFile {
+    id: String,
+    name: String,
+    category: String,
+    // 配置信息以json展开，非json格式的则为字符串
+    setting: Value,
+    created_at: String,
+    updated_at: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+struct VariableFile {
+    id: String,
+    category: String,
+    // 基础环境的变量不保存该字段
+    #[serde(skip_serializing_if = "String::is_empty")]
+    environment: String,
+    name: String,
+    value: String,
+    enabled: String,
+    // 加密变量的值不保存至文件
+    #[serde(skip_serializing_if = "String::is_empty")]
+    secret: String,
+    created_at: String,
+    updated_at: String,
+}
+
+// 当前使用的环境为本机的设置，不保存至文件
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+struct EnvironmentFile {
+    id: String,
+    name: String,
+    base: String,
+    created_at: String,
+    updated_at: String,
+}
+
+// 解析后的元素与其所在目录的ID（根目录为空）
+#[derive(Debug, Clone, PartialEq)]
+struct ParsedItem<T> {
+    item: T,
+    parent: String,
+}
+
+#[derive(Debug, Default)]
+struct ParsedData {
+    collection: CollectionFile,
+    variables: Vec<VariableFile>,
+    environments: Vec<EnvironmentFile>,
+    folders: Vec<ParsedItem<FolderFile>>,
+    settings: Vec<ParsedItem<RequestFile>>,
+}
+
+fn file_store_error(message: String) -> DbErr {
+    DbErr::Custom(format!("file store: {}", message))
+}
+
+// 按key排序，保证输出的顺序稳定
+fn sort_json_value(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            let mut result = serde_json::Map::new();
+            for (key, value) in entries {
+                result.insert(key, sort_json_value(value));
+            }
+            Value::Object(result)
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(sort_json_value).collect()),
+        _ => value,
+    }
+}
+
+fn to_file_content<T: Serialize>(value: &T) -> Result<String, DbErr> {
+    let content =
+        serde_json::to_string_pretty(value).map_err(|err| file_store_error(err.to_string()))?;
+    Ok(content + "\n")
+}
+
+fn from_file_content<T: DeserializeOwned>(path: &str, content: &str) -> Result<T, DbErr> {
+    serde_json::from_str(content).map_err(|err| file_store_error(format!("{}: {}", path, err)))
+}
+
+fn setting_to_value(setting: &str) -> Value {
+    if setting.is_empty() {
+        return Value::Null;
+    }
+    match serde_json::from_str::<Value>(setting) {
+        Ok(value) => sort_json_value(value),
+        Err(_) => Value::String(setting.to_string()),
+    }
+}
+
+fn value_to_setting(value: &Value) -> String {
+    match value {
+        Value::Null => "".to_string(),
+        Value::String(value) => value.clone(),
+        _ => value.to_string(),
+    }
+}
+
+fn join_path(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        return name.to_string();
+    }
+    format!("{}{}{}", dir, PATH_SEPARATOR, name)
+}
+
+fn split_path(path: &str) -> (&str, &str) {
+    match path.rfind(PATH_SEPARATOR) {
+        Some(index) => (&path[..index], &path[index + 1..]),
+        None => ("", path),
+    }
+}
+
+fn split_children(children: &Option<String>) -> Vec<String> {
+    let mut result = vec![];
+    for child in children.as_deref().unwrap_or_default().split(',') {
+        let child = child.trim();
+        if !child.is_empty() && !result.iter().any(|item| item == child) {
+            result.push(child.to_string());
+        }
+    }
+    result
+}
+
+// 替换文件名中不允许的字符，以.开头的文件会被忽略因此去除
+fn sanitize_file_name(name: &str, id: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_control() || "/\\:*?\"<>|".contains(c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    let name = name.trim().trim_matches('.').trim();
+    if name.is_empty() {
+        return id.to_string();
+    }
+    name.to_string()
+}
+
+// 同一目录下名称重复（不区分大小写）时添加id后缀
+fn unique_file_name(used: &mut HashSet<String>, name: &str, suffix: &str, id: &str) -> String {
+    let mut file_name = format!("{}{}", name, suffix);
+    if used.contains(&file_name.to_lowercase()) {
+        file_name = format!("{}-{}{}", name, id, suffix);
+    }
+    used.insert(file_name.to_lowercase());
+    file_name
+}
+
+fn is_store_file(name: &str) -> bool {
+    name == COLLECTION_FILE
+        || name == VARIABLES_FILE
+        || name == ENVIRONMENTS_FILE
+        || name == FOLDER_FILE
+        || name.ends_with(REQUEST_FILE_SUFFIX)
+}
+
+struct FileMapBuilder<'a> {
+    folders: HashMap<&'a str, &'a api_folders::Model>,
+    settings: HashMap<&'a str, &'a api_settings::Model>,
+    visited: HashSet<String>,
+    files: FileMap,
+}
+
+impl<'a> FileMapBuilder<'a> {
+    fn add_children(&mut self, dir: &str, ids: Vec<String>) -> Result<(), DbErr> {
+        // 避免目录关系异常时重复输出
+        let visited = &mut self.visited;
+        let ids: Vec<String> = ids
+            .into_iter()
+            .filter(|id| visited.insert(id.clone()))
+            .collect();
+        // 按id顺序处理重名，调整顺序时文件名不变
+        let mut sorted_ids = ids.clone();
+        sorted_ids.sort();
+        let mut used = HashSet::new();
+        let mut file_names = HashMap::new();
+        for id in sorted_ids {
+            let file_name = if let Some(folder) = self.folders.get(id.as_str()) {
+                let name = sanitize_file_name(folder.name.as_deref().unwrap_or_default(), &id);
+                unique_file_name(&mut used, &name, "", &id)
+            } else if let Some(setting) = self.settings.get(id.as_str()) {
+                let name = sanitize_file_name(setting.name.as_deref().unwrap_or_default(), &id);
+                unique_file_name(&mut used, &name, REQUEST_FILE_SUFFIX, &id)
+            } else {
+                continue;
+            };
+            file_names.insert(id, file_name);
+        }
+
+        for id in ids {
+            let path = match file_names.get(&id) {
+                Some(file_name) => join_path(dir, file_name),
+                None => continue,
+            };
+            if let Some(folder) = self.folders.get(id.as_str()).copied() {
+                let children: Vec<String> = split_children(&folder.children)
+                    .into_iter()
+                    .filter(|child| {
+                        !self.visited.contains(child)
+                            && (self.folders.contains_key(child.as_str())
+                                || self.settings.contains_key(child.as_str()))
+                    })
+                    .collect();
+                let content = to_file_content(&FolderFile {
+                    id: folder.id.clone(),
+                    name: folder.name.clone().unwrap_or_default(),
+                    children: children.clone(),
+                    created_at: folder.created_at.clone().unwrap_or_default(),
+                    updated_at: folder.updated_at.clone().unwrap_or_default(),
+                })?;
+                self.files.insert(join_path(&path, FOLDER_FILE), content);
+                self.add_children(&path, children)?;
+            } else if let Some(setting) = self.settings.get(id.as_str()).copied() {
+                let content = to_file_content(&RequestFile {
+                    id: setting.id.clone(),
+                    name: setting.name.clone().unwrap_or_default(),
+                    category: setting.category.clone().unwrap_or_default(),
+                    setting: setting_to_value(setting.setting.as_deref().unwrap_or_default()),
+                    created_at: setting.created_at.clone().unwrap_or_default(),
+                    updated_at: setting.updated_at.clone().unwrap_or_default(),
+                })?;
+                self.files.insert(path, content);
+            }
+        }
+        Ok(())
+    }
+}
+
+// 根据数据库中的数据生成collection目录的文件
+async fn build_file_map<C>(db: &C, collection: &str) -> Result<FileMap, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let model = ApiCollections::find_by_id(collection)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound(format!("api collection: {}", collection)))?;
+    let folders = ApiFolders::find()
+        .filter(api_folders::Column::Collection.eq(collection))
+        .filter(api_folders::Column::DeletedAt.eq(""))
+        .all(db)
+        .await?;
+    let settings = ApiSettings::find()
+        .filter(api_settings::Column::Collection.eq(collection))
+        .filter(api_settings::Column::DeletedAt.eq(""))
+        .all(db)
+        .await?;
+    let mut variables = Variables::find()
+        .filter(variables::Column::Collection.eq(collection))
+        .all(db)
+        .await?;
+    let mut environments = Environments::find()
+        .filter(environments::Column::Collection.eq(collection))
+        .all(db)
+        .await?;
+
+    let mut builder = FileMapBuilder {
+        folders: folders
+            .iter()
+            .map(|item| (item.id.as_str(), item))
+            .collect(),
+        settings: settings
+            .iter()
+            .map(|item| (item.id.as_str(), item))
+            .collect(),
+        visited: HashSet::new(),
+        files: FileMap::new(),
+    };
+    builder.files.insert(
+        COLLECTION_FILE.to_string(),
+        to_file_content(&CollectionFile {
+            id: model.id,
+            name: model.name.unwrap_or_default(),
+            description: model.description.unwrap_or_default(),
+            setting: setting_to_value(model.setting.as_deref().unwrap_or_default()),
+            created_at: model.created_at.unwrap_or_default(),
+            updated_at: model.updated_at.unwrap_or_default(),
+        })?,
+    );
+    if !variables.is_empty() {
+        variables.sort_by(|a, b| {
+            (&a.category, &a.environment, &a.name, &a.id).cmp(&(
+                &b.category,
+                &b.environment,
+                &b.name,
+                &b.id,
+            ))
+        });
+        let items: Vec<VariableFile> = variables
+            .into_iter()
+            .map(|item| VariableFile {
+                value: if is_secret(&item.secret) {
+                    "".to_string()
+                } else {
+                    item.value.unwrap_or_default()
+                },
+                id: item.id,
+                category: item.category,
+                environment: item.environment.unwrap_or_default(),
+                name: item.name.unwrap_or_default(),
+                enabled: item.enabled.unwrap_or_default(),
+                secret: item.secret.unwrap_or_default(),
+                created_at: item.created_at.unwrap_or_default(),
+                updated_at: item.updated_at.unwrap_or_default(),
+            })
+            .collect();
+        builder
+            .files
+            .insert(VARIABLES_FILE.to_string(), to_file_content(&items)?);
+    }
+    if !environments.is_empty() {
+        environments.sort_by(|a, b| (&a.name, &a.id).cmp(&(&b.name, &b.id)));
+        let items: Vec<EnvironmentFile> = environments
+            .into_iter()
+            .map(|item| EnvironmentFile {
+                id: item.id,
+                name: item.name.unwrap_or_default(),
+                base: item.base.unwrap_or_default(),
+                created_at: item.created_at.unwrap_or_default(),
+                updated_at: item.updated_at.unwrap_or_default(),
+            })
+            .collect();
+        builder
+            .files
+            .insert(ENVIRONMENTS_FILE.to_string(), to_file_content(&items)?);
+    }
+
+    // 不属于任何目录的为顶层元素
+    let mut children = HashSet::new();
+    for folder in folders.iter() {
+        for child in split_children(&folder.children) {
+            children.insert(child);
+        }
+    }
+    let mut roots: Vec<String> = folders
+        .iter()
+        .map(|item| item.id.clone())
+        .chain(settings.iter().map(|item| item.id.clone()))
+        .filter(|id| !children.contains(id))
+        .collect();
+    roots.sort();
+    builder.add_children("", roots)?;
+
+    Ok(builder.files)
+}
+
+fn read_dir_files(root: &Path, dir: &str, files: &mut FileMap) -> Result<(), DbErr> {
+    let entries = fs::read_dir(root.join(dir)).map_err(|err| file_store_error(err.to_string()))?;
+    for entry in entries {
+        let entry = entry.map_err(|err| file_store_error(err.to_string()))?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        // 忽略.git等隐藏文件
+        if name.starts_with('.') {
+            continue;
+        }
+        let path = join_path(dir, &name);
+        let file_type = entry
+            .file_type()
+            .map_err(|err| file_store_error(err.to_string()))?;
+        if file_type.is_dir() {
+            read_dir_files(root, &path, files)?;
+        } else if file_type.is_file() && is_store_file(&name) {
+            let content = fs::read_to_string(entry.path())
+                .map_err(|err| file_store_error(format!("{}: {}", path, err)))?;
+            files.insert(path, content);
+        }
+    }
+    Ok(())
+}
+
+fn read_file_map(dir: &str) -> Result<FileMap, DbErr> {
+    let mut files = FileMap::new();
+    read_dir_files(Path::new(dir), "", &mut files)?;
+    Ok(files)
+}
+
+// 删除不再包含任何文件的目录，返回目录是否为空
+fn remove_empty_dirs(root: &Path, dir: &str) -> Result<bool, DbErr> {
+    let mut empty = true;
+    let entries = fs::read_dir(root.join(dir)).map_err(|err| file_store_error(err.to_string()))?;
+    for entry in entries {
+        let entry = entry.map_err(|err| file_store_error(err.to_string()))?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or_default();
+        if is_dir && !name.starts_with('.') && remove_empty_dirs(root, &join_path(dir, &name))? {
+            fs::remove_dir(entry.path()).map_err(|err| file_store_error(err.to_string()))?;
+            continue;
+        }
+        empty = false;
+    }
+    Ok(empty)
+}
+
+// 先写入同目录的临时文件再重命名，避免写入过程中崩溃导致文件不完整而被当作外部修改
+// 临时文件以.开头，读取与监听时均忽略
+fn write_file_atomic(file: &Path, content: &str) -> std::io::Result<()> {
+    let name = file
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let tmp = file.with_file_name(format!(".{}.tmp", name));
+    let mut tmp_file = fs::File::create(&tmp)?;
+    tmp_file.write_all(content.as_bytes())?;
+    tmp_file.sync_all()?;
+    fs::rename(&tmp, file)
+}
+
+// 仅写入有变化的文件，并删除已不存在的文件
+fn write_file_map(dir: &str, files: &FileMap) -> Result<(), DbErr> {
+    let root = Path::new(dir);
+    let current = read_file_map(dir)?;
+    for (path, content) in files.iter() {
+        if current.get(path) == Some(content) {
+            continue;
+        }
+        let file = root.join(path);
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent).map_err(|err| file_store_error(err.to_string()))?;
+        }
+        write_file_atomic(&file, content)
+            .map_err(|err| file_store_error(format!("{}: {}", path, err)))?;
+    }
+    let mut removed = false;
+    for path in current.keys() {
+        if !files.contains_key(path) {
+            fs::remove_file(root.join(path))
+                .map_err(|err| file_store_error(format!("{}: {}", path, err)))?;
+            removed = true;
+        }
+    }
+    if removed {
+        remove_empty_dirs(root, "")?;
+    }
+    Ok(())
+}
+
+fn new_id(used: &mut HashSet<String>, id: &str) -> String {
+    // 复制文件时id会重复，重新生成
+    if id.is_empty() || used.contains(id) {
+        let id = ulid::Ulid::new().to_string();
+        used.insert(id.clone());
+        return id;
+    }
+    used.insert(id.to_string());
+    id.to_string()
+}
+
+// 解析collection目录的文件，目录层级即为目录关系
+// id重复（复制文件）时，base中已有的文件保留原id
+fn parse_file_map(files: &FileMap, base: &FileMap) -> Result<ParsedData, DbErr> {
+    let mut data = ParsedData::default();
+    let content = files
+        .get(COLLECTION_FILE)
+        .ok_or_else(|| file_store_error(format!("{} is not found", COLLECTION_FILE)))?;
+    data.collection = from_file_content(COLLECTION_FILE, content)?;
+
+    let mut used = HashSet::new();
+    if let Some(content) = files.get(VARIABLES_FILE) {
+        let variables: Vec<VariableFile> = from_file_content(VARIABLES_FILE, content)?;
+        for mut item in variables {
+            item.id = new_id(&mut used, &item.id);
+            data.variables.push(item);
+        }
+    }
+    if let Some(content) = files.get(ENVIRONMENTS_FILE) {
+        let environments: Vec<EnvironmentFile> = from_file_content(ENVIRONMENTS_FILE, content)?;
+        for mut item in environments {
+            item.id = new_id(&mut used, &item.id);
+            data.environments.push(item);
+        }
+    }
+
+    // 所有包含文件的目录均作为API目录，未有folder.json的以目录名称为名
+    let mut dirs = BTreeSet::new();
+    for path in files.keys() {
+        let mut dir = split_path(path).0;
+        while !dir.is_empty() {
+            dirs.insert(dir.to_string());
+            dir = split_path(dir).0;
+        }
+    }
+    let mut dirs: Vec<String> = dirs.into_iter().collect();
+    dirs.sort_by_key(|dir| !base.contains_key(&join_path(dir, FOLDER_FILE)));
+    let mut folder_ids = HashMap::new();
+    let mut folders = vec![];
+    for dir in dirs.iter() {
+        let path = join_path(dir, FOLDER_FILE);
+        let mut folder: FolderFile = match files.get(&path) {
+            Some(content) => from_file_content(&path, content)?,
+            None => FolderFile::default(),
+        };
+        if folder.name.is_empty() {
+            folder.name = split_path(dir).1.to_string();
+        }
+        folder.id = new_id(&mut used, &folder.id);
+        folder_ids.insert(dir.clone(), folder.id.clone());
+        folders.push((dir.clone(), folder));
+    }
+
+    // 各目录下的子元素：(id, 路径)
+    let mut dir_children: HashMap<String, Vec<(String, String)>> = HashMap::new();
+    for (dir, folder) in folders.iter() {
+        let parent = split_path(dir).0.to_string();
+        dir_children
+            .entry(parent)
+            .or_default()
+            .push((folder.id.clone(), dir.clone()));
+    }
+    let mut paths: Vec<&String> = files
+        .keys()
+        .filter(|path| path.ends_with(REQUEST_FILE_SUFFIX))
+        .collect();
+    paths.sort_by_key(|path| !base.contains_key(*path));
+    for path in paths {
+        let content = &files[path];
+        let (dir, name) = split_path(path);
+        let mut item: RequestFile = from_file_content(path, content)?;
+        if item.name.is_empty() {
+            item.name = name.trim_end_matches(REQUEST_FILE_SUFFIX).to_string();
+        }
+        item.id = new_id(&mut used, &item.id);
+        item.setting = sort_json_value(item.setting);
+        dir_children
+            .entry(dir.to_string())
+            .or_default()
+            .push((item.id.clone(), path.clone()));
+        data.settings.push(ParsedItem {
+            item,
+            parent: folder_ids.get(dir).cloned().unwrap_or_default(),
+        });
+    }
+
+    for (dir, mut folder) in folders {
+        // 按folder.json中的顺序，新增的文件按路径排在最后
+        let mut children = dir_children.remove(&dir).unwrap_or_default();
+        children.sort_by(|a, b| a.1.cmp(&b.1));
+        let mut ordered = vec![];
+        for id in folder.children.iter() {
+            if let Some(index) = children.iter().position(|item| &item.0 == id) {
+                ordered.push(children.remove(index).0);
+            }
+        }
+        ordered.extend(children.into_iter().map(|item| item.0));
+        folder.children = ordered;
+        data.folders.push(ParsedItem {
+            item: folder,
+            parent: folder_ids
+                .get(split_path(&dir).0)
+                .cloned()
+                .unwrap_or_default(),
+        });
+    }
+    Ok(data)
+}
+
+fn some_value(value: &str) -> Option<String> {
+    Some(value.to_string())
+}
+
+// 将相对于base有变化的文件写入数据库，仅base中存在而当前已删除的元素移至回收站
+async fn apply_parsed_data<C>(
+    db: &C,
+    collection: &str,
+    base: &ParsedData,
+    data: &ParsedData,
+) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let now = Utc::now().to_rfc3339();
+    if base.collection != data.collection {
+        if let Some(model) = ApiCollections::find_by_id(collection).one(db).await? {
+            let mut model: api_collections::ActiveModel = model.into();
+            model.name = Set(some_value(&data.collection.name));
+            model.description = Set(some_value(&data.collection.description));
+            model.setting = Set(Some(value_to_setting(&data.collection.setting)));
+            model.updated_at = Set(some_value(&data.collection.updated_at));
+            model.update(db).await?;
+        }
+    }
+
+    let base_folders: HashMap<&str, &ParsedItem<FolderFile>> = base
+        .folders
+        .iter()
+        .map(|item| (item.item.id.as_str(), item))
+        .collect();
+    let base_settings: HashMap<&str, &ParsedItem<RequestFile>> = base
+        .settings
+        .iter()
+        .map(|item| (item.item.id.as_str(), item))
+        .collect();
+    let current_ids: HashSet<&str> = data
+        .folders
+        .iter()
+        .map(|item| item.item.id.as_str())
+        .chain(data.settings.iter().map(|item| item.item.id.as_str()))
+        .collect();
+    // 文件已删除的移至回收站
+    let removed_ids: Vec<String> = base_folders
+        .keys()
+        .chain(base_settings.keys())
+        .filter(|id| !current_ids.contains(**id))
+        .map(|id| id.to_string())
+        .collect();
+    if !removed_ids.is_empty() {
+        ApiFolders::update_many()
+            .col_expr(api_folders::Column::DeletedAt, Expr::value(now.clone()))
+            .filter(api_folders::Column::Id.is_in(removed_ids.clone()))
+            .filter(api_folders::Column::DeletedAt.eq(""))
+            .exec(db)
+            .await?;
+        ApiSettings::update_many()
+            .col_expr(api_settings::Column::DeletedAt, Expr::value(now.clone()))
+            .filter(api_settings::Column::Id.is_in(removed_ids))
+            .filter(api_settings::Column::DeletedAt.eq(""))
+            .exec(db)
+            .await?;
+    }
+
+    // 回收站中的元素不在文件中，保留其在目录中的位置以便恢复
+    let deleted_ids: HashSet<String> = ApiFolders::find()
+        .filter(api_folders::Column::Collection.eq(collection))
+        .filter(api_folders::Column::DeletedAt.ne(""))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|item| item.id)
+        .chain(
+            ApiSettings::find()
+                .filter(api_settings::Column::Collection.eq(collection))
+                .filter(api_settings::Column::DeletedAt.ne(""))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|item| item.id),
+        )
+        .collect();
+
+    for folder in data.folders.iter() {
+        let item = &folder.item;
+        if base_folders.get(item.id.as_str()) == Some(&folder) {
+            continue;
+        }
+        let current = ApiFolders::find_by_id(item.id.clone()).one(db).await?;
+        let mut children = item.children.clone();
+        if let Some(current) = current.as_ref() {
+            for child in split_children(&current.children) {
+                if deleted_ids.contains(&child) && !children.contains(&child) {
+                    children.push(child);
+                }
+            }
+        }
+        let model = api_folders::ActiveModel {
+            id: Set(item.id.clone()),
+            collection: Set(collection.to_string()),
+            children: Set(Some(children.join(","))),
+            name: Set(some_value(&item.name)),
+            created_at: Set(some_value(&item.created_at)),
+            updated_at: Set(some_value(&item.updated_at)),
+            deleted_at: Set(Some("".to_string())),
+        };
+        if current.is_some() {
+            model.update(db).await?;
+        } else {
+            model.insert(db).await?;
+        }
+    }
+
+    for setting in data.settings.iter() {
+        let item = &setting.item;
+        if base_settings.get(item.id.as_str()) == Some(&setting) {
+            continue;
+        }
+        let model = api_settings::ActiveModel {
+            id: Set(item.id.clone()),
+            collection: Set(collection.to_string()),
+            name: Set(some_value(&item.name)),
+            category: Set(some_value(&item.category)),
+            setting: Set(some_value(&value_to_setting(&item.setting))),
+            created_at: Set(some_value(&item.created_at)),
+            updated_at: Set(some_value(&item.updated_at)),
+            deleted_at: Set(Some("".to_string())),
+        };
+        match ApiSettings::find_by_id(item.id.clone()).one(db).await? {
+            Some(current) => {
+                // 外部的修改也记录历史版本
+                save_api_setting_revision(db, &current, &model, false).await?;
+                model.update(db).await?;
+            }
+            None => {
+                model.insert(db).await?;
+            }
+        }
+    }
+
+    // 环境需在变量之前写入
+    let base_environments: HashMap<&str, &EnvironmentFile> = base
+        .environments
+        .iter()
+        .map(|item| (item.id.as_str(), item))
+        .collect();
+    let mut environment_ids = HashSet::new();
+    for item in data.environments.iter() {
+        environment_ids.insert(item.id.as_str());
+        if base_environments.get(item.id.as_str()) == Some(&item) {
+            continue;
+        }
+        let mut model = environments::ActiveModel {
+            id: Set(item.id.clone()),
+            collection: Set(collection.to_string()),
+            name: Set(some_value(&item.name)),
+            base: Set(some_value(&item.base)),
+            active: NotSet,
+            created_at: Set(some_value(&item.created_at)),
+            updated_at: Set(some_value(&item.updated_at)),
+        };
+        if Environments::find_by_id(item.id.clone())
+            .one(db)
+            .await?
+            .is_some()
+        {
+            model.update(db).await?;
+        } else {
+            model.active = Set(Some("".to_string()));
+            model.insert(db).await?;
+        }
+    }
+
+    let base_variables: HashMap<&str, &VariableFile> = base
+        .variables
+        .iter()
+        .map(|item| (item.id.as_str(), item))
+        .collect();
+    let mut variable_ids = HashSet::new();
+    for item in data.variables.iter() {
+        variable_ids.insert(item.id.as_str());
+        if base_variables.get(item.id.as_str()) == Some(&item) {
+            continue;
+        }
+        let secret = some_value(&item.secret);
+        // 文件中加密变量的值为空，保留数据库中的值；如填写了值则加密后保存
+        let value = if !is_secret(&secret) {
+            Set(some_value(&item.value))
+        } else if item.value.is_empty() {
+            NotSet
+        } else {
+            Set(Some(encrypt_secret(db, &item.value).await?))
+        };
+        let model = variables::ActiveModel {
+            id: Set(item.id.clone()),
+            category: Set(item.category.clone()),
+            collection: Set(collection.to_string()),
+            environment: Set(some_value(&item.environment)),
+            name: Set(some_value(&item.name)),
+            value,
+            enabled: Set(some_value(&item.enabled)),
+            secret: Set(secret),
+            created_at: Set(some_value(&item.created_at)),
+            updated_at: Set(some_value(&item.updated_at)),
+        };
+        if Variables::find_by_id(item.id.clone())
+            .one(db)
+            .await?
+            .is_some()
+        {
+            model.update(db).await?;
+        } else {
+            model.insert(db).await?;
+        }
+    }
+    let removed_variables: Vec<String> = base_variables
+        .keys()
+        .filter(|id| !variable_ids.contains(**id))
+        .map(|id| id.to_string())
+        .collect();
+    if !removed_variables.is_empty() {
+        Variables::delete_many()
+            .filter(variables::Column::Id.is_in(removed_variables))
+            .exec(db)
+            .await?;
+    }
+    // 删除环境时其变量一并删除，继承该环境的改为仅继承基础环境
+    let removed_environments: Vec<String> = base_environments
+        .keys()
+        .filter(|id| !environment_ids.contains(**id))
+        .map(|id| id.to_string())
+        .collect();
+    if !removed_environments.is_empty() {
+        Variables::delete_many()
+            .filter(variables::Column::Collection.eq(collection))
+            .filter(variables::Column::Environment.is_in(removed_environments.clone()))
+            .exec(db)
+            .await?;
+        Environments::update_many()
+            .col_expr(environments::Column::Base, Expr::value(""))
+            .filter(environments::Column::Collection.eq(collection))
+            .filter(environments::Column::Base.is_in(removed_environments.clone()))
+            .exec(db)
+            .await?;
+        Environments::delete_many()
+            .filter(environments::Column::Id.is_in(removed_environments))
+            .exec(db)
+            .await?;
+    }
+
+    index_api_settings(db, collection, None).await?;
+    Ok(())
+}
+
+async fn find_file_store<C>(db: &C, collection: &str) -> Result<Option<file_stores::Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    FileStores::find_by_id(collection).one(db).await
+}
+
+// 目录的读写为阻塞操作，在blocking线程中执行，避免阻塞异步任务
+async fn read_file_map_blocking(dir: &str) -> Result<FileMap, DbErr> {
+    let dir = dir.to_string();
+    tokio::task::spawn_blocking(move || read_file_map(&dir))
+        .await
+        .map_err(|err| file_store_error(err.to_string()))?
+}
+
+// 写入完成后返回写入的文件，用于记录同步状态
+async fn write_file_map_blocking(dir: &str, files: FileMap) -> Result<FileMap, DbErr> {
+    let dir = dir.to_string();
+    tokio::task::spawn_blocking(move || write_file_map(&dir, &files).map(|_| files))
+        .await
+        .map_err(|err| file_store_error(err.to_string()))?
+}
+
+// 同步collection与其目录：先将目录中被外部修改的文件写入数据库，再将数据库的数据写入目录
+// 返回是否有外部修改
+async fn sync_dir(collection: &str, dir: &str) -> Result<bool, DbErr> {
+    let mut snapshots = FILE_SNAPSHOTS.lock().await;
+    let db = get_database().await?;
+    let files = read_file_map_blocking(dir).await?;
+    // 未有同步记录时以数据库的数据为基准
+    let base = match snapshots.get(collection) {
+        Some(snapshot) => snapshot.clone(),
+        None => build_file_map(&db, collection).await?,
+    };
+    let changed = files != base;
+    if changed {
+        let base_data = parse_file_map(&base, &base)?;
+        let data = parse_file_map(&files, &base)?;
+        let txn = db.begin().await?;
+        apply_parsed_data(&txn, collection, &base_data, &data).await?;
+        txn.commit().await?;
+    }
+    let files = build_file_map(&db, collection).await?;
+    let files = write_file_map_blocking(dir, files).await?;
+    snapshots.insert(collection.to_string(), files);
+    Ok(changed)
+}
+
+// 数据更新后同步至collection对应的目录（如果有）
+pub async fn sync_file_store(collection: &str) -> Result<bool, DbErr> {
+    let db = get_database().await?;
+    match find_file_store(&db, collection).await? {
+        Some(store) => sync_dir(collection, &store.dir).await,
+        None => Ok(false),
+    }
+}
+
+// 数据已提交后同步目录，同步失败不影响已保存的数据（否则前端重试时会重复新增），
+// 仅记录日志并通知前端，目录在下次修改或启动时再次同步
+pub async fn sync_file_store_after_commit(collection: &str) {
+    if let Err(err) = sync_file_store(collection).await {
+        tracing::warn!(collection, error = %err, "sync file store fail");
+        let _ = SYNC_FAILURES.send(FileStoreSyncFailure {
+            collection: collection.to_string(),
+            message: err.to_string(),
+        });
+    }
+}
+
+// 同步多个collection对应的目录，如批量删除涉及的collection
+pub async fn sync_file_stores(collections: Vec<String>) {
+    let collections: BTreeSet<String> = collections.into_iter().collect();
+    for collection in collections {
+        sync_file_store_after_commit(&collection).await;
+    }
+}
+
+// 订阅数据保存后同步目录失败的通知
+pub fn subscribe_file_store_failures() -> broadcast::Receiver<FileStoreSyncFailure> {
+    SYNC_FAILURES.subscribe()
+}
+
+// 切换数据库后，原有的同步记录已无意义
+pub async fn clear_file_store_snapshots() {
+    FILE_SNAPSHOTS.lock().await.clear();
+}
+
+pub async fn list_file_store() -> Result<Vec<FileStore>, DbErr> {
+    let db = get_database().await?;
+    let result = FileStores::find().all(&db).await?;
+    Ok(result.into_iter().map(FileStore::from).collect())
+}
+
+// 将collection保存至目录，若目录中已有该collection的文件，则以文件为准
+pub async fn link_file_store(collection: String, dir: String) -> Result<FileStore, DbErr> {
+    let db = get_database().await?;
+    if ApiCollections::find_by_id(collection.clone())
+        .one(&db)
+        .await?
+        .is_none()
+    {
+        return Err(DbErr::RecordNotFound(format!(
+            "api collection: {}",
+            collection
+        )));
+    }
+    for store in FileStores::find().all(&db).await? {
+        if store.dir == dir && store.collection != collection {
+            return Err(file_store_error(format!(
+                "{} is used by other collection",
+                dir
+            )));
+        }
+    }
+    fs::create_dir_all(&dir).map_err(|err| file_store_error(err.to_string()))?;
+    let files = read_file_map_blocking(&dir).await?;
+    if let Some(content) = files.get(COLLECTION_FILE) {
+        let file: CollectionFile = from_file_content(COLLECTION_FILE, content)?;
+        if !file.id.is_empty() && file.id != collection {
+            return Err(file_store_error(format!(
+                "{} is used by other collection",
+                dir
+            )));
+        }
+    }
+
+    let now = Utc::now().to_rfc3339();
+    let model = file_stores::ActiveModel {
+        collection: Set(collection.clone()),
+        dir: Set(dir.clone()),
+        created_at: NotSet,
+        updated_at: Set(Some(now.clone())),
+    };
+    let result = match find_file_store(&db, &collection).await? {
+        Some(_) => model.update(&db).await?,
+        None => {
+            let mut model = model;
+            model.created_at = Set(Some(now));
+            model.insert(&db).await?
+        }
+    };
+    FILE_SNAPSHOTS.lock().await.remove(&collection);
+    // 目录中无文件时，所有文件均为新增，以数据库为准
+    if files.is_empty() {
+        let files = build_file_map(&db, &collection).await?;
+        let files = write_file_map_blocking(&dir, files).await?;
+        FILE_SNAPSHOTS.lock().await.insert(collection, files);
+    } else {
+        sync_dir(&collection, &dir).await?;
+    }
+    Ok(result.into())
+}
+
+// 打开collection目录，数据库中无该collection时新增
+pub async fn open_file_store(dir: String) -> Result<APICollection, DbErr> {
+    let files = read_file_map_blocking(&dir).await?;
+    let data = parse_file_map(&files, &FileMap::new())?;
+    let id = if data.collection.id.is_empty() {
+        ulid::Ulid::new().to_string()
+    } else {
+        data.collection.id.clone()
+    };
+    let db = get_database().await?;
+    match ApiCollections::find_by_id(id.clone()).one(&db).await? {
+        Some(model) => {
+            // 已在回收站的则恢复
+            if !model.deleted_at.clone().unwrap_or_default().is_empty() {
+                let mut model: api_collections::ActiveModel = model.into();
+                model.deleted_at = Set(Some("".to_string()));
+                model.update(&db).await?;
+            }
+        }
+        None => {
+            let now = Utc::now().to_rfc3339();
+            let collection = &data.collection;
+            api_collections::ActiveModel {
+                id: Set(id.clone()),
+                name: Set(some_value(&collection.name)),
+                description: Set(some_value(&collection.description)),
+                setting: Set(Some(value_to_setting(&collection.setting))),
+                created_at: Set(Some(if collection.created_at.is_empty() {
+                    now.clone()
+                } else {
+                    collection.created_at.clone()
+                })),
+                updated_at: Set(Some(if collection.updated_at.is_empty() {
+                    now
+                } else {
+                    collection.updated_at.clone()
+                })),
+                deleted_at: Set(Some("".to_string())),
+            }
+            .insert(&db)
+            .await?;
+        }
+    }
+    // 关联时目录中的文件均写入数据库
+    link_file_store(id.clone(), dir).await?;
+    let model = ApiCollections::find_by_id(id.clone())
+        .one(&db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound(format!("api collection: {}", id)))?;
+    Ok(model.into())
+}
+
+// 取消collection与目录的关联，目录中的文件保留
+pub async fn unlink_file_store(collection: String) -> Result<(), DbErr> {
+    let db = get_database().await?;
+    FileStores::delete_by_id(collection.clone())
+        .exec(&db)
+        .await?;
+    FILE_SNAPSHOTS.lock().await.remove(&collection);
+    Ok(())
+}
+
+// 取消所有collection与目录的关联，用于数据库的副本（如命令行使用的临时数据库），
+// 避免修改同步至原数据库关联的目录
+pub async fn unlink_all_file_store() -> Result<(), DbErr> {
+    let db = get_database().await?;
+    FileStores::delete_many().exec(&db).await?;
+    clear_file_store_snapshots().await;
+    Ok(())
+}
@@ -0,0 +1,229 @@
+use crate::{
+    entities::{global_variables, prelude::*},
+    error::CyberAPIError,
+};
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, Set,
+};
+use serde::{Deserialize, Serialize};
+
+use super::database::{get_database, ExportData};
+use super::secret::{decrypt_secret, prepare_secret_value, SECRET_MASK};
+use super::variable::{is_secret, VARIABLE_ENABLED, VARIABLE_SECRET};
+
+// 全局变量，不属于任何collection，所有请求均可使用
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GlobalVariable {
+    // id
+    pub id: String,
+    // 变量名称
+    pub name: Option<String>,
+    // 变量值
+    pub value: Option<String>,
+    // 是否启用(0:禁用 1:启用)
+    pub enabled: Option<String>,
+    // 是否加密保存(0:否 1:是)
+    pub secret: Option<String>,
+    // 创建时间
+    pub created_at: Option<String>,
+    // 更新时间
+    pub updated_at: Option<String>,
+}
+
+// 加密变量的值以掩码展示
+impl From<global_variables::Model> for GlobalVariable {
+    fn from(model: global_variables::Model) -> Self {
+        let value =
+            if is_secret(&model.secret) && !model.value.clone().unwrap_or_default().is_empty() {
+                Some(SECRET_MASK.to_string())
+            } else {
+                model.value
+            };
+        GlobalVariable {
+            id: model.id,
+            name: model.name,
+            value,
+            enabled: model.enabled,
+            secret: model.secret,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+        }
+    }
+}
+
+impl GlobalVariable {
+    fn into_active_model(self) -> global_variables::ActiveModel {
+        let created_at = self.created_at.or_else(|| Some(Utc::now().to_rfc3339()));
+        let updated_at = self.updated_at.or_else(|| Some(Utc::now().to_rfc3339()));
+        global_variables::ActiveModel {
+            id: Set(self.id),
+            name: Set(self.name),
+            value: Set(self.value),
+            enabled: Set(self.enabled),
+            secret: Set(self.secret),
+            created_at: Set(created_at),
+            updated_at: Set(updated_at),
+        }
+    }
+}
+
+pub fn get_global_variables_create_sql() -> String {
+    "CREATE TABLE IF NOT EXISTS global_variables (
+        id TEXT PRIMARY KEY NOT NULL check (id != ''),
+        name TEXT DEFAULT '',
+        value TEXT DEFAULT '',
+        enabled TEXT DEFAULT '',
+        secret TEXT DEFAULT '',
+        created_at TEXT DEFAULT '',
+        updated_at TEXT DEFAULT ''
+    )"
+    .to_string()
+}
+
+async fn prepare_value<C>(db: &C, value: &mut GlobalVariable) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let current = value.value.clone().unwrap_or_default();
+    let stored = if current == SECRET_MASK {
+        GlobalVariables::find_by_id(value.id.clone())
+            .one(db)
+            .await?
+            .and_then(|item| item.value)
+    } else {
+        None
+    };
+    value.value = Some(prepare_secret_value(db, &current, is_secret(&value.secret), stored).await?);
+    Ok(())
+}
+
+pub async fn add_global_variable(mut value: GlobalVariable) -> Result<GlobalVariable, DbErr> {
+    let db = get_database().await?;
+    prepare_value(&db, &mut value).await?;
+    let result = value.into_active_model().insert(&db).await?;
+    Ok(result.into())
+}
+
+pub async fn update_global_variable(mut value: GlobalVariable) -> Result<GlobalVariable, DbErr> {
+    let db = get_database().await?;
+    prepare_value(&db, &mut value).await?;
+    let result = value.into_active_model().update(&db).await?;
+    Ok(result.into())
+}
+
+// 加密变量的值默认以掩码展示，reveal为true时解密
+pub async fn list_global_variable(reveal: bool) -> Result<Vec<GlobalVariable>, DbErr> {
+    let db = get_database().await?;
+    let result = GlobalVariables::find().all(&db).await?;
+    let mut variables = vec![];
+    for item in result {
+        let secret_value = if reveal && is_secret(&item.secret) {
+            Some(decrypt_secret(&db, &item.value.clone().unwrap_or_default()).await?)
+        } else {
+            None
+        };
+        let mut variable = GlobalVariable::from(item);
+        if secret_value.is_some() {
+            variable.value = secret_value;
+        }
+        variables.push(variable);
+    }
+    Ok(variables)
+}
+
+// 获取所有启用的全局变量（包括加密变量的原始值）
+pub async fn list_enabled_global_variable<C>(db: &C) -> Result<Vec<global_variables::Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    GlobalVariables::find()
+        .filter(global_variables::Column::Enabled.eq(VARIABLE_ENABLED))
+        .all(db)
+        .await
+}
+
+// 按名称更新全局变量的值，不存在则新增，返回是否为加密变量
+pub async fn set_global_variable_value<C>(db: &C, name: &str, value: &str) -> Result<bool, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let now = Utc::now().to_rfc3339();
+    let current = GlobalVariables::find()
+        .filter(global_variables::Column::Name.eq(name))
+        .one(db)
+        .await?;
+    let model = match current {
+        Some(current) => current,
+        None => {
+            global_variables::ActiveModel {
+                id: Set(ulid::Ulid::new().to_string()),
+                name: Set(Some(name.to_string())),
+                value: Set(Some(value.to_string())),
+                enabled: Set(Some(VARIABLE_ENABLED.to_string())),
+                secret: Set(Some("".to_string())),
+                created_at: Set(Some(now.clone())),
+                updated_at: Set(Some(now)),
+            }
+            .insert(db)
+            .await?;
+            return Ok(false);
+        }
+    };
+    let secret = is_secret(&model.secret);
+    let mut model: global_variables::ActiveModel = model.into();
+    model.value = Set(Some(prepare_secret_value(db, value, secret, None).await?));
+    model.updated_at = Set(Some(now));
+    model.update(db).await?;
+    Ok(secret)
+}
+
+pub async fn delete_global_variable(ids: Vec<String>) -> Result<u64, DbErr> {
+    let db = get_database().await?;
+    let result = GlobalVariables::delete_many()
+        .filter(global_variables::Column::Id.is_in(ids))
+        .exec(&db)
+        .await?;
+    Ok(result.rows_affected)
+}
+
+pub fn get_table_name_global_variable() -> String {
+    "global_variables".to_string()
+}
+
+pub async fn delete_all_global_variable() -> Result<(), CyberAPIError> {
+    let db = get_database().await?;
+    GlobalVariables::delete_many().exec(&db).await?;
+    Ok(())
+}
+
+pub async fn export_global_variable() -> Result<ExportData, DbErr> {
+    let db = get_database().await?;
+    let mut data = GlobalVariables::find().into_json().all(&db).await?;
+    // 加密变量的值不导出
+    for item in data.iter_mut() {
+        if item.get("secret").and_then(|value| value.as_str()) == Some(VARIABLE_SECRET) {
+            item["value"] = serde_json::Value::String("".to_string());
+        }
+    }
+    Ok(ExportData {
+        name: get_table_name_global_variable(),
+        data,
+    })
+}
+
+pub async fn import_global_variable(data: Vec<serde_json::Value>) -> Result<(), CyberAPIError> {
+    let db = get_database().await?;
+
+    let mut records = Vec::new();
+    for ele in data {
+        let model = global_variables::ActiveModel::from_json(ele)?;
+        records.push(model);
+    }
+    if records.is_empty() {
+        return Ok(());
+    }
+    GlobalVariables::insert_many(records).exec(&db).await?;
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use crate::entities::{api_folders, api_settings, prelude::*, variables};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, Set,
+    TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use super::database::get_database;
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseRepairResult {
+    // 所属collection已不存在的目录
+    pub folders: Vec<String>,
+    // 所属collection已不存在的API配置
+    pub settings: Vec<String>,
+    // 所属collection已不存在的变量
+    pub variables: Vec<String>,
+    // 子元素中包含已不存在目录或API的目录
+    pub children: Vec<String>,
+}
+
+// 查找孤立数据，非dry run时则删除孤立数据并修正目录的子元素
+pub async fn find_orphans<C>(db: &C, dry_run: bool) -> Result<DatabaseRepairResult, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let collections: HashSet<String> = ApiCollections::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|item| item.id)
+        .collect();
+    let mut result = DatabaseRepairResult::default();
+
+    let folders = ApiFolders::find().all(db).await?;
+    let settings = ApiSettings::find().all(db).await?;
+    for item in Variables::find().all(db).await? {
+        if !collections.contains(&item.collection) {
+            result.variables.push(item.id);
+        }
+    }
+
+    // 仍然有效的目录与API，用于校验目录的子元素
+    let mut valid_ids = HashSet::new();
+    for item in folders.iter() {
+        if collections.contains(&item.collection) {
+            valid_ids.insert(item.id.clone());
+        } else {
+            result.folders.push(item.id.clone());
+        }
+    }
+    for item in settings.iter() {
+        if collections.contains(&item.collection) {
+            valid_ids.insert(item.id.clone());
+        } else {
+            result.settings.push(item.id.clone());
+        }
+    }
+
+    let mut updated_children = Vec::new();
+    for item in folders {
+        if !valid_ids.contains(&item.id) {
+            continue;
+        }
+        let children = item.children.clone().unwrap_or_default();
+        let arr: Vec<&str> = children
+            .split(',')
+            .map(|id| id.trim())
+            .filter(|id| !id.is_empty())
+            .collect();
+        let filtered: Vec<&str> = arr
+            .iter()
+            .filter(|id| valid_ids.contains(**id))
+            .copied()
+            .collect();
+        if filtered.len() != arr.len() {
+            result.children.push(item.id.clone());
+            updated_children.push((item, filtered.join(",")));
+        }
+    }
+
+    if dry_run {
+        return Ok(result);
+    }
+
+    if !result.folders.is_empty() {
+        ApiFolders::delete_many()
+            .filter(api_folders::Column::Id.is_in(result.folders.clone()))
+            .exec(db)
+            .await?;
+    }
+    if !result.settings.is_empty() {
+        ApiSettings::delete_many()
+            .filter(api_settings::Column::Id.is_in(result.settings.clone()))
+            .exec(db)
+            .await?;
+    }
+    if !result.variables.is_empty() {
+        Variables::delete_many()
+            .filter(variables::Column::Id.is_in(result.variables.clone()))
+            .exec(db)
+            .await?;
+    }
+    for (folder, children) in updated_children {
+        let mut model: api_folders::ActiveModel = folder.into();
+        model.children = Set(Some(children));
+        model.update(db).await?;
+    }
+
+    Ok(result)
+}
+
+pub async fn repair_database(dry_run: bool) -> Result<DatabaseRepairResult, DbErr> {
+    let db = get_database().await?;
+    let txn = db.begin().await?;
+    let result = find_orphans(&txn, dry_run).await?;
+    txn.commit().await?;
+    Ok(result)
+}
@@ -1,25 +1,70 @@
 mod api_collection;
+mod api_example;
 mod api_folder;
 mod api_setting;
+mod api_setting_revision;
 mod database;
+mod environment;
+mod file_store;
+mod global_variable;
+mod maintenance;
+mod oauth_token;
+mod search;
+mod secret;
+mod trash;
 mod variable;
 mod version;
 
-pub use database::{export_tables, import_tables, init_tables};
+pub use database::{export_tables, import_tables, init_tables, switch_database};
+#[cfg(test)]
+pub use database::{init_test_database, with_secret_locked};
 
 pub use api_collection::{
-    add_api_collection, delete_api_collection, list_api_collection, update_api_collection,
-    APICollection,
+    add_api_collection, delete_api_collection, get_collection_retry_policy, list_api_collection,
+    update_api_collection, APICollection,
+};
+pub use api_example::{
+    add_api_example, delete_api_example, find_api_example, list_api_example,
+    list_collection_api_example, rename_api_example, APIExample,
 };
 pub use api_folder::{
-    add_api_folder, delete_api_folder_by_collection, delete_api_folders, list_api_folder,
-    list_api_folder_all_children, update_api_folder, APIFolder, APIFolderChildren,
+    add_api_folder, delete_api_folder, list_api_folder, update_api_folder, APIFolder,
+    APIFolderChildren,
 };
 pub use api_setting::{
-    add_api_setting, delete_api_setting_by_collection, delete_api_settings, list_api_setting,
-    update_api_setting, APISetting,
+    add_api_setting, delete_api_settings, find_api_setting, list_api_setting,
+    restore_api_setting_revision, update_api_setting, APISetting,
+};
+pub use api_setting_revision::{
+    clean_api_setting_revision, diff_api_setting_revision, list_api_setting_revision,
+    APISettingChange, APISettingRevision,
+};
+pub use environment::{
+    activate_environment, add_environment, clone_environment, compare_environment,
+    delete_environment, get_active_environment, list_environment, update_environment, Environment,
+    EnvironmentChange,
+};
+pub use file_store::{
+    clear_file_store_snapshots, link_file_store, list_file_store, open_file_store,
+    subscribe_file_store_failures, sync_file_store, unlink_all_file_store, unlink_file_store,
+    FileStore, FileStoreSyncFailure,
 };
+pub use global_variable::{
+    add_global_variable, delete_global_variable, list_global_variable, update_global_variable,
+    GlobalVariable,
+};
+pub use maintenance::{repair_database, DatabaseRepairResult};
+pub use oauth_token::{delete_oauth_token, find_oauth_token, save_oauth_token, OAuthToken};
+pub use search::{search_api_settings, SearchResult};
+pub use secret::{
+    get_secret_status, import_key_file, init_secret_key, lock_secret, unlock_secret, SecretStatus,
+    SECRET_CATEGORY_KEY_FILE, SECRET_MASK,
+};
+pub use trash::{clean_trash, list_trash, purge_trash, restore_trash, TrashItem};
 
-pub use variable::{add_variable, delete_variable, list_variable, update_variable, Variable};
+pub use variable::{
+    add_variable, delete_variable, get_template_context, list_global_req_headers, list_variable,
+    reveal_variable, save_extracted_variable, update_variable, Variable,
+};
 
 pub use version::{add_version, get_latest_version, Version};
@@ -0,0 +1,258 @@
+use crate::entities::{oauth_tokens, prelude::*};
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{ActiveModelTrait, ColumnTrait, DbErr, EntityTrait, QueryFilter, Set};
+use serde::{Deserialize, Serialize};
+
+use super::database::get_database;
+use super::secret::{decrypt_secret, encrypt_secret, is_encrypted};
+
+// 距过期时间不足此时长(秒)则视为已过期，避免请求时刚好过期
+const EXPIRY_SKEW: i64 = 30;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthToken {
+    // id
+    pub id: String,
+    // 所属collection
+    pub collection: String,
+    // 所属环境，无激活的环境时为空
+    pub environment: String,
+    // 由OAuth配置生成，配置变化时使用新的token
+    pub key: String,
+    pub access_token: String,
+    pub token_type: String,
+    pub refresh_token: String,
+    pub scope: String,
+    // 过期时间，为空表示未返回有效期
+    pub expires_at: String,
+    // 创建时间
+    pub created_at: Option<String>,
+    // 更新时间
+    pub updated_at: Option<String>,
+}
+
+impl From<oauth_tokens::Model> for OAuthToken {
+    fn from(model: oauth_tokens::Model) -> Self {
+        OAuthToken {
+            id: model.id,
+            collection: model.collection,
+            environment: model.environment,
+            key: model.key,
+            access_token: model.access_token,
+            token_type: model.token_type.unwrap_or_default(),
+            refresh_token: model.refresh_token.unwrap_or_default(),
+            scope: model.scope.unwrap_or_default(),
+            expires_at: model.expires_at.unwrap_or_default(),
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+        }
+    }
+}
+
+impl OAuthToken {
+    fn into_active_model(self) -> oauth_tokens::ActiveModel {
+        let created_at = self.created_at.or_else(|| Some(Utc::now().to_rfc3339()));
+        let updated_at = self.updated_at.or_else(|| Some(Utc::now().to_rfc3339()));
+        oauth_tokens::ActiveModel {
+            id: Set(self.id),
+            collection: Set(self.collection),
+            environment: Set(self.environment),
+            key: Set(self.key),
+            access_token: Set(self.access_token),
+            token_type: Set(Some(self.token_type)),
+            refresh_token: Set(Some(self.refresh_token)),
+            scope: Set(Some(self.scope)),
+            expires_at: Set(Some(self.expires_at)),
+            created_at: Set(created_at),
+            updated_at: Set(updated_at),
+        }
+    }
+    pub fn is_expired(&self) -> bool {
+        if self.expires_at.is_empty() {
+            return false;
+        }
+        match DateTime::parse_from_rfc3339(&self.expires_at) {
+            Ok(expires_at) => {
+                expires_at.with_timezone(&Utc) <= Utc::now() + Duration::seconds(EXPIRY_SKEW)
+            }
+            Err(_) => true,
+        }
+    }
+}
+
+pub fn get_oauth_tokens_create_sql() -> String {
+    "CREATE TABLE IF NOT EXISTS oauth_tokens (
+        id TEXT PRIMARY KEY NOT NULL check (id != ''),
+        collection TEXT NOT NULL check (collection != '')
+            REFERENCES api_collections(id) ON DELETE CASCADE,
+        environment TEXT NOT NULL DEFAULT '',
+        key TEXT NOT NULL check (key != ''),
+        access_token TEXT NOT NULL DEFAULT '',
+        token_type TEXT DEFAULT '',
+        refresh_token TEXT DEFAULT '',
+        scope TEXT DEFAULT '',
+        expires_at TEXT DEFAULT '',
+        created_at TEXT DEFAULT '',
+        updated_at TEXT DEFAULT '',
+        UNIQUE(collection, environment, key)
+    )"
+    .to_string()
+}
+
+// 获取缓存的token，token加密保存，无法解密（密钥未解锁）或未加密时视为无缓存
+pub async fn find_oauth_token(
+    collection: &str,
+    environment: &str,
+    key: &str,
+) -> Result<Option<OAuthToken>, DbErr> {
+    let db = get_database().await?;
+    let result = OauthTokens::find()
+        .filter(oauth_tokens::Column::Collection.eq(collection))
+        .filter(oauth_tokens::Column::Environment.eq(environment))
+        .filter(oauth_tokens::Column::Key.eq(key))
+        .one(&db)
+        .await?;
+    let mut token = match result {
+        Some(model) => OAuthToken::from(model),
+        None => return Ok(None),
+    };
+    if !is_encrypted(&token.access_token) {
+        return Ok(None);
+    }
+    match (
+        decrypt_secret(&db, &token.access_token).await,
+        decrypt_secret(&db, &token.refresh_token).await,
+    ) {
+        (Ok(access_token), Ok(refresh_token)) => {
+            token.access_token = access_token;
+            token.refresh_token = refresh_token;
+            Ok(Some(token))
+        }
+        _ => Ok(None),
+    }
+}
+
+// 保存token，已存在则更新（保留创建时间）
+// access token与refresh token使用变量的密钥加密保存，密钥不存在或未解锁时不保存（并删除原有的token），
+// 返回的token id为空
+pub async fn save_oauth_token(token: OAuthToken) -> Result<OAuthToken, DbErr> {
+    let db = get_database().await?;
+    let current = OauthTokens::find()
+        .filter(oauth_tokens::Column::Collection.eq(token.collection.clone()))
+        .filter(oauth_tokens::Column::Environment.eq(token.environment.clone()))
+        .filter(oauth_tokens::Column::Key.eq(token.key.clone()))
+        .one(&db)
+        .await?;
+    let mut stored = token.clone();
+    stored.access_token = match encrypt_secret(&db, &token.access_token).await {
+        Ok(value) => value,
+        Err(_) => {
+            if let Some(current) = current {
+                OauthTokens::delete_by_id(current.id).exec(&db).await?;
+            }
+            return Ok(token);
+        }
+    };
+    if !token.refresh_token.is_empty() {
+        stored.refresh_token = encrypt_secret(&db, &token.refresh_token).await?;
+    }
+    stored.updated_at = Some(Utc::now().to_rfc3339());
+    let result = match current {
+        Some(current) => {
+            stored.id = current.id;
+            stored.created_at = current.created_at;
+            stored.into_active_model().update(&db).await?
+        }
+        None => {
+            if stored.id.is_empty() {
+                stored.id = ulid::Ulid::new().to_string();
+            }
+            stored.into_active_model().insert(&db).await?
+        }
+    };
+    let mut result = OAuthToken::from(result);
+    result.access_token = token.access_token;
+    result.refresh_token = token.refresh_token;
+    Ok(result)
+}
+
+// 删除collection的所有token
+pub async fn delete_oauth_token(collection: String) -> Result<u64, DbErr> {
+    let db = get_database().await?;
+    let result = OauthTokens::delete_many()
+        .filter(oauth_tokens::Column::Collection.eq(collection))
+        .exec(&db)
+        .await?;
+    Ok(result.rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schemas::{add_api_collection, init_test_database, APICollection};
+
+    fn expires_in(seconds: i64) -> OAuthToken {
+        OAuthToken {
+            expires_at: (Utc::now() + Duration::seconds(seconds)).to_rfc3339(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn token_expiry() {
+        assert!(!OAuthToken::default().is_expired());
+        assert!(!expires_in(3600).is_expired());
+        assert!(expires_in(-10).is_expired());
+        // 即将过期的视为已过期
+        assert!(expires_in(EXPIRY_SKEW - 5).is_expired());
+        assert!(OAuthToken {
+            expires_at: "invalid".to_string(),
+            ..Default::default()
+        }
+        .is_expired());
+    }
+
+    #[test]
+    fn token_is_encrypted_at_rest() {
+        tauri::async_runtime::block_on(async {
+            let _secret = init_test_database().await;
+            add_api_collection(APICollection {
+                id: "oauth-token-encrypted".to_string(),
+                name: None,
+                description: None,
+                setting: None,
+                created_at: None,
+                updated_at: None,
+            })
+            .await
+            .unwrap();
+            let token = OAuthToken {
+                collection: "oauth-token-encrypted".to_string(),
+                key: "key".to_string(),
+                access_token: "access".to_string(),
+                refresh_token: "refresh".to_string(),
+                ..Default::default()
+            };
+            let saved = save_oauth_token(token).await.unwrap();
+            assert_eq!(saved.access_token, "access");
+            assert_eq!(saved.refresh_token, "refresh");
+
+            let db = get_database().await.unwrap();
+            let model = OauthTokens::find_by_id(saved.id)
+                .one(&db)
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(is_encrypted(&model.access_token));
+            assert!(is_encrypted(&model.refresh_token.unwrap_or_default()));
+
+            let found = find_oauth_token("oauth-token-encrypted", "", "key")
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(found.access_token, "access");
+            assert_eq!(found.refresh_token, "refresh");
+        });
+    }
+}
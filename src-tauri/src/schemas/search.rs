@@ -0,0 +1,301 @@
+use crate::entities::{api_folders, api_settings, prelude::*};
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, Statement, TransactionTrait,
+    Value,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::database::get_database;
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    // API配置ID
+    pub id: String,
+    // collection ID
+    pub collection: String,
+    // collection名称
+    pub collection_name: Option<String>,
+    // API名称
+    pub name: Option<String>,
+    // 所在目录路径
+    pub path: String,
+    // 匹配内容的片段，匹配字符以<mark></mark>标记
+    pub snippet: String,
+    // 匹配度，越小越匹配
+    pub rank: f64,
+}
+
+// trigram分词支持任意位置的子串匹配（包括中文）
+pub fn get_api_setting_search_create_sql() -> String {
+    "CREATE VIRTUAL TABLE IF NOT EXISTS api_setting_search USING fts5(
+        id UNINDEXED,
+        collection UNINDEXED,
+        name,
+        uri,
+        headers,
+        body,
+        folders,
+        tokenize = 'trigram'
+    )"
+    .to_string()
+}
+
+// trigram分词无法匹配少于3个字符的关键字
+const MIN_MATCH_LENGTH: usize = 3;
+
+const SEARCH_FOLDER_SEPARATOR: &str = " / ";
+
+fn get_kv_text(value: &serde_json::Value, key: &str, separator: &str) -> String {
+    let mut arr = vec![];
+    if let Some(items) = value.get(key).and_then(|items| items.as_array()) {
+        for item in items {
+            let key = item.get("key").and_then(|v| v.as_str()).unwrap_or_default();
+            let value = item
+                .get("value")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+            if key.is_empty() && value.is_empty() {
+                continue;
+            }
+            arr.push(format!("{}{}{}", key, separator, value));
+        }
+    }
+    arr.join("\n")
+}
+
+// 获取目录路径
+fn get_folder_path(folders: &[api_folders::Model], id: &str) -> String {
+    let mut parents: HashMap<String, &api_folders::Model> = HashMap::new();
+    for folder in folders {
+        for child in folder.children.as_deref().unwrap_or_default().split(',') {
+            parents.insert(child.trim().to_string(), folder);
+        }
+    }
+    let mut names = vec![];
+    let mut current = id.to_string();
+    // 避免目录关系异常时的死循环
+    while let Some(parent) = parents.get(&current) {
+        if names.len() > folders.len() {
+            break;
+        }
+        names.push(parent.name.clone().unwrap_or_default());
+        current = parent.id.clone();
+    }
+    names.reverse();
+    names.join(SEARCH_FOLDER_SEPARATOR)
+}
+
+// 更新collection下API配置的索引，ids为空则更新所有API配置
+pub async fn index_api_settings<C>(
+    db: &C,
+    collection: &str,
+    ids: Option<Vec<String>>,
+) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let backend = db.get_database_backend();
+    let folders = ApiFolders::find()
+        .filter(api_folders::Column::Collection.eq(collection))
+        .all(db)
+        .await?;
+    let mut query = ApiSettings::find().filter(api_settings::Column::Collection.eq(collection));
+    match ids {
+        Some(ids) => {
+            query = query.filter(api_settings::Column::Id.is_in(ids.clone()));
+            for id in ids {
+                db.execute(Statement::from_sql_and_values(
+                    backend,
+                    "DELETE FROM api_setting_search WHERE id = ?",
+                    [id.into()],
+                ))
+                .await?;
+            }
+        }
+        None => {
+            db.execute(Statement::from_sql_and_values(
+                backend,
+                "DELETE FROM api_setting_search WHERE collection = ?",
+                [collection.into()],
+            ))
+            .await?;
+        }
+    }
+
+    for setting in query.all(db).await? {
+        let value: serde_json::Value =
+            serde_json::from_str(setting.setting.as_deref().unwrap_or_default())
+                .unwrap_or_default();
+        let mut uri = value
+            .get("uri")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let query = get_kv_text(&value, "query", "=");
+        if !query.is_empty() {
+            uri = format!("{}\n{}", uri, query);
+        }
+        let values: Vec<Value> = vec![
+            setting.id.clone().into(),
+            setting.collection.clone().into(),
+            setting.name.clone().unwrap_or_default().into(),
+            uri.into(),
+            get_kv_text(&value, "headers", ": ").into(),
+            value
+                .get("body")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .into(),
+            get_folder_path(&folders, &setting.id).into(),
+        ];
+        db.execute(Statement::from_sql_and_values(
+            backend,
+            "INSERT INTO api_setting_search (id, collection, name, uri, headers, body, folders) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            values,
+        ))
+        .await?;
+    }
+    Ok(())
+}
+
+// 删除collection或者API配置的索引
+pub async fn delete_api_setting_index<C>(db: &C, id: &str) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    db.execute(Statement::from_sql_and_values(
+        db.get_database_backend(),
+        "DELETE FROM api_setting_search WHERE id = ? OR collection = ?",
+        [id.into(), id.into()],
+    ))
+    .await?;
+    Ok(())
+}
+
+// 重建所有索引
+pub async fn rebuild_search_index() -> Result<(), DbErr> {
+    let db = get_database().await?;
+    let txn = db.begin().await?;
+    txn.execute(Statement::from_string(
+        txn.get_database_backend(),
+        "DELETE FROM api_setting_search".to_string(),
+    ))
+    .await?;
+    for collection in ApiCollections::find().all(&txn).await? {
+        index_api_settings(&txn, &collection.id, None).await?;
+    }
+    txn.commit().await?;
+    Ok(())
+}
+
+// 索引为空而有API配置时（旧版本数据）重建索引
+pub async fn init_search_index() -> Result<(), DbErr> {
+    let db = get_database().await?;
+    let result = db
+        .query_one(Statement::from_string(
+            db.get_database_backend(),
+            "SELECT (SELECT COUNT(*) FROM api_setting_search) AS indexed, (SELECT COUNT(*) FROM api_settings) AS total".to_string(),
+        ))
+        .await?;
+    if let Some(row) = result {
+        let indexed = row.try_get::<i64>("", "indexed")?;
+        let total = row.try_get::<i64>("", "total")?;
+        if indexed == 0 && total != 0 {
+            rebuild_search_index().await?;
+        }
+    }
+    Ok(())
+}
+
+// 转换为fts5的查询语句，每个关键字均作为短语匹配
+fn to_match_query(keyword: &str) -> String {
+    keyword
+        .split_whitespace()
+        .map(|item| format!("\"{}\"", item.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// 转义like中的通配符，需配合ESCAPE '\'使用
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+pub async fn search_api_settings(
+    keyword: String,
+    collection: Option<String>,
+    limit: u64,
+) -> Result<Vec<SearchResult>, DbErr> {
+    let keyword = keyword.trim().to_string();
+    if keyword.is_empty() {
+        return Ok(vec![]);
+    }
+    let db = get_database().await?;
+    let short_keyword = keyword
+        .split_whitespace()
+        .any(|item| item.chars().count() < MIN_MATCH_LENGTH);
+
+    let mut values: Vec<Value> = vec![];
+    // 关键字过短时无法使用全文索引，使用like匹配
+    let (select, condition) = if short_keyword {
+        let mut conditions = vec![];
+        for item in keyword.split_whitespace() {
+            conditions.push(
+                "(s.name LIKE ? ESCAPE '\\' OR s.uri LIKE ? ESCAPE '\\' OR s.headers LIKE ? ESCAPE '\\'
+                OR s.body LIKE ? ESCAPE '\\' OR s.folders LIKE ? ESCAPE '\\')",
+            );
+            let like = format!("%{}%", escape_like(item));
+            for _ in 0..5 {
+                values.push(like.clone().into());
+            }
+        }
+        ("s.name AS snippet, 0.0 AS rank", conditions.join(" AND "))
+    } else {
+        values.push(to_match_query(&keyword).into());
+        (
+            "snippet(api_setting_search, -1, '<mark>', '</mark>', '...', 16) AS snippet,
+            bm25(api_setting_search, 0.0, 0.0, 10.0, 5.0, 2.0, 1.0, 3.0) AS rank",
+            "api_setting_search MATCH ?".to_string(),
+        )
+    };
+    let mut sql = format!(
+        "SELECT s.id, s.collection, c.name AS collection_name, a.name, s.folders AS path, {}
+        FROM api_setting_search s
+        JOIN api_settings a ON a.id = s.id
+        JOIN api_collections c ON c.id = s.collection
+        WHERE {} AND a.deleted_at = '' AND c.deleted_at = ''",
+        select, condition
+    );
+    if let Some(collection) = collection {
+        sql += " AND s.collection = ?";
+        values.push(collection.into());
+    }
+    sql += " ORDER BY rank LIMIT ?";
+    values.push(limit.into());
+
+    let rows = db
+        .query_all(Statement::from_sql_and_values(
+            db.get_database_backend(),
+            &sql,
+            values,
+        ))
+        .await?;
+    let mut result = vec![];
+    for row in rows {
+        result.push(SearchResult {
+            id: row.try_get("", "id")?,
+            collection: row.try_get("", "collection")?,
+            collection_name: row.try_get("", "collection_name")?,
+            name: row.try_get("", "name")?,
+            path: row.try_get("", "path")?,
+            snippet: row.try_get("", "snippet")?,
+            rank: row.try_get("", "rank")?,
+        });
+    }
+    Ok(result)
+}
@@ -0,0 +1,329 @@
+use crate::entities::{prelude::*, secret_keys};
+use crate::util::get_app_dir;
+use aes_gcm::aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use sea_orm::{ActiveModelTrait, ConnectionTrait, DbErr, EntityTrait, Set};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{fs, path::Path, path::PathBuf, sync::Mutex};
+
+use super::database::get_database;
+
+// 加密变量在列表中展示的值，更新时如仍为该值则表示未修改
+pub const SECRET_MASK: &str = "******";
+
+const SECRET_PREFIX: &str = "enc:v1:";
+// 用于校验密码或密钥文件是否正确
+const SECRET_VERIFIER: &str = "cyberapi";
+const PBKDF2_ROUNDS: u32 = 600_000;
+const KEY_SIZE: usize = 32;
+const SALT_SIZE: usize = 16;
+const NONCE_SIZE: usize = 12;
+const KEY_FILE_DIR: &str = "keys";
+
+pub const SECRET_CATEGORY_PASSPHRASE: &str = "passphrase";
+pub const SECRET_CATEGORY_KEY_FILE: &str = "keyFile";
+
+// 已解锁的密钥，仅保存在内存中
+static SECRET_KEY: Lazy<Mutex<Option<[u8; KEY_SIZE]>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretStatus {
+    // 密钥类型(passphrase, keyFile)，未设置则为空
+    pub category: String,
+    // 是否已解锁
+    pub unlocked: bool,
+}
+
+pub fn get_secret_keys_create_sql() -> String {
+    "CREATE TABLE IF NOT EXISTS secret_keys (
+        id TEXT PRIMARY KEY NOT NULL check (id != ''),
+        category TEXT NOT NULL check (category != ''),
+        salt TEXT DEFAULT '',
+        verifier TEXT DEFAULT '',
+        created_at TEXT DEFAULT '',
+        updated_at TEXT DEFAULT ''
+    )"
+    .to_string()
+}
+
+fn new_error(message: &str) -> DbErr {
+    DbErr::Custom(format!("secret: {}", message))
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(SECRET_PREFIX)
+}
+
+fn encrypt_with(key: &[u8; KEY_SIZE], value: &str) -> Result<String, DbErr> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let mut data = cipher
+        .encrypt(&nonce, value.as_bytes())
+        .map_err(|_| new_error("encrypt fail"))?;
+    data.splice(0..0, nonce.iter().cloned());
+    Ok(format!(
+        "{}{}",
+        SECRET_PREFIX,
+        general_purpose::STANDARD.encode(data)
+    ))
+}
+
+fn decrypt_with(key: &[u8; KEY_SIZE], value: &str) -> Result<String, DbErr> {
+    let data = general_purpose::STANDARD
+        .decode(value.trim_start_matches(SECRET_PREFIX))
+        .map_err(|_| new_error("value is invalid"))?;
+    if data.len() < NONCE_SIZE {
+        return Err(new_error("value is invalid"));
+    }
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let data = cipher
+        .decrypt(Nonce::from_slice(&data[..NONCE_SIZE]), &data[NONCE_SIZE..])
+        .map_err(|_| new_error("decrypt fail, the key does not match"))?;
+    String::from_utf8(data).map_err(|_| new_error("value is invalid"))
+}
+
+// 密钥派生耗时较长（数百毫秒），在blocking线程中执行，避免阻塞异步任务
+async fn derive_key(passphrase: String, salt: Vec<u8>) -> Result<[u8; KEY_SIZE], DbErr> {
+    tokio::task::spawn_blocking(move || {
+        let mut key = [0u8; KEY_SIZE];
+        pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), &salt, PBKDF2_ROUNDS, &mut key);
+        key
+    })
+    .await
+    .map_err(|err| new_error(&err.to_string()))
+}
+
+// 密钥文件仅保存在本机，其它设备使用同一数据库时需复制该文件
+fn get_key_file(id: &str) -> PathBuf {
+    Path::new(get_app_dir())
+        .join(KEY_FILE_DIR)
+        .join(format!("{}.key", id))
+}
+
+fn read_key_file(id: &str) -> Result<[u8; KEY_SIZE], DbErr> {
+    read_key(&get_key_file(id))
+}
+
+fn read_key(file: &Path) -> Result<[u8; KEY_SIZE], DbErr> {
+    let content = fs::read_to_string(file)
+        .map_err(|err| new_error(&format!("{}, {}", err, file.display())))?;
+    let data = general_purpose::STANDARD
+        .decode(content.trim())
+        .map_err(|_| new_error("key file is invalid"))?;
+    if data.len() != KEY_SIZE {
+        return Err(new_error("key file is invalid"));
+    }
+    let mut key = [0u8; KEY_SIZE];
+    key.copy_from_slice(&data);
+    Ok(key)
+}
+
+fn write_key_file(id: &str, key: &[u8; KEY_SIZE]) -> Result<(), DbErr> {
+    let file = get_key_file(id);
+    let err_handle = |err: std::io::Error| new_error(&format!("{}, {}", err, file.display()));
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir).map_err(err_handle)?;
+    }
+    fs::write(&file, general_purpose::STANDARD.encode(key)).map_err(err_handle)?;
+    Ok(())
+}
+
+async fn find_secret_key<C>(db: &C) -> Result<Option<secret_keys::Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    SecretKeys::find().one(db).await
+}
+
+// 校验密钥是否与数据库中的一致
+fn verify_key(model: &secret_keys::Model, key: &[u8; KEY_SIZE]) -> Result<(), DbErr> {
+    match decrypt_with(key, model.verifier.as_deref().unwrap_or_default()) {
+        Ok(value) if value == SECRET_VERIFIER => Ok(()),
+        _ => Err(new_error("passphrase or key file is invalid")),
+    }
+}
+
+// 获取已解锁的密钥，使用密钥文件时自动加载
+async fn get_secret_key<C>(db: &C) -> Result<[u8; KEY_SIZE], DbErr>
+where
+    C: ConnectionTrait,
+{
+    if let Some(key) = *SECRET_KEY.lock().unwrap() {
+        return Ok(key);
+    }
+    let model = find_secret_key(db)
+        .await?
+        .ok_or_else(|| new_error("secret key is not initialized"))?;
+    if model.category != SECRET_CATEGORY_KEY_FILE {
+        return Err(new_error("secret is locked, please unlock it first"));
+    }
+    let key = read_key_file(&model.id)?;
+    verify_key(&model, &key)?;
+    *SECRET_KEY.lock().unwrap() = Some(key);
+    Ok(key)
+}
+
+pub async fn encrypt_secret<C>(db: &C, value: &str) -> Result<String, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let key = get_secret_key(db).await?;
+    encrypt_with(&key, value)
+}
+
+pub async fn decrypt_secret<C>(db: &C, value: &str) -> Result<String, DbErr>
+where
+    C: ConnectionTrait,
+{
+    if !is_encrypted(value) {
+        return Ok(value.to_string());
+    }
+    let key = get_secret_key(db).await?;
+    decrypt_with(&key, value)
+}
+
+// 保存前处理变量的值：值为掩码时表示未修改，使用数据库中原有的值（取消加密时解密），
+// 加密变量的新值则加密后保存
+pub async fn prepare_secret_value<C>(
+    db: &C,
+    value: &str,
+    secret: bool,
+    stored: Option<String>,
+) -> Result<String, DbErr>
+where
+    C: ConnectionTrait,
+{
+    if value == SECRET_MASK {
+        let stored = stored.unwrap_or_default();
+        if secret {
+            return Ok(stored);
+        }
+        return decrypt_secret(db, &stored).await;
+    }
+    if !secret || value.is_empty() {
+        return Ok(value.to_string());
+    }
+    // 已加密的值（如导入的数据）需可使用当前密钥解密，否则仅是以前缀开头的普通值，仍需加密
+    if is_encrypted(value) && decrypt_secret(db, value).await.is_ok() {
+        return Ok(value.to_string());
+    }
+    encrypt_secret(db, value).await
+}
+
+pub async fn get_secret_status() -> Result<SecretStatus, DbErr> {
+    let db = get_database().await?;
+    let category = find_secret_key(&db)
+        .await?
+        .map(|item| item.category)
+        .unwrap_or_default();
+    let unlocked = !category.is_empty() && get_secret_key(&db).await.is_ok();
+    Ok(SecretStatus { category, unlocked })
+}
+
+// 初始化密钥，指定密码则由密码生成，否则生成随机密钥保存至本机的密钥文件
+pub async fn init_secret_key(passphrase: Option<String>) -> Result<SecretStatus, DbErr> {
+    let db = get_database().await?;
+    if find_secret_key(&db).await?.is_some() {
+        return Err(new_error("secret key already exists"));
+    }
+    let id = ulid::Ulid::new().to_string();
+    let mut key = [0u8; KEY_SIZE];
+    let mut salt = "".to_string();
+    let category = match passphrase {
+        Some(passphrase) if !passphrase.is_empty() => {
+            let mut data = [0u8; SALT_SIZE];
+            OsRng.fill_bytes(&mut data);
+            salt = general_purpose::STANDARD.encode(data);
+            key = derive_key(passphrase, data.to_vec()).await?;
+            SECRET_CATEGORY_PASSPHRASE
+        }
+        _ => {
+            OsRng.fill_bytes(&mut key);
+            write_key_file(&id, &key)?;
+            SECRET_CATEGORY_KEY_FILE
+        }
+    };
+    let now = Utc::now().to_rfc3339();
+    secret_keys::ActiveModel {
+        id: Set(id),
+        category: Set(category.to_string()),
+        salt: Set(Some(salt)),
+        verifier: Set(Some(encrypt_with(&key, SECRET_VERIFIER)?)),
+        created_at: Set(Some(now.clone())),
+        updated_at: Set(Some(now)),
+    }
+    .insert(&db)
+    .await?;
+    *SECRET_KEY.lock().unwrap() = Some(key);
+    get_secret_status().await
+}
+
+// 以密码解锁，使用密钥文件时重新加载密钥文件
+pub async fn unlock_secret(passphrase: Option<String>) -> Result<SecretStatus, DbErr> {
+    let db = get_database().await?;
+    let model = find_secret_key(&db)
+        .await?
+        .ok_or_else(|| new_error("secret key is not initialized"))?;
+    let key = if model.category == SECRET_CATEGORY_PASSPHRASE {
+        let salt = general_purpose::STANDARD
+            .decode(model.salt.clone().unwrap_or_default())
+            .map_err(|_| new_error("salt is invalid"))?;
+        derive_key(passphrase.unwrap_or_default(), salt).await?
+    } else {
+        read_key_file(&model.id)?
+    };
+    verify_key(&model, &key)?;
+    *SECRET_KEY.lock().unwrap() = Some(key);
+    get_secret_status().await
+}
+
+// 导入其它设备复制的密钥文件（如命令行使用的临时应用目录），导入后需再解锁
+pub async fn import_key_file(file: &str) -> Result<(), DbErr> {
+    let db = get_database().await?;
+    let model = find_secret_key(&db)
+        .await?
+        .ok_or_else(|| new_error("secret key is not initialized"))?;
+    if model.category != SECRET_CATEGORY_KEY_FILE {
+        return Err(new_error("secret key does not use key file"));
+    }
+    let key = read_key(Path::new(file))?;
+    verify_key(&model, &key)?;
+    write_key_file(&model.id, &key)
+}
+
+// 清除内存中的密钥，切换数据库时也需清除
+pub fn lock_secret() {
+    *SECRET_KEY.lock().unwrap() = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schemas::init_test_database;
+
+    #[test]
+    fn secret_value_with_prefix_is_encrypted() {
+        tauri::async_runtime::block_on(async {
+            let _secret = init_test_database().await;
+            let db = get_database().await.unwrap();
+
+            // 以前缀开头但无法解密的值仍需加密
+            let value = format!("{}not-a-ciphertext", SECRET_PREFIX);
+            let stored = prepare_secret_value(&db, &value, true, None).await.unwrap();
+            assert_ne!(stored, value);
+            assert_eq!(decrypt_secret(&db, &stored).await.unwrap(), value);
+
+            // 已加密的值不重复加密
+            let encrypted = encrypt_secret(&db, "token").await.unwrap();
+            let stored = prepare_secret_value(&db, &encrypted, true, None)
+                .await
+                .unwrap();
+            assert_eq!(stored, encrypted);
+        });
+    }
+}
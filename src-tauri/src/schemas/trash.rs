@@ -0,0 +1,336 @@
+use crate::entities::{api_collections, api_folders, api_settings, prelude::*};
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait,
+    QueryFilter, Set, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use super::api_folder::{find_all_children, get_folder_children_map};
+use super::database::get_database;
+use super::file_store::sync_file_stores;
+use super::search::delete_api_setting_index;
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum TrashCategory {
+    Collection,
+    Folder,
+    Setting,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashItem {
+    // id
+    pub id: String,
+    // 类型
+    pub category: TrashCategory,
+    // collection ID
+    pub collection: String,
+    // 名称
+    pub name: Option<String>,
+    // 删除时间
+    pub deleted_at: String,
+}
+
+// 查找子元素所在的目录
+fn find_parent(folder_children: &HashMap<String, Option<String>>, id: &str) -> Option<String> {
+    for (folder, children) in folder_children.iter() {
+        let children = children.clone().unwrap_or_default();
+        if children.split(',').any(|child| child.trim() == id) {
+            return Some(folder.clone());
+        }
+    }
+    None
+}
+
+async fn list_trash_items<C>(db: &C) -> Result<Vec<TrashItem>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let mut result = vec![];
+    let mut deleted_collections = HashSet::new();
+    for item in ApiCollections::find()
+        .filter(api_collections::Column::DeletedAt.ne(""))
+        .all(db)
+        .await?
+    {
+        deleted_collections.insert(item.id.clone());
+        result.push(TrashItem {
+            id: item.id.clone(),
+            category: TrashCategory::Collection,
+            collection: item.id,
+            name: item.name,
+            deleted_at: item.deleted_at.unwrap_or_default(),
+        });
+    }
+
+    // 查询所有目录，用于判断父目录是否同时被删除
+    let folders = ApiFolders::find().all(db).await?;
+    let mut parents = HashMap::new();
+    for folder in folders.iter() {
+        for child in folder.children.as_deref().unwrap_or_default().split(',') {
+            parents.insert(child.trim().to_string(), folder);
+        }
+    }
+    // collection已删除或者与父目录同时删除的，随其一起展示与恢复
+    let is_root = |id: &str, collection: &str, deleted_at: &str| -> bool {
+        if deleted_collections.contains(collection) {
+            return false;
+        }
+        match parents.get(id) {
+            Some(parent) => parent.deleted_at.as_deref() != Some(deleted_at),
+            None => true,
+        }
+    };
+
+    for item in folders.iter() {
+        let deleted_at = item.deleted_at.clone().unwrap_or_default();
+        if deleted_at.is_empty() || !is_root(&item.id, &item.collection, &deleted_at) {
+            continue;
+        }
+        result.push(TrashItem {
+            id: item.id.clone(),
+            category: TrashCategory::Folder,
+            collection: item.collection.clone(),
+            name: item.name.clone(),
+            deleted_at,
+        });
+    }
+    for item in ApiSettings::find()
+        .filter(api_settings::Column::DeletedAt.ne(""))
+        .all(db)
+        .await?
+    {
+        let deleted_at = item.deleted_at.unwrap_or_default();
+        if !is_root(&item.id, &item.collection, &deleted_at) {
+            continue;
+        }
+        result.push(TrashItem {
+            id: item.id,
+            category: TrashCategory::Setting,
+            collection: item.collection,
+            name: item.name,
+            deleted_at,
+        });
+    }
+    result.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+
+    Ok(result)
+}
+
+// 获取回收站中的数据，目录的子元素不单独展示
+pub async fn list_trash() -> Result<Vec<TrashItem>, DbErr> {
+    let db = get_database().await?;
+    list_trash_items(&db).await
+}
+
+// 查询目录或API配置所属的collection与删除时间
+async fn find_folder_or_setting<C>(db: &C, id: &str) -> Result<Option<(String, String)>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    if let Some(folder) = ApiFolders::find_by_id(id).one(db).await? {
+        return Ok(Some((
+            folder.collection,
+            folder.deleted_at.unwrap_or_default(),
+        )));
+    }
+    if let Some(setting) = ApiSettings::find_by_id(id).one(db).await? {
+        return Ok(Some((
+            setting.collection,
+            setting.deleted_at.unwrap_or_default(),
+        )));
+    }
+    Ok(None)
+}
+
+// 恢复回收站中的数据，返回所属的collection
+async fn restore_item<C>(db: &C, id: &str) -> Result<Option<String>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    if ApiCollections::find_by_id(id).one(db).await?.is_some() {
+        ApiCollections::update_many()
+            .col_expr(api_collections::Column::DeletedAt, Expr::value(""))
+            .filter(api_collections::Column::Id.eq(id))
+            .exec(db)
+            .await?;
+        return Ok(Some(id.to_string()));
+    }
+    let (collection, deleted_at) = match find_folder_or_setting(db, id).await? {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+
+    let folder_children = get_folder_children_map(db, collection.clone()).await?;
+    // 同一次删除的子元素一并恢复
+    let children = find_all_children(&folder_children, id);
+    let mut folders = vec![id.to_string()];
+    let mut settings = vec![id.to_string()];
+    if !deleted_at.is_empty() {
+        ApiFolders::update_many()
+            .col_expr(api_folders::Column::DeletedAt, Expr::value(""))
+            .filter(api_folders::Column::Id.is_in(children.folders))
+            .filter(api_folders::Column::DeletedAt.eq(deleted_at.clone()))
+            .exec(db)
+            .await?;
+        ApiSettings::update_many()
+            .col_expr(api_settings::Column::DeletedAt, Expr::value(""))
+            .filter(api_settings::Column::Id.is_in(children.settings))
+            .filter(api_settings::Column::DeletedAt.eq(deleted_at))
+            .exec(db)
+            .await?;
+    }
+
+    // 父目录（以及collection）如果已删除，则同时恢复，否则无法展示
+    // 子元素未从父目录中移除，恢复后即在原来的位置
+    let mut current = id.to_string();
+    while let Some(parent) = find_parent(&folder_children, &current) {
+        if folders.contains(&parent) {
+            break;
+        }
+        folders.push(parent.clone());
+        current = parent;
+    }
+    settings.retain(|item| !folder_children.contains_key(item));
+    folders.retain(|item| folder_children.contains_key(item));
+
+    ApiFolders::update_many()
+        .col_expr(api_folders::Column::DeletedAt, Expr::value(""))
+        .filter(api_folders::Column::Id.is_in(folders))
+        .exec(db)
+        .await?;
+    ApiSettings::update_many()
+        .col_expr(api_settings::Column::DeletedAt, Expr::value(""))
+        .filter(api_settings::Column::Id.is_in(settings))
+        .exec(db)
+        .await?;
+    ApiCollections::update_many()
+        .col_expr(api_collections::Column::DeletedAt, Expr::value(""))
+        .filter(api_collections::Column::Id.eq(collection.clone()))
+        .exec(db)
+        .await?;
+    Ok(Some(collection))
+}
+
+// 从回收站中恢复
+pub async fn restore_trash(ids: Vec<String>) -> Result<(), DbErr> {
+    let db = get_database().await?;
+    let txn = db.begin().await?;
+    let mut collections = vec![];
+    for id in ids {
+        if let Some(collection) = restore_item(&txn, &id).await? {
+            collections.push(collection);
+        }
+    }
+    txn.commit().await?;
+    sync_file_stores(collections).await;
+    Ok(())
+}
+
+// 永久删除回收站中的数据，返回所属的collection（删除collection时为None）
+async fn purge_item<C>(db: &C, id: &str) -> Result<Option<String>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    if ApiCollections::find_by_id(id).one(db).await?.is_some() {
+        // 目录、API配置与变量均通过外键级联删除
+        ApiCollections::delete_by_id(id).exec(db).await?;
+        delete_api_setting_index(db, id).await?;
+        return Ok(None);
+    }
+    let (collection, deleted_at) = match find_folder_or_setting(db, id).await? {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    let folder_children = get_folder_children_map(db, collection.clone()).await?;
+    let children = find_all_children(&folder_children, id);
+    // 只删除同一次删除的子元素，单独删除的子元素仍保留在回收站中
+    let mut folders: Vec<String> = ApiFolders::find()
+        .filter(api_folders::Column::Id.is_in(children.folders))
+        .filter(api_folders::Column::DeletedAt.eq(deleted_at.clone()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|item| item.id)
+        .collect();
+    let mut settings: Vec<String> = ApiSettings::find()
+        .filter(api_settings::Column::Id.is_in(children.settings))
+        .filter(api_settings::Column::DeletedAt.eq(deleted_at))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|item| item.id)
+        .collect();
+    if folder_children.contains_key(id) {
+        folders.push(id.to_string());
+    } else {
+        settings.push(id.to_string());
+    }
+    ApiFolders::delete_many()
+        .filter(api_folders::Column::Id.is_in(folders))
+        .exec(db)
+        .await?;
+    ApiSettings::delete_many()
+        .filter(api_settings::Column::Id.is_in(settings.clone()))
+        .exec(db)
+        .await?;
+    for setting in settings {
+        delete_api_setting_index(db, &setting).await?;
+    }
+
+    // 从父目录中移除
+    if let Some(parent) = find_parent(&folder_children, id) {
+        if let Some(folder) = ApiFolders::find_by_id(parent).one(db).await? {
+            let children = folder
+                .children
+                .clone()
+                .unwrap_or_default()
+                .split(',')
+                .filter(|child| child.trim() != id)
+                .collect::<Vec<_>>()
+                .join(",");
+            let mut model: api_folders::ActiveModel = folder.into();
+            model.children = Set(Some(children));
+            model.update(db).await?;
+        }
+    }
+    Ok(Some(collection))
+}
+
+// 永久删除回收站中的数据
+pub async fn purge_trash(ids: Vec<String>) -> Result<(), DbErr> {
+    let db = get_database().await?;
+    let txn = db.begin().await?;
+    let mut collections = vec![];
+    for id in ids {
+        if let Some(collection) = purge_item(&txn, &id).await? {
+            collections.push(collection);
+        }
+    }
+    txn.commit().await?;
+    sync_file_stores(collections).await;
+    Ok(())
+}
+
+// 永久删除超过指定天数的数据
+pub async fn clean_trash(expired_days: u32) -> Result<Vec<TrashItem>, DbErr> {
+    let db = get_database().await?;
+    let expired_at = Utc::now() - Duration::days(expired_days as i64);
+    let txn = db.begin().await?;
+    let mut result = vec![];
+    for item in list_trash_items(&txn).await? {
+        let expired = DateTime::parse_from_rfc3339(&item.deleted_at)
+            .map(|deleted_at| deleted_at < expired_at)
+            .unwrap_or_default();
+        if expired {
+            purge_item(&txn, &item.id).await?;
+            result.push(item);
+        }
+    }
+    txn.commit().await?;
+    Ok(result)
+}
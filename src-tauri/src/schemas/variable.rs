@@ -1,12 +1,33 @@
 use crate::{
     entities::{prelude::*, variables},
     error::CyberAPIError,
+    template::{TemplateContext, VariableScope},
 };
 use chrono::Utc;
-use sea_orm::{ActiveModelTrait, ColumnTrait, DbErr, EntityTrait, QueryFilter, Set};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, Set,
+};
 use serde::{Deserialize, Serialize};
 
 use super::database::{get_database, ExportData};
+use super::environment::{
+    environment_condition, find_active_environment, list_active_environment_variables,
+};
+use super::file_store::{sync_file_store_after_commit, sync_file_stores};
+use super::global_variable::{list_enabled_global_variable, set_global_variable_value};
+use super::secret::{decrypt_secret, prepare_secret_value, SECRET_MASK};
+
+// 加密保存的变量
+pub const VARIABLE_SECRET: &str = "1";
+// 启用的变量
+pub const VARIABLE_ENABLED: &str = "1";
+
+// 环境变量
+pub const VARIABLE_CATEGORY_ENV: &str = "env";
+// 自定义变量，collection内通用
+pub const VARIABLE_CATEGORY_CUSTOMIZE: &str = "customize";
+// 全局请求头，collection内所有请求均添加
+pub const VARIABLE_CATEGORY_GLOBAL_REQ_HEADERS: &str = "globalReqHeaders";
 
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -17,27 +38,44 @@ pub struct Variable {
     pub category: String,
     // collection ID
     pub collection: String,
+    // 所属环境ID，为空则为基础环境（仅环境变量）
+    pub environment: Option<String>,
     // 变量名称
     pub name: Option<String>,
     // 变量值
     pub value: Option<String>,
     // 是否启用(0:禁用 1:启用)
     pub enabled: Option<String>,
+    // 是否加密保存(0:否 1:是)
+    pub secret: Option<String>,
     // 创建时间
     pub created_at: Option<String>,
     // 更新时间
     pub updated_at: Option<String>,
 }
 
+pub fn is_secret(secret: &Option<String>) -> bool {
+    secret.as_deref() == Some(VARIABLE_SECRET)
+}
+
+// 加密变量的值以掩码展示
 impl From<variables::Model> for Variable {
     fn from(model: variables::Model) -> Self {
+        let value =
+            if is_secret(&model.secret) && !model.value.clone().unwrap_or_default().is_empty() {
+                Some(SECRET_MASK.to_string())
+            } else {
+                model.value
+            };
         Variable {
             id: model.id,
             category: model.category,
             collection: model.collection,
+            environment: model.environment,
             name: model.name,
-            value: model.value,
+            value,
             enabled: model.enabled,
+            secret: model.secret,
             created_at: model.created_at,
             updated_at: model.updated_at,
         }
@@ -51,9 +89,11 @@ impl Variable {
             id: Set(self.id),
             category: Set(self.category),
             collection: Set(self.collection),
+            environment: Set(Some(self.environment.unwrap_or_default())),
             name: Set(self.name),
             value: Set(self.value),
             enabled: Set(self.enabled),
+            secret: Set(self.secret),
             created_at: Set(created_at),
             updated_at: Set(updated_at),
         }
@@ -61,49 +101,273 @@ impl Variable {
 }
 
 pub fn get_variables_create_sql() -> String {
-    "CREATE TABLE IF NOT EXISTS variables (
+    new_variables_create_sql(&get_table_name_variable())
+}
+
+pub fn new_variables_create_sql(table: &str) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} (
             id TEXT PRIMARY KEY NOT NULL check (id != ''),
             category TEXT NOT NULL check (category != ''),
-            collection TEXT NOT NULL check (collection != ''),
+            collection TEXT NOT NULL check (collection != '')
+                REFERENCES api_collections(id) ON DELETE CASCADE,
+            environment TEXT DEFAULT '',
             name TEXT DEFAULT '',
             value TEXT DEFAULT '',
             enabled TEXT DEFAULT '',
+            secret TEXT DEFAULT '',
             created_at TEXT DEFAULT '',
             updated_at TEXT DEFAULT ''
-        )"
-    .to_string()
+        )",
+        table
+    )
+}
+
+async fn prepare_value<C>(db: &C, value: &mut Variable) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let current = value.value.clone().unwrap_or_default();
+    let stored = if current == SECRET_MASK {
+        Variables::find_by_id(value.id.clone())
+            .one(db)
+            .await?
+            .and_then(|item| item.value)
+    } else {
+        None
+    };
+    value.value = Some(prepare_secret_value(db, &current, is_secret(&value.secret), stored).await?);
+    Ok(())
 }
 
-pub async fn add_variable(value: Variable) -> Result<Variable, DbErr> {
+pub async fn add_variable(mut value: Variable) -> Result<Variable, DbErr> {
+    let db = get_database().await?;
+    prepare_value(&db, &mut value).await?;
     let model = value.into_active_model();
-    let db = get_database().await;
     let result = model.insert(&db).await?;
+    sync_file_store_after_commit(&result.collection).await;
     Ok(result.into())
 }
 
-pub async fn update_variable(value: Variable) -> Result<Variable, DbErr> {
+pub async fn update_variable(mut value: Variable) -> Result<Variable, DbErr> {
+    let db = get_database().await?;
+    prepare_value(&db, &mut value).await?;
     let model = value.into_active_model();
-    let db = get_database().await;
     let result = model.update(&db).await?;
+    sync_file_store_after_commit(&result.collection).await;
     Ok(result.into())
 }
 
-pub async fn list_variable(collection: String, category: String) -> Result<Vec<Variable>, DbErr> {
-    let db = get_database().await;
+// 加密变量的值默认以掩码展示，reveal为true时解密
+// environment为空时获取基础环境的变量
+pub async fn list_variable(
+    collection: String,
+    category: String,
+    environment: String,
+    reveal: bool,
+) -> Result<Vec<Variable>, DbErr> {
+    let db = get_database().await?;
     let result = Variables::find()
         .filter(variables::Column::Collection.eq(collection))
         .filter(variables::Column::Category.eq(category))
+        .filter(environment_condition(&environment))
+        .all(&db)
+        .await?;
+    let mut variables = vec![];
+    for item in result {
+        let secret_value = if reveal && is_secret(&item.secret) {
+            Some(decrypt_secret(&db, &item.value.clone().unwrap_or_default()).await?)
+        } else {
+            None
+        };
+        let mut variable = Variable::from(item);
+        if secret_value.is_some() {
+            variable.value = secret_value;
+        }
+        variables.push(variable);
+    }
+    Ok(variables)
+}
+
+// 获取单个变量的值，加密变量则解密后返回，用于界面中查看
+pub async fn reveal_variable(id: String) -> Result<String, DbErr> {
+    let db = get_database().await?;
+    let item = Variables::find_by_id(id.clone())
+        .one(&db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound(format!("variable: {}", id)))?;
+    let value = item.value.unwrap_or_default();
+    if !is_secret(&item.secret) {
+        return Ok(value);
+    }
+    decrypt_secret(&db, &value).await
+}
+
+// 获取用于替换占位符的变量，优先级：全局 < collection < 环境 < 请求
+// 环境变量为collection当前使用的环境（包括继承的）的变量
+// 加密变量无法解密（未解锁）时忽略，引用时作为未定义变量
+pub async fn get_template_context(
+    collection: Option<String>,
+    local: Vec<(String, String)>,
+) -> Result<TemplateContext, DbErr> {
+    let db = get_database().await?;
+    let mut context = TemplateContext::new();
+    let mut items = vec![];
+    for item in list_enabled_global_variable(&db).await? {
+        items.push((VariableScope::Global, item.name, item.value, item.secret));
+    }
+    if let Some(collection) = collection {
+        let result = Variables::find()
+            .filter(variables::Column::Collection.eq(collection.clone()))
+            .filter(variables::Column::Category.eq(VARIABLE_CATEGORY_CUSTOMIZE))
+            .filter(variables::Column::Enabled.eq(VARIABLE_ENABLED))
+            .all(&db)
+            .await?;
+        for item in result {
+            items.push((
+                VariableScope::Collection,
+                item.name,
+                item.value,
+                item.secret,
+            ));
+        }
+        for item in list_active_environment_variables(&db, &collection).await? {
+            items.push((
+                VariableScope::Environment,
+                item.name,
+                item.value,
+                item.secret,
+            ));
+        }
+    }
+    for (scope, name, value, secret) in items {
+        let name = name.unwrap_or_default();
+        if name.is_empty() {
+            continue;
+        }
+        let mut value = value.unwrap_or_default();
+        if is_secret(&secret) {
+            value = match decrypt_secret(&db, &value).await {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
+        }
+        context.add(scope, name, value);
+    }
+    for (name, value) in local {
+        if !name.is_empty() {
+            context.add(VariableScope::Local, name, value);
+        }
+    }
+    Ok(context)
+}
+
+// 获取collection启用的全局请求头，secret_only为true时仅获取加密的请求头
+// 加密的请求头解密后返回，无法解密（未解锁）时忽略
+pub async fn list_global_req_headers(
+    collection: &str,
+    secret_only: bool,
+) -> Result<Vec<(String, String)>, DbErr> {
+    let db = get_database().await?;
+    let result = Variables::find()
+        .filter(variables::Column::Collection.eq(collection))
+        .filter(variables::Column::Category.eq(VARIABLE_CATEGORY_GLOBAL_REQ_HEADERS))
+        .filter(variables::Column::Enabled.eq(VARIABLE_ENABLED))
         .all(&db)
         .await?;
-    Ok(result.into_iter().map(Variable::from).collect())
+    let mut headers = vec![];
+    for item in result {
+        let name = item.name.unwrap_or_default();
+        let secret = is_secret(&item.secret);
+        if name.is_empty() || (secret_only && !secret) {
+            continue;
+        }
+        let mut value = item.value.unwrap_or_default();
+        if secret {
+            value = match decrypt_secret(&db, &value).await {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
+        }
+        headers.push((name, value));
+    }
+    Ok(headers)
+}
+
+// 保存从响应中提取的值，同名变量已存在则更新其值，否则新增，返回是否为加密变量
+// 环境变量保存至collection当前使用的环境，请求范围的变量不保存
+pub async fn save_extracted_variable(
+    collection: &str,
+    scope: VariableScope,
+    name: &str,
+    value: &str,
+) -> Result<bool, DbErr> {
+    let db = get_database().await?;
+    let (category, environment) = match scope {
+        VariableScope::Global => return set_global_variable_value(&db, name, value).await,
+        VariableScope::Local => return Ok(false),
+        VariableScope::Collection => (VARIABLE_CATEGORY_CUSTOMIZE, "".to_string()),
+        VariableScope::Environment => (
+            VARIABLE_CATEGORY_ENV,
+            find_active_environment(&db, collection)
+                .await?
+                .map(|item| item.id)
+                .unwrap_or_default(),
+        ),
+    };
+    let now = Utc::now().to_rfc3339();
+    let current = Variables::find()
+        .filter(variables::Column::Collection.eq(collection))
+        .filter(variables::Column::Category.eq(category))
+        .filter(environment_condition(&environment))
+        .filter(variables::Column::Name.eq(name))
+        .one(&db)
+        .await?;
+    let secret = match current {
+        Some(current) => {
+            let secret = is_secret(&current.secret);
+            let mut model: variables::ActiveModel = current.into();
+            model.value = Set(Some(prepare_secret_value(&db, value, secret, None).await?));
+            model.updated_at = Set(Some(now));
+            model.update(&db).await?;
+            secret
+        }
+        None => {
+            variables::ActiveModel {
+                id: Set(ulid::Ulid::new().to_string()),
+                category: Set(category.to_string()),
+                collection: Set(collection.to_string()),
+                environment: Set(Some(environment)),
+                name: Set(Some(name.to_string())),
+                value: Set(Some(value.to_string())),
+                enabled: Set(Some(VARIABLE_ENABLED.to_string())),
+                secret: Set(Some("".to_string())),
+                created_at: Set(Some(now.clone())),
+                updated_at: Set(Some(now)),
+            }
+            .insert(&db)
+            .await?;
+            false
+        }
+    };
+    sync_file_store_after_commit(collection).await;
+    Ok(secret)
 }
 
 pub async fn delete_variable(ids: Vec<String>) -> Result<u64, DbErr> {
-    let db = get_database().await;
+    let db = get_database().await?;
+    let collections = Variables::find()
+        .filter(variables::Column::Id.is_in(ids.clone()))
+        .all(&db)
+        .await?
+        .into_iter()
+        .map(|item| item.collection)
+        .collect();
     let result = Variables::delete_many()
         .filter(variables::Column::Id.is_in(ids))
         .exec(&db)
         .await?;
+    sync_file_stores(collections).await;
     Ok(result.rows_affected)
 }
 
@@ -112,14 +376,20 @@ pub fn get_table_name_variable() -> String {
 }
 
 pub async fn delete_all_variable() -> Result<(), CyberAPIError> {
-    let db = get_database().await;
+    let db = get_database().await?;
     Variables::delete_many().exec(&db).await?;
     Ok(())
 }
 
 pub async fn export_variable() -> Result<ExportData, DbErr> {
-    let db = get_database().await;
-    let data = Variables::find().into_json().all(&db).await?;
+    let db = get_database().await?;
+    let mut data = Variables::find().into_json().all(&db).await?;
+    // 加密变量的值不导出
+    for item in data.iter_mut() {
+        if item.get("secret").and_then(|value| value.as_str()) == Some(VARIABLE_SECRET) {
+            item["value"] = serde_json::Value::String("".to_string());
+        }
+    }
     Ok(ExportData {
         name: get_table_name_variable(),
         data,
@@ -127,7 +397,7 @@ pub async fn export_variable() -> Result<ExportData, DbErr> {
 }
 
 pub async fn import_variable(data: Vec<serde_json::Value>) -> Result<(), CyberAPIError> {
-    let db = get_database().await;
+    let db = get_database().await?;
 
     let mut records = Vec::new();
     for ele in data {
@@ -0,0 +1,240 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+const PLACEHOLDER_START: &str = "{{";
+const PLACEHOLDER_END: &str = "}}";
+
+// 变量的作用域，按优先级从低到高排列
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum VariableScope {
+    // 全局
+    Global,
+    // collection
+    Collection,
+    // 环境
+    Environment,
+    // 单个请求
+    Local,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateVariable {
+    pub name: String,
+    pub value: String,
+    pub scope: VariableScope,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UnresolvedPlaceholder {
+    // 变量名称
+    pub name: String,
+    // 占位符所在的字段，如uri、headers.Authorization
+    pub field: String,
+}
+
+// 模板预览的结果
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplatePreview {
+    pub value: String,
+    // 未定义的变量，占位符保持原样
+    pub unresolved: Vec<UnresolvedPlaceholder>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum TemplateError {
+    // 未定义的变量
+    Unresolved {
+        placeholders: Vec<UnresolvedPlaceholder>,
+    },
+    // 变量之间循环引用，如a -> b -> a
+    Cycle {
+        field: String,
+        chain: Vec<String>,
+    },
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::Unresolved { placeholders } => {
+                let names: Vec<String> = placeholders
+                    .iter()
+                    .map(|item| {
+                        if item.field.is_empty() {
+                            item.name.clone()
+                        } else {
+                            format!("{}({})", item.name, item.field)
+                        }
+                    })
+                    .collect();
+                write!(f, "unresolved variables: {}", names.join(", "))
+            }
+            TemplateError::Cycle { chain, .. } => {
+                write!(f, "circular variable reference: {}", chain.join(" -> "))
+            }
+        }
+    }
+}
+
+// 占位符仅支持变量名称，如{{md5(...)}}等函数由其它方式处理，保持原样
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct TemplateContext {
+    variables: HashMap<String, TemplateVariable>,
+}
+
+impl TemplateContext {
+    pub fn new() -> Self {
+        TemplateContext::default()
+    }
+    // 同名变量以优先级高的作用域为准
+    pub fn add(&mut self, scope: VariableScope, name: String, value: String) {
+        if let Some(current) = self.variables.get(&name) {
+            if current.scope > scope {
+                return;
+            }
+        }
+        self.variables
+            .insert(name.clone(), TemplateVariable { name, value, scope });
+    }
+    pub fn get(&self, name: &str) -> Option<&TemplateVariable> {
+        self.variables.get(name)
+    }
+    // 所有生效的变量，按名称排序
+    pub fn list(&self) -> Vec<TemplateVariable> {
+        let mut result: Vec<TemplateVariable> = self.variables.values().cloned().collect();
+        result.sort_by(|a, b| a.name.cmp(&b.name));
+        result
+    }
+    pub fn renderer(&self) -> TemplateRenderer<'_> {
+        TemplateRenderer {
+            context: self,
+            resolved: HashMap::new(),
+            unresolved: vec![],
+        }
+    }
+    pub fn render(&self, text: &str) -> Result<String, TemplateError> {
+        let mut renderer = self.renderer();
+        let result = renderer.render("", text)?;
+        renderer.finish()?;
+        Ok(result)
+    }
+    // 预览替换的结果，未定义的变量保持原样并一并返回，循环引用仍出错
+    pub fn preview(&self, text: &str) -> Result<TemplatePreview, TemplateError> {
+        let mut renderer = self.renderer();
+        let value = renderer.render("", text)?;
+        Ok(TemplatePreview {
+            value,
+            unresolved: renderer.into_unresolved(),
+        })
+    }
+}
+
+// 渲染多个字段时，未定义的变量统一在finish时返回
+pub struct TemplateRenderer<'a> {
+    context: &'a TemplateContext,
+    // 已解析的变量值（变量值中也可引用其它变量）
+    resolved: HashMap<String, String>,
+    unresolved: Vec<UnresolvedPlaceholder>,
+}
+
+impl<'a> TemplateRenderer<'a> {
+    pub fn render(&mut self, field: &str, text: &str) -> Result<String, TemplateError> {
+        let mut stack = vec![];
+        self.render_text(field, text, &mut stack)
+    }
+    pub fn finish(self) -> Result<(), TemplateError> {
+        if self.unresolved.is_empty() {
+            return Ok(());
+        }
+        Err(TemplateError::Unresolved {
+            placeholders: self.unresolved,
+        })
+    }
+    // 未定义的变量保持原样，仅返回其列表
+    pub fn into_unresolved(self) -> Vec<UnresolvedPlaceholder> {
+        self.unresolved
+    }
+    fn render_text(
+        &mut self,
+        field: &str,
+        text: &str,
+        stack: &mut Vec<String>,
+    ) -> Result<String, TemplateError> {
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find(PLACEHOLDER_START) {
+            let end = match rest[start..].find(PLACEHOLDER_END) {
+                Some(end) => start + end,
+                None => break,
+            };
+            result.push_str(&rest[..start]);
+            let placeholder = &rest[start..end + PLACEHOLDER_END.len()];
+            let name = rest[start + PLACEHOLDER_START.len()..end].trim();
+            rest = &rest[end + PLACEHOLDER_END.len()..];
+
+            if !is_variable_name(name) {
+                result.push_str(placeholder);
+                continue;
+            }
+            match self.resolve(field, name, stack)? {
+                Some(value) => result.push_str(&value),
+                None => {
+                    let item = UnresolvedPlaceholder {
+                        name: name.to_string(),
+                        field: field.to_string(),
+                    };
+                    if !self.unresolved.contains(&item) {
+                        self.unresolved.push(item);
+                    }
+                    result.push_str(placeholder);
+                }
+            }
+        }
+        result.push_str(rest);
+        Ok(result)
+    }
+    fn resolve(
+        &mut self,
+        field: &str,
+        name: &str,
+        stack: &mut Vec<String>,
+    ) -> Result<Option<String>, TemplateError> {
+        if let Some(value) = self.resolved.get(name) {
+            return Ok(Some(value.clone()));
+        }
+        if let Some(index) = stack.iter().position(|item| item == name) {
+            let mut chain = stack[index..].to_vec();
+            chain.push(name.to_string());
+            return Err(TemplateError::Cycle {
+                field: field.to_string(),
+                chain,
+            });
+        }
+        let value = match self.context.get(name) {
+            Some(variable) => variable.value.clone(),
+            None => return Ok(None),
+        };
+        let unresolved_count = self.unresolved.len();
+        stack.push(name.to_string());
+        let value = self.render_text(field, &value, stack)?;
+        stack.pop();
+        // 引用了未定义变量的值不缓存，以便记录其它字段中的占位符
+        if self.unresolved.len() == unresolved_count {
+            self.resolved.insert(name.to_string(), value.clone());
+        }
+        Ok(Some(value))
+    }
+}
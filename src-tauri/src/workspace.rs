@@ -0,0 +1,248 @@
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{fs, fs::File, io::BufReader, io::BufWriter, path::Path, path::PathBuf, sync::Mutex};
+
+use crate::error::CyberAPIError;
+use crate::util::get_app_dir;
+use crate::{cookies, file_watcher, schemas};
+
+const WORKSPACE_FILE: &str = "workspaces.json";
+
+// 默认工作区，使用应用目录下的数据库，不可关闭
+pub const DEFAULT_WORKSPACE: &str = "default";
+const DEFAULT_WORKSPACE_NAME: &str = "Default";
+const DEFAULT_DB_FILE: &str = "my_db.db";
+
+static CONFIG: Lazy<Mutex<WorkspaceConfig>> = Lazy::new(|| Mutex::new(load_config()));
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Workspace {
+    // id
+    pub id: String,
+    // 名称
+    pub name: String,
+    // 数据库文件路径
+    pub path: String,
+    // 最近打开时间
+    pub opened_at: String,
+}
+
+impl Workspace {
+    pub fn is_default(&self) -> bool {
+        self.id == DEFAULT_WORKSPACE
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct WorkspaceConfig {
+    // 当前工作区ID
+    current: String,
+    // 最近打开的工作区
+    recent: Vec<Workspace>,
+}
+
+impl WorkspaceConfig {
+    fn find(&self, id: &str) -> Option<&Workspace> {
+        self.recent.iter().find(|item| item.id == id)
+    }
+    fn find_by_path(&self, path: &str) -> Option<&Workspace> {
+        self.recent.iter().find(|item| item.path == path)
+    }
+    fn current(&self) -> Workspace {
+        self.find(&self.current)
+            .cloned()
+            .unwrap_or_else(default_workspace)
+    }
+}
+
+fn default_workspace() -> Workspace {
+    let path = Path::new(get_app_dir()).join(DEFAULT_DB_FILE);
+    Workspace {
+        id: DEFAULT_WORKSPACE.to_string(),
+        name: DEFAULT_WORKSPACE_NAME.to_string(),
+        path: path.to_string_lossy().to_string(),
+        opened_at: "".to_string(),
+    }
+}
+
+fn get_config_file() -> PathBuf {
+    Path::new(get_app_dir()).join(WORKSPACE_FILE)
+}
+
+fn load_config() -> WorkspaceConfig {
+    let mut config: WorkspaceConfig = File::open(get_config_file())
+        .ok()
+        .and_then(|file| serde_json::from_reader(BufReader::new(file)).ok())
+        .unwrap_or_default();
+    // 默认工作区的路径以应用目录为准
+    config.recent.retain(|item| !item.is_default());
+    config.recent.insert(0, default_workspace());
+
+    // 网络盘未挂载等原因导致数据库不可访问时，使用默认工作区启动
+    let current = config.current();
+    if !current.is_default() && !Path::new(&current.path).exists() {
+        config.current = DEFAULT_WORKSPACE.to_string();
+    }
+    if config.current.is_empty() {
+        config.current = DEFAULT_WORKSPACE.to_string();
+    }
+    config
+}
+
+fn save_config(config: &WorkspaceConfig) -> Result<(), CyberAPIError> {
+    fs::create_dir_all(get_app_dir())?;
+    let file = get_config_file();
+    // 先写入临时文件再重命名，避免写入中断导致配置损坏
+    let tmp_file = file.with_extension("json.tmp");
+    {
+        let mut writer = File::create(&tmp_file).map(BufWriter::new)?;
+        serde_json::to_writer_pretty(&mut writer, config)?;
+    }
+    fs::rename(tmp_file, file)?;
+    Ok(())
+}
+
+// 获取当前工作区
+pub fn get_current_workspace() -> Workspace {
+    CONFIG.lock().unwrap().current()
+}
+
+// 获取最近打开的工作区，按打开时间倒序，默认工作区始终在首位
+pub fn list_workspace() -> Vec<Workspace> {
+    let mut result = CONFIG.lock().unwrap().recent.clone();
+    result.sort_by(|a, b| {
+        b.is_default()
+            .cmp(&a.is_default())
+            .then_with(|| b.opened_at.cmp(&a.opened_at))
+    });
+    result
+}
+
+fn new_error(message: String) -> CyberAPIError {
+    CyberAPIError::new(message, "workspace")
+}
+
+// 工作区可位于任意路径（包括网络盘），因此只接受绝对路径
+fn check_path(path: &str) -> Result<(), CyberAPIError> {
+    if path.is_empty() || !Path::new(path).is_absolute() {
+        return Err(new_error(format!(
+            "workspace path should be absolute, {}",
+            path
+        )));
+    }
+    Ok(())
+}
+
+// 当前工作区共用的cookie复制至已有的collection（仅首次执行）
+pub async fn migrate_shared_cookies() -> Result<(), CyberAPIError> {
+    let collections: Vec<String> = schemas::list_api_collection()
+        .await?
+        .into_iter()
+        .map(|item| item.id)
+        .collect();
+    cookies::migrate_shared_cookie_store(&collections)?;
+    Ok(())
+}
+
+// 切换数据库后，cookie、目录监听等均以新的工作区为准
+async fn activate(workspace: Workspace) -> Result<Workspace, CyberAPIError> {
+    file_watcher::unwatch_all();
+    // 新数据库不可用时仍使用原数据库
+    schemas::switch_database(&workspace.path).await?;
+    schemas::clear_file_store_snapshots().await;
+    // 重新记录各目录的同步状态，避免切换后数据库的修改被目录中的旧数据覆盖
+    // 同步失败的目录在开始监听时再提示
+    for store in schemas::list_file_store().await? {
+        let _ = schemas::sync_file_store(&store.collection).await;
+    }
+
+    let mut workspace = workspace;
+    workspace.opened_at = Utc::now().to_rfc3339();
+    {
+        let mut config = CONFIG.lock().unwrap();
+        config.recent.retain(|item| item.id != workspace.id);
+        config.recent.push(workspace.clone());
+        config.current = workspace.id.clone();
+        save_config(&config)?;
+    }
+    migrate_shared_cookies().await?;
+    Ok(workspace)
+}
+
+// 在指定路径创建新的工作区并切换
+pub async fn create_workspace(name: String, path: String) -> Result<Workspace, CyberAPIError> {
+    check_path(&path)?;
+    if Path::new(&path).exists() {
+        return Err(new_error(format!(
+            "workspace file already exists, {}",
+            path
+        )));
+    }
+    if let Some(dir) = Path::new(&path).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    activate(Workspace {
+        id: ulid::Ulid::new().to_string(),
+        name,
+        path,
+        opened_at: "".to_string(),
+    })
+    .await
+}
+
+// 打开已存在的数据库文件作为工作区，如已在最近列表中则复用
+pub async fn open_workspace(path: String) -> Result<Workspace, CyberAPIError> {
+    check_path(&path)?;
+    if !Path::new(&path).is_file() {
+        return Err(new_error(format!("workspace file not found, {}", path)));
+    }
+    let exists = CONFIG.lock().unwrap().find_by_path(&path).cloned();
+    let workspace = exists.unwrap_or_else(|| Workspace {
+        id: ulid::Ulid::new().to_string(),
+        name: Path::new(&path)
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        path,
+        opened_at: "".to_string(),
+    });
+    activate(workspace).await
+}
+
+// 切换至最近打开的工作区
+pub async fn switch_workspace(id: String) -> Result<Workspace, CyberAPIError> {
+    let workspace = CONFIG
+        .lock()
+        .unwrap()
+        .find(&id)
+        .cloned()
+        .ok_or_else(|| new_error(format!("workspace not found, {}", id)))?;
+    if !workspace.is_default() && !Path::new(&workspace.path).is_file() {
+        return Err(new_error(format!(
+            "workspace file not found, {}",
+            workspace.path
+        )));
+    }
+    activate(workspace).await
+}
+
+// 从最近列表中移除工作区（不删除数据库文件），如为当前工作区则切换至默认工作区
+pub async fn close_workspace(id: String) -> Result<Workspace, CyberAPIError> {
+    if id == DEFAULT_WORKSPACE {
+        return Err(new_error("default workspace can not be closed".to_string()));
+    }
+    if get_current_workspace().id == id {
+        activate(default_workspace()).await?;
+    }
+    {
+        let mut config = CONFIG.lock().unwrap();
+        config.recent.retain(|item| item.id != id);
+        save_config(&config)?;
+    }
+    // 工作区的cookie仅保存在本机，关闭后不再使用
+    cookies::remove_cookie_file(&id)?;
+    Ok(get_current_workspace())
+}
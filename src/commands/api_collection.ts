@@ -20,6 +20,8 @@ export interface APICollection {
   name: string;
   // 描述
   description: string;
+  // collection的配置，json格式，如{"retry": {"maxAttempts": 3}}
+  setting?: string;
   // 创建时间
   createdAt: string;
   // 更新时间
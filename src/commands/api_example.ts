@@ -0,0 +1,91 @@
+import dayjs from "dayjs";
+import { ulid } from "ulid";
+
+import { isWebMode } from "../helpers/util";
+import { HTTPResponse } from "./http_response";
+import {
+  run,
+  cmdAddAPIExample,
+  cmdListAPIExample,
+  cmdListCollectionAPIExample,
+  cmdRenameAPIExample,
+  cmdDeleteAPIExample,
+} from "./invoke";
+
+export interface APIExample {
+  [key: string]: unknown;
+  id: string;
+  apiSetting: string;
+  // 名称
+  name: string;
+  // 响应状态码
+  status: number;
+  // 响应头，json格式
+  headers: string;
+  // 响应数据，base64格式
+  body: string;
+  // 创建时间
+  createdAt: string;
+  // 更新时间
+  updatedAt: string;
+}
+
+// 将响应保存为示例
+export function newAPIExampleFromResponse(
+  apiSetting: string,
+  name: string,
+  resp: HTTPResponse,
+): APIExample {
+  return {
+    id: ulid(),
+    apiSetting,
+    name,
+    status: resp.status,
+    headers: JSON.stringify(Object.fromEntries(resp.headers || new Map())),
+    body: resp.body,
+    createdAt: dayjs().format(),
+    updatedAt: dayjs().format(),
+  };
+}
+
+export async function createAPIExample(example: APIExample) {
+  return await run<APIExample>(cmdAddAPIExample, {
+    example,
+  });
+}
+
+export async function listAPIExample(
+  apiSetting: string,
+): Promise<APIExample[]> {
+  if (isWebMode()) {
+    return [];
+  }
+  return await run<APIExample[]>(cmdListAPIExample, {
+    apiSetting,
+  });
+}
+
+// collection下所有API配置的示例
+export async function listCollectionAPIExample(
+  collection: string,
+): Promise<APIExample[]> {
+  if (isWebMode()) {
+    return [];
+  }
+  return await run<APIExample[]>(cmdListCollectionAPIExample, {
+    collection,
+  });
+}
+
+export async function renameAPIExample(id: string, name: string) {
+  return await run<APIExample>(cmdRenameAPIExample, {
+    id,
+    name,
+  });
+}
+
+export async function deleteAPIExample(id: string) {
+  await run(cmdDeleteAPIExample, {
+    id,
+  });
+}
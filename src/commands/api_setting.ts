@@ -8,6 +8,11 @@ import {
   cmdListAPISetting,
   cmdUpdateAPISetting,
   cmdDeleteAPISettings,
+  cmdSearchAPISettings,
+  cmdListAPISettingRevision,
+  cmdDiffAPISettingRevision,
+  cmdRestoreAPISettingRevision,
+  cmdCleanAPISettingRevision,
 } from "./invoke";
 import { fakeList, fakeAdd, fakeUpdate, fakeUpdateStore } from "./fake";
 
@@ -106,3 +111,97 @@ export async function deleteAPISettings(ids: string[]) {
     ids,
   });
 }
+
+export interface SearchResult {
+  [key: string]: unknown;
+  id: string;
+  collection: string;
+  collectionName: string;
+  name: string;
+  // 所在目录路径
+  path: string;
+  // 匹配内容的片段，匹配字符以<mark></mark>标记
+  snippet: string;
+  rank: number;
+}
+
+export async function searchAPISettings(params: {
+  keyword: string;
+  collection?: string;
+  limit?: number;
+}): Promise<SearchResult[]> {
+  if (isWebMode()) {
+    return [];
+  }
+  return await run<SearchResult[]>(cmdSearchAPISettings, {
+    keyword: params.keyword,
+    collection: params.collection,
+    limit: params.limit || 50,
+  });
+}
+
+export interface APISettingRevision {
+  [key: string]: unknown;
+  id: string;
+  apiSetting: string;
+  name: string;
+  category: string;
+  setting: string;
+  // 与下一版本相比修改的字段，以", "分隔
+  summary: string;
+  createdAt: string;
+}
+
+export interface APISettingChange {
+  [key: string]: unknown;
+  // 修改的字段，如name、body、headers.Content-Type
+  field: string;
+  before: unknown;
+  after: unknown;
+}
+
+export async function listAPISettingRevision(
+  apiSetting: string,
+): Promise<APISettingRevision[]> {
+  if (isWebMode()) {
+    return [];
+  }
+  return await run<APISettingRevision[]>(cmdListAPISettingRevision, {
+    apiSetting,
+  });
+}
+
+// 对比两个历史版本，未指定to则与当前配置对比
+export async function diffAPISettingRevision(
+  from: string,
+  to?: string,
+): Promise<APISettingChange[]> {
+  if (isWebMode()) {
+    return [];
+  }
+  return await run<APISettingChange[]>(cmdDiffAPISettingRevision, {
+    from,
+    to,
+  });
+}
+
+export async function restoreAPISettingRevision(
+  id: string,
+): Promise<APISetting> {
+  return await run<APISetting>(cmdRestoreAPISettingRevision, {
+    id,
+  });
+}
+
+export async function cleanAPISettingRevision(
+  maxCount: number,
+  expiredDays: number,
+): Promise<number> {
+  if (isWebMode()) {
+    return 0;
+  }
+  return await run<number>(cmdCleanAPISettingRevision, {
+    maxCount,
+    expiredDays,
+  });
+}
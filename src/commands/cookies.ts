@@ -1,23 +1,62 @@
-import { get, values } from "lodash-es";
 import { isWebMode } from "../helpers/util";
 import {
   cmdAddCookie,
   cmdClearCookie,
   cmdDeleteCookie,
+  cmdExportCookie,
+  cmdImportCookie,
   cmdListCookie,
+  cmdUpdateCookie,
   run,
 } from "./invoke";
 
+// cookie的作用域，collection为空时为工作区共用的cookie
+export interface CookieScope {
+  [key: string]: unknown;
+  collection: string;
+  // 环境，为空则collection的所有环境共用
+  environment: string;
+}
+
+export function newSharedCookieScope(): CookieScope {
+  return {
+    collection: "",
+    environment: "",
+  };
+}
+
+// RFC 6265定义的cookie属性
 export interface Cookie {
   [key: string]: unknown;
   name: string;
   value: string;
   path: string;
   domain: string;
+  // 过期时间，为空则为会话cookie
   expires: string;
+  // 有效时长(秒)，优先于expires
+  maxAge?: number | null;
+  secure?: boolean;
+  httpOnly?: boolean;
+  // Strict、Lax或None，为空则未设置
+  sameSite?: string;
+  // 仅发送至domain对应的host，不包括其子域名
+  hostOnly?: boolean;
+  // 以下字段仅在获取时返回
+  persistent?: boolean;
+  createdAt?: string;
+  lastAccessedAt?: string;
+}
+
+// cookie的标识，用于更新时定位原cookie
+export interface CookieKey {
+  [key: string]: unknown;
+  name: string;
+  domain: string;
+  path: string;
 }
 
-export async function listCookie(): Promise<Cookie[]> {
+export async function listCookie(scope: CookieScope): Promise<Cookie[]> {
   if (isWebMode()) {
     return Promise.resolve([
       {
@@ -36,59 +75,100 @@ export async function listCookie(): Promise<Cookie[]> {
       },
     ]);
   }
-  const arr = await run<string[]>(cmdListCookie, {});
-  if (!arr || !arr.length) {
-    return [];
-  }
-  const cookies: Cookie[] = [];
-  arr.forEach((data) => {
-    const item = JSON.parse(data);
-    const cookie = get(item, "raw_cookie");
-    if (!cookie) {
-      return;
-    }
-    const cookieValues = (cookie as string).split(";");
-    const [name, ...value] = cookieValues[0].split("=");
-    const path = (get(item, "path.0") || "/") as string;
-
-    const domainValues = values(get(item, "domain"));
-    let domain = "";
-    if (domainValues && domainValues.length) {
-      domain = domainValues[0];
-    }
-    const expires = get(item, "expires.AtUtc") as string;
-    cookies.push({
-      name,
-      value: value.join("="),
-      path: path || "",
-      domain: domain || "",
-      expires: expires || "",
-    });
+  const cookies = await run<Cookie[]>(cmdListCookie, {
+    scope,
   });
-  return cookies;
+  return cookies || [];
 }
 
-export async function deleteCookie(c: Cookie) {
+export async function deleteCookie(scope: CookieScope, c: Cookie) {
   if (isWebMode()) {
     return;
   }
   await run(cmdDeleteCookie, {
+    scope,
     c,
   });
 }
 
-export async function clearCookie() {
+export async function clearCookie(scope: CookieScope) {
   if (isWebMode()) {
     return;
   }
-  await run(cmdClearCookie, {});
+  await run(cmdClearCookie, {
+    scope,
+  });
 }
 
-export async function addOrUpdate(c: Cookie) {
+export async function addOrUpdate(scope: CookieScope, c: Cookie) {
   if (isWebMode()) {
     return;
   }
   await run(cmdAddCookie, {
+    scope,
+    c,
+  });
+}
+
+export async function updateCookie(
+  scope: CookieScope,
+  original: CookieKey,
+  c: Cookie,
+) {
+  if (isWebMode()) {
+    return;
+  }
+  await run(cmdUpdateCookie, {
+    scope,
+    original,
     c,
   });
 }
+
+// cookies.txt中无法导入的行
+export interface CookieLineError {
+  line: number;
+  content: string;
+  message: string;
+}
+
+export interface CookieImportResult {
+  imported: number;
+  // 已过期而忽略的数量
+  expired: number;
+  errors: CookieLineError[];
+}
+
+// 导入cookies.txt（Netscape格式）
+export async function importCookie(
+  scope: CookieScope,
+  file: string,
+): Promise<CookieImportResult> {
+  if (isWebMode()) {
+    return {
+      imported: 0,
+      expired: 0,
+      errors: [],
+    };
+  }
+  return await run<CookieImportResult>(cmdImportCookie, {
+    scope,
+    file,
+  });
+}
+
+// 导出为cookies.txt（Netscape格式），domain为空则导出所有cookie
+export async function exportCookie(
+  scope: CookieScope,
+  file: string,
+  domain = "",
+) {
+  if (isWebMode()) {
+    return;
+  }
+  await run(cmdExportCookie, {
+    scope,
+    file,
+    domain,
+  });
+}
@@ -4,12 +4,18 @@ import dayjs from "dayjs";
 import { ulid } from "ulid";
 
 import { isWebMode } from "../helpers/util";
+import { getRevisionRetention, getTrashExpiredDays } from "../stores/setting";
+import { cleanTrash } from "./trash";
+import { cleanAPISettingRevision } from "./api_setting";
+import { watchFileStores } from "./file_store";
+import { getCurrentWorkspace } from "./workspace";
 import {
   cmdAddVersion,
   cmdExportTables,
   cmdGetLatestVersion,
   cmdImportTables,
   cmdInitTables,
+  cmdRepairDatabase,
   run,
 } from "./invoke";
 
@@ -34,6 +40,8 @@ export async function handleDatabaseCompatible() {
     return;
   }
   try {
+    // 记录当前工作区，请求响应等记录按工作区区分
+    await getCurrentWorkspace();
     await run(cmdInitTables);
     const version = await getVersion();
     const latestVersion = await getDatabaseLatestVersion();
@@ -47,6 +55,18 @@ export async function handleDatabaseCompatible() {
         },
       });
     }
+    // 清除回收站中已过期的数据
+    await cleanTrash(await getTrashExpiredDays());
+    // 清除超出保留数量或已过期的历史版本
+    const retention = await getRevisionRetention();
+    await cleanAPISettingRevision(retention.maxCount, retention.expiredDays);
+    // 同步保存至目录的collection
+    const results = await watchFileStores();
+    results.forEach((item) => {
+      if (item.message) {
+        console.error(`sync file store fail, ${item.message}`);
+      }
+    });
     // TODO 后续针对数据库做更新
   } catch (err) {
     if (err instanceof Error) {
@@ -65,3 +85,18 @@ export async function importTables(file: string) {
     file,
   });
 }
+
+export interface DatabaseRepairResult {
+  folders: string[];
+  settings: string[];
+  variables: string[];
+  children: string[];
+}
+
+export async function repairDatabase(
+  dryRun: boolean,
+): Promise<DatabaseRepairResult> {
+  return await run(cmdRepairDatabase, {
+    dryRun,
+  });
+}
@@ -0,0 +1,121 @@
+import { ulid } from "ulid";
+import dayjs from "dayjs";
+import { isWebMode } from "../helpers/util";
+import {
+  cmdActivateEnvironment,
+  cmdAddEnvironment,
+  cmdCloneEnvironment,
+  cmdCompareEnvironment,
+  cmdDeleteEnvironment,
+  cmdGetActiveEnvironment,
+  cmdListEnvironment,
+  cmdUpdateEnvironment,
+  run,
+} from "./invoke";
+
+// collection的环境（如dev、staging、prod），均继承基础环境的变量
+export interface Environment {
+  [key: string]: unknown;
+  id: string;
+  collection: string;
+  // 名称
+  name: string;
+  // 继承的环境，为空则仅继承基础环境
+  base: string;
+  // 是否当前使用的环境(1:是)
+  active: string;
+  // 创建时间
+  createdAt: string;
+  // 更新时间
+  updatedAt: string;
+}
+
+// 两个环境中值不同的变量
+export interface EnvironmentChange {
+  name: string;
+  // 未定义时为null
+  before: string | null;
+  after: string | null;
+}
+
+export function newDefaultEnvironment(): Environment {
+  return {
+    id: ulid(),
+    collection: "",
+    name: "",
+    base: "",
+    active: "",
+    createdAt: dayjs().format(),
+    updatedAt: dayjs().format(),
+  };
+}
+
+export async function createEnvironment(environment: Environment) {
+  return await run<Environment>(cmdAddEnvironment, {
+    environment,
+  });
+}
+
+export async function updateEnvironment(environment: Environment) {
+  return await run<Environment>(cmdUpdateEnvironment, {
+    environment,
+  });
+}
+
+// 删除环境及其变量
+export async function deleteEnvironment(id: string) {
+  await run(cmdDeleteEnvironment, {
+    id,
+  });
+}
+
+export async function listEnvironment(
+  collection: string,
+): Promise<Environment[]> {
+  if (isWebMode()) {
+    return [];
+  }
+  return await run<Environment[]>(cmdListEnvironment, {
+    collection,
+  });
+}
+
+// 复制环境及其变量
+export async function cloneEnvironment(id: string, name: string) {
+  return await run<Environment>(cmdCloneEnvironment, {
+    id,
+    name,
+  });
+}
+
+// 对比两个环境（包括继承的变量），为空则为基础环境
+export async function compareEnvironment(
+  collection: string,
+  from: string,
+  to: string,
+): Promise<EnvironmentChange[]> {
+  return await run<EnvironmentChange[]>(cmdCompareEnvironment, {
+    collection,
+    from: from || null,
+    to: to || null,
+  });
+}
+
+// 设置collection当前使用的环境，为空则仅使用基础环境
+export async function activateEnvironment(collection: string, id: string) {
+  await run(cmdActivateEnvironment, {
+    collection,
+    id: id || null,
+  });
+}
+
+export async function getActiveEnvironment(
+  collection: string,
+): Promise<Environment | null> {
+  if (isWebMode()) {
+    return null;
+  }
+  return await run<Environment | null>(cmdGetActiveEnvironment, {
+    collection,
+  });
+}
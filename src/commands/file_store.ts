@@ -0,0 +1,87 @@
+import { listen } from "@tauri-apps/api/event";
+
+import { isWebMode } from "../helpers/util";
+import { APICollection } from "./api_collection";
+import {
+  cmdLinkFileStore,
+  cmdListFileStore,
+  cmdOpenFileStore,
+  cmdUnlinkFileStore,
+  cmdWatchFileStores,
+  run,
+} from "./invoke";
+
+const fileStoreChangedEvent = "file-store-changed";
+
+export interface FileStore {
+  [key: string]: unknown;
+  collection: string;
+  // 保存的目录
+  dir: string;
+  // 创建时间
+  createdAt: string;
+  // 更新时间
+  updatedAt: string;
+}
+
+export interface FileStoreSyncResult {
+  [key: string]: unknown;
+  collection: string;
+  // 是否有外部修改
+  changed: boolean;
+  // 同步失败时的出错信息
+  message: string;
+}
+
+export async function listFileStore(): Promise<FileStore[]> {
+  if (isWebMode()) {
+    return [];
+  }
+  return await run<FileStore[]>(cmdListFileStore);
+}
+
+// 将collection保存至目录，目录中已有该collection的文件则以文件为准
+export async function linkFileStore(
+  collection: string,
+  dir: string,
+): Promise<FileStore> {
+  return await run<FileStore>(cmdLinkFileStore, {
+    collection,
+    dir,
+  });
+}
+
+// 打开collection目录
+export async function openFileStore(dir: string): Promise<APICollection> {
+  return await run<APICollection>(cmdOpenFileStore, {
+    dir,
+  });
+}
+
+export async function unlinkFileStore(collection: string) {
+  await run(cmdUnlinkFileStore, {
+    collection,
+  });
+}
+
+// 同步应用关闭期间目录的修改，并监听目录
+export async function watchFileStores(): Promise<FileStoreSyncResult[]> {
+  if (isWebMode()) {
+    return [];
+  }
+  return await run<FileStoreSyncResult[]>(cmdWatchFileStores);
+}
+
+// 目录被外部修改并同步后触发
+export async function onFileStoreChanged(
+  ln: (result: FileStoreSyncResult) => void,
+) {
+  if (isWebMode()) {
+    return () => {
+      // 浏览器模式无需处理
+    };
+  }
+  return await listen<FileStoreSyncResult>(fileStoreChangedEvent, (event) => {
+    ln(event.payload);
+  });
+}
@@ -12,7 +12,12 @@ import md5 from "crypto-js/md5";
 
 import { i18nCommon } from "../i18n";
 import { getLatestResponse, getResponseBody } from "./http_response";
-import { listVariable, VariableCategory, VariableStatus } from "./variable";
+import {
+  listVariable,
+  revealVariable,
+  VariableCategory,
+  VariableStatus,
+} from "./variable";
 interface FnHandler {
   collection: string;
   // 原始字符
@@ -194,7 +199,10 @@ export async function doFnHandler(handler: FnHandler): Promise<string> {
             (item) =>
               item.enabled === VariableStatus.Enabled && item.name === name,
           );
-          if (found) {
+          // 仅解密当前使用的加密变量
+          if (found?.secret === VariableStatus.Enabled) {
+            p = await revealVariable(found.id);
+          } else if (found) {
             p = found.value;
           }
         }
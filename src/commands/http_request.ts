@@ -13,6 +13,8 @@ import { isWebMode, delay, formatError } from "../helpers/util";
 import { doFnHandler, parseFunctions } from "./fn";
 import { HTTPResponse, addLatestResponse } from "./http_response";
 import { Cookie } from "./cookies";
+import { VariableScope } from "./variable";
+import { OAuthConfig } from "./oauth";
 import * as mime from "mime";
 
 export enum HTTPMethod {
@@ -40,6 +42,62 @@ export interface RequestTimeout {
   read: number;
 }
 
+export enum CookieJar {
+  // collection的所有环境共用
+  Collection = "collection",
+  // collection的各环境分别使用
+  Environment = "environment",
+  // 不发送也不保存cookie
+  Disabled = "disabled",
+}
+
+// 重试策略，未设置时使用collection的配置
+export interface RetryPolicy {
+  // 最大尝试次数（包括首次请求），小于等于1则不重试
+  maxAttempts: number;
+  // 需要重试的出错代码，如connect_refused、read_timeout，为空则按出错是否可重试判断
+  errorCodes?: string[];
+  // 需要重试的响应状态码，默认为429、502、503、504
+  statuses?: number[];
+  // 首次重试前的等待时长(ms)，之后每次翻倍
+  baseDelay?: number;
+  // 最大等待时长(ms)
+  maxDelay?: number;
+  // 是否按响应的Retry-After等待，默认为true
+  respectRetryAfter?: boolean;
+  // 非幂等的请求（如POST）在请求已发出后出错或返回重试状态码时是否重试
+  nonIdempotent?: boolean;
+}
+
+// 认证方式，由后端在url与body确定后生成认证的请求头
+export type HTTPAuth =
+  | { type: "basic"; username: string; password: string }
+  | { type: "bearer"; token: string }
+  // 首次请求返回401后根据WWW-Authenticate重新发送
+  | { type: "digest"; username: string; password: string }
+  | {
+      type: "awsSigV4";
+      accessKey: string;
+      secretKey: string;
+      sessionToken?: string;
+      region: string;
+      service: string;
+    }
+  | {
+      // 签名的内容为：METHOD\npath?query\ntimestamp\nhex(sha256(body))
+      type: "hmac";
+      secret: string;
+      keyId?: string;
+      algorithm?: "sha256" | "sha512";
+      encoding?: "hex" | "base64";
+      // 签名的请求头，默认为X-Signature
+      header?: string;
+      // 时间戳的请求头，默认为X-Timestamp，为空则不添加，签名中也不包含时间戳
+      timestampHeader?: string;
+    }
+  // 使用获取的access token作为Bearer认证，token按collection与环境缓存
+  | ({ type: "oauth2" } & OAuthConfig);
+
 export interface HTTPRequest {
   [key: string]: unknown;
   method: string;
@@ -49,8 +107,80 @@ export interface HTTPRequest {
   headers: KVParam[];
   query: KVParam[];
   auth: KVParam[];
+  // 仅用于该请求的变量，优先级高于环境变量
+  variables?: KVParam[];
+  // 响应后提取值保存为变量
+  extractions?: ExtractionRule[];
+  // 响应的断言
+  assertions?: AssertionRule[];
+  // 使用的cookie，默认为collection共用
+  cookieJar?: CookieJar;
+  retry?: RetryPolicy;
+  // 认证配置，未设置则使用auth中的Basic认证
+  authConfig?: HTTPAuth;
+}
+
+// 提取值的来源
+export enum ExtractionSource {
+  // 响应数据的JSONPath，如$.data.token
+  JsonPath = "jsonPath",
+  Header = "header",
+  Cookie = "cookie",
+  // 正则匹配响应数据，有分组时取第一个分组
+  Regex = "regex",
+}
+
+export interface ExtractionRule {
+  source: ExtractionSource;
+  // JSONPath、响应头名称、cookie名称或正则表达式
+  expression: string;
+  // 保存的变量名称
+  name: string;
+  // 保存的作用域，local则不保存
+  scope: VariableScope;
+  enabled: boolean;
+}
+
+// 断言的对象
+export enum AssertionTarget {
+  Status = "status",
+  // property为响应头名称
+  Header = "header",
+  // property为JSONPath
+  JsonPath = "jsonPath",
+  Body = "body",
+  // expected为JSON Schema
+  JsonSchema = "jsonSchema",
+  // 耗时(ms)
+  Latency = "latency",
+}
+
+export enum AssertionOperator {
+  Equal = "equal",
+  NotEqual = "notEqual",
+  GreaterThan = "greaterThan",
+  GreaterOrEqual = "greaterOrEqual",
+  LessThan = "lessThan",
+  LessOrEqual = "lessOrEqual",
+  // expected为min,max
+  Between = "between",
+  Contains = "contains",
+  NotContains = "notContains",
+  Matches = "matches",
+  Exists = "exists",
+  NotExists = "notExists",
+}
+
+export interface AssertionRule {
+  target: AssertionTarget;
+  // 响应头名称或JSONPath
+  property: string;
+  operator: AssertionOperator;
+  expected: string;
+  enabled: boolean;
 }
 
+
 function convertKVListToURLValues(kvList: KVParam[]) {
   if (!kvList || kvList.length === 0) {
     return [];
@@ -141,6 +271,22 @@ function is_json(str: string) {
   return key === "{}" || key === "[]";
 }
 
+// 未设置认证配置时，使用auth中第一个启用的Basic认证
+function getRequestAuth(req: HTTPRequest): HTTPAuth | undefined {
+  if (req.authConfig) {
+    return req.authConfig;
+  }
+  const auth = req.auth.find((item) => item.enabled);
+  if (!auth) {
+    return;
+  }
+  return {
+    type: "basic",
+    username: auth.key,
+    password: auth.value,
+  };
+}
+
 async function convertBody(collection: string, data: string) {
   let body = data;
   // 注释的处理
@@ -309,6 +455,10 @@ export async function doHTTPRequest(options: {
     contentType,
     headers: req.headers,
     query: req.query,
+    variables: req.variables || [],
+    cookieJar: req.cookieJar || CookieJar.Collection,
+    retry: req.retry,
+    auth: getRequestAuth(req),
   };
   await convertKVParams(collection, params.query);
   await convertKVParams(collection, params.headers);
@@ -354,16 +504,6 @@ export async function doHTTPRequest(options: {
     enabled: true,
   });
 
-  const auth = req.auth.filter((item) => item.enabled);
-  if (auth.length) {
-    const value = encode(`${auth[0].key}:${auth[0].value}`);
-    params.headers.push({
-      key: "Authorization",
-      value: `Basic ${value}`,
-      enabled: true,
-    });
-  }
-
   const requestTimeout = {
     connect: 10,
     write: 120,
@@ -4,7 +4,12 @@ import { forEach } from "lodash-es";
 import mitt, { Emitter } from "mitt";
 
 import { getLatestResponseStore } from "../stores/local";
-import { HTTPRequest } from "./http_request";
+import {
+  AssertionOperator,
+  AssertionTarget,
+  HTTPRequest,
+} from "./http_request";
+import { VariableScope } from "./variable";
 import { ulid } from "ulid";
 
 const applicationJSON = "application/json";
@@ -37,6 +42,49 @@ export interface HTTPResponse {
   headers: Map<string, string[]>;
   body: string;
   stats: HTTPStats;
+  // 从响应中提取并保存的变量
+  extractions?: ExtractionResult[];
+  // 断言的结果
+  assertions?: AssertionResult[];
+  // 按重试策略发送时每次请求的结果
+  attempts?: RetryAttempt[];
+}
+
+export interface RetryAttempt {
+  // 第几次请求，从1开始
+  attempt: number;
+  startedAt: string;
+  // 请求耗时(ms)
+  latency: number;
+  status?: number;
+  error?: {
+    message: string;
+    category: string;
+    code: string;
+  };
+  // 重试前的等待时长(ms)，不再重试则为0
+  delay: number;
+}
+
+export interface AssertionResult {
+  target: AssertionTarget;
+  property: string;
+  operator: AssertionOperator;
+  expected: string;
+  // 实际的值，不存在时为null
+  actual: string | null;
+  passed: boolean;
+  // 未通过的原因
+  message: string;
+}
+
+export interface ExtractionResult {
+  name: string;
+  scope: VariableScope;
+  // 提取失败时为null，加密变量为掩码
+  value: string | null;
+  // 失败时的出错信息
+  message: string;
 }
 
 const selectEvent = "select";
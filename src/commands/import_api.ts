@@ -3,6 +3,7 @@ import { get, uniq, forEach, has } from "lodash-es";
 import dayjs from "dayjs";
 import { ulid } from "ulid";
 import { SettingType } from "../stores/api_setting";
+import { APIExample, createAPIExample } from "./api_example";
 import { APIFolder, createAPIFolder, newDefaultAPIFolder } from "./api_folder";
 import {
   APISetting,
@@ -67,6 +68,7 @@ interface InsomniaSetting {
 interface ImportData {
   settings: APISetting[];
   folders: APIFolder[];
+  examples: APIExample[];
 }
 
 export enum ImportCategory {
@@ -357,6 +359,7 @@ export async function importAPI(params: {
   const result: ImportData = {
     settings: [],
     folders: [],
+    examples: [],
   };
   if (params.fileData.startsWith("curl")) {
     const req = parseCurl(params.fileData);
@@ -462,6 +465,11 @@ export async function importAPI(params: {
       {
         const arr = Array.isArray(json) ? json : [json];
         arr.forEach((item) => {
+          // 示例关联API配置，无collection字段
+          if (item.apiSetting) {
+            result.examples.push(item);
+            return;
+          }
           item.collection = collection;
           if (item.category === SettingType.HTTP) {
             result.settings.push(item);
@@ -491,11 +499,25 @@ export async function importAPI(params: {
     }
     await createAPIFolder(item);
   });
+  // id冲突时API配置会使用新的id，示例需要对应修改
+  const settingIDMap: Map<string, string> = new Map();
   await Promise.each(result.settings, async (item) => {
     if (!childrenIDMap.has(item.id)) {
       topIDList.push(item.id);
     }
+    const id = item.id;
     await createAPISetting(item);
+    settingIDMap.set(id, item.id);
+  });
+  await Promise.each(result.examples, async (item) => {
+    const apiSetting = settingIDMap.get(item.apiSetting);
+    // 忽略API配置不在导入数据中的示例
+    if (!apiSetting) {
+      return;
+    }
+    item.id = ulid();
+    item.apiSetting = apiSetting;
+    await createAPIExample(item);
   });
   await Promise.each(environments, async (item) => {
     if (!item.name && !item.value) {
@@ -5,11 +5,29 @@ import { isWebMode } from "../helpers/util";
 export const cmdInitTables = "init_tables";
 export const cmdExportTables = "export_tables";
 export const cmdImportTables = "import_tables";
+export const cmdRepairDatabase = "repair_database";
+
+export const cmdListWorkspace = "list_workspace";
+export const cmdGetCurrentWorkspace = "get_current_workspace";
+export const cmdCreateWorkspace = "create_workspace";
+export const cmdOpenWorkspace = "open_workspace";
+export const cmdSwitchWorkspace = "switch_workspace";
+export const cmdCloseWorkspace = "close_workspace";
 
 export const cmdAddAPISetting = "add_api_setting";
 export const cmdUpdateAPISetting = "update_api_setting";
 export const cmdListAPISetting = "list_api_setting";
 export const cmdDeleteAPISettings = "delete_api_settings";
+export const cmdSearchAPISettings = "search_api_settings";
+export const cmdListAPISettingRevision = "list_api_setting_revision";
+export const cmdDiffAPISettingRevision = "diff_api_setting_revision";
+export const cmdRestoreAPISettingRevision = "restore_api_setting_revision";
+export const cmdCleanAPISettingRevision = "clean_api_setting_revision";
+export const cmdAddAPIExample = "add_api_example";
+export const cmdListAPIExample = "list_api_example";
+export const cmdListCollectionAPIExample = "list_collection_api_example";
+export const cmdRenameAPIExample = "rename_api_example";
+export const cmdDeleteAPIExample = "delete_api_example";
 
 export const cmdAddAPIFolder = "add_api_folder";
 export const cmdListAPIFolder = "list_api_folder";
@@ -21,22 +39,79 @@ export const cmdUpdateAPICollection = "update_api_collection";
 export const cmdListAPICollection = "list_api_collection";
 export const cmdDeleteAPICollection = "delete_api_collection";
 
+export const cmdListFileStore = "list_file_store";
+export const cmdLinkFileStore = "link_file_store";
+export const cmdOpenFileStore = "open_file_store";
+export const cmdUnlinkFileStore = "unlink_file_store";
+export const cmdWatchFileStores = "watch_file_stores";
+
+export const cmdListTrash = "list_trash";
+export const cmdRestoreTrash = "restore_trash";
+export const cmdPurgeTrash = "purge_trash";
+export const cmdCleanTrash = "clean_trash";
+
 export const cmdDoHTTPRequest = "do_http_request";
+export const cmdRunCollection = "run_collection";
+export const cmdSaveRunnerReport = "save_runner_report";
+export const cmdLoadTest = "load_test";
+export const cmdStopLoadTest = "stop_load_test";
+export const cmdStartMockServer = "start_mock_server";
+export const cmdStopMockServer = "stop_mock_server";
+export const cmdGetMockServerStatus = "get_mock_server_status";
+export const cmdListMockRequests = "list_mock_requests";
+export const cmdClearMockRequests = "clear_mock_requests";
 
 export const cmdListCookie = "list_cookie";
 export const cmdDeleteCookie = "delete_cookie";
 export const cmdAddCookie = "add_cookie";
+export const cmdUpdateCookie = "update_cookie";
+export const cmdImportCookie = "import_cookie";
+export const cmdExportCookie = "export_cookie";
 export const cmdClearCookie = "clear_cookie";
 
+export const cmdGetOAuthToken = "get_oauth_token";
+export const cmdDeleteOAuthToken = "delete_oauth_token";
+
 export const cmdAddVariable = "add_variable";
 export const cmdUpdateVariable = "update_variable";
 export const cmdDeleteVariable = "delete_variable";
 export const cmdListVariable = "list_variable";
+export const cmdRevealVariable = "reveal_variable";
+export const cmdAddEnvironment = "add_environment";
+export const cmdUpdateEnvironment = "update_environment";
+export const cmdDeleteEnvironment = "delete_environment";
+export const cmdListEnvironment = "list_environment";
+export const cmdCloneEnvironment = "clone_environment";
+export const cmdCompareEnvironment = "compare_environment";
+export const cmdActivateEnvironment = "activate_environment";
+export const cmdGetActiveEnvironment = "get_active_environment";
+export const cmdAddGlobalVariable = "add_global_variable";
+export const cmdUpdateGlobalVariable = "update_global_variable";
+export const cmdDeleteGlobalVariable = "delete_global_variable";
+export const cmdListGlobalVariable = "list_global_variable";
+export const cmdResolveTemplate = "resolve_template";
+export const cmdListResolvedVariable = "list_resolved_variable";
+export const cmdGetSecretStatus = "get_secret_status";
+export const cmdInitSecretKey = "init_secret_key";
+export const cmdUnlockSecret = "unlock_secret";
+export const cmdLockSecret = "lock_secret";
 
 export const cmdGetLatestVersion = "get_latest_version";
 export const cmdAddVersion = "add_version";
 
 const debug = Debug("invoke");
+
+// 命令出错时返回的信息，code、phase与retryable用于区分网络类的出错
+export type CommandError = Error & {
+  code?: string;
+  // 底层的出错信息，由外至内
+  causes?: string[];
+  // 请求出错时所处的阶段，如dns、connect、tls、send等
+  phase?: string;
+  retryable?: boolean;
+  details?: unknown;
+};
+
 export async function run<T>(cmd: string, args?: InvokeArgs): Promise<T> {
   if (isWebMode()) {
     debug("invoke, cmd:%s, args:%o", cmd, args);
@@ -52,6 +127,15 @@ export async function run<T>(cmd: string, args?: InvokeArgs): Promise<T> {
     // eslint-disable-next-line
     // @ts-ignore: mock
     const message = `[${err.category}]${err.message}`;
-    throw new Error(message);
+    const e = new Error(message) as CommandError;
+    // eslint-disable-next-line
+    // @ts-ignore: mock
+    const { code, causes, phase, retryable, details } = err;
+    e.code = code;
+    e.causes = causes;
+    e.phase = phase;
+    e.retryable = retryable;
+    e.details = details;
+    throw e;
   }
 }
@@ -0,0 +1,121 @@
+import { listen } from "@tauri-apps/api/event";
+
+import { isWebMode } from "../helpers/util";
+import { HTTPRequest, RequestTimeout } from "./http_request";
+import { cmdLoadTest, cmdStopLoadTest, run } from "./invoke";
+
+const loadTestProgressEvent = "load-test-progress";
+
+export interface LoadTestOptions {
+  [key: string]: unknown;
+  // 并发数
+  concurrency: number;
+  // 每秒请求数，为0则不限制
+  rps?: number;
+  // 压测时长(秒)，不包括预热时长
+  duration?: number;
+  // 请求总数，不包括预热时的请求
+  count?: number;
+  // 预热时长(秒)，预热时的请求不统计
+  warmUp?: number;
+}
+
+// 耗时的统计(ms)
+export interface LatencySummary {
+  min: number;
+  mean: number;
+  p50: number;
+  p90: number;
+  p99: number;
+  max: number;
+}
+
+export interface LoadTestPhases {
+  dnsLookup: LatencySummary;
+  tcp: LatencySummary;
+  tls: LatencySummary;
+  send: LatencySummary;
+  serverProcessing: LatencySummary;
+  contentTransfer: LatencySummary;
+}
+
+export interface LoadTestErrorGroup {
+  count: number;
+  // 首个出错信息
+  message: string;
+}
+
+export interface LoadTestProgress {
+  id: string;
+  // 是否预热中
+  warmingUp: boolean;
+  // 已耗时(ms)
+  elapsed: number;
+  // 已完成（不包括预热）的请求数
+  completed: number;
+  failed: number;
+  // 最近一次推送后的每秒请求数
+  rps: number;
+  // 最近一次推送后的平均耗时(ms)
+  latency: number;
+}
+
+export interface LoadTestReport {
+  [key: string]: unknown;
+  id: string;
+  api: string;
+  concurrency: number;
+  targetRps: number;
+  startedAt: string;
+  finishedAt: string;
+  // 不包括预热的耗时(ms)
+  duration: number;
+  // 手动停止
+  stopped: boolean;
+  total: number;
+  succeeded: number;
+  failed: number;
+  rps: number;
+  latency: LatencySummary;
+  phases: LoadTestPhases;
+  // 各状态码的请求数
+  statuses: Record<string, number>;
+  // 按出错类别分组
+  errors: Record<string, LoadTestErrorGroup>;
+}
+
+// req为doHTTPRequest发送的请求参数
+export async function loadTest(
+  api: string,
+  req: HTTPRequest,
+  timeout: RequestTimeout,
+  options: LoadTestOptions,
+) {
+  return await run<LoadTestReport>(cmdLoadTest, {
+    api,
+    req,
+    timeout,
+    options,
+  });
+}
+
+// id为进度事件中压测的id
+export async function stopLoadTest(id: string) {
+  await run(cmdStopLoadTest, {
+    id,
+  });
+}
+
+// 压测过程中定时触发
+export async function onLoadTestProgress(
+  ln: (progress: LoadTestProgress) => void,
+) {
+  if (isWebMode()) {
+    return () => {
+      // 浏览器模式无需处理
+    };
+  }
+  return await listen<LoadTestProgress>(loadTestProgressEvent, (event) => {
+    ln(event.payload);
+  });
+}
@@ -0,0 +1,93 @@
+import { listen } from "@tauri-apps/api/event";
+
+import { isWebMode } from "../helpers/util";
+import { KVParam } from "./interface";
+import {
+  cmdClearMockRequests,
+  cmdGetMockServerStatus,
+  cmdListMockRequests,
+  cmdStartMockServer,
+  cmdStopMockServer,
+  run,
+} from "./invoke";
+
+const mockRequestEvent = "mock-server-request";
+
+// 保存在API配置中的mock响应
+export interface MockSetting {
+  enabled?: boolean;
+  // 路由路径，如/users/:id，为空则使用请求uri的路径
+  path?: string;
+  // 使用的示例ID，为空则使用以下配置的响应
+  example?: string;
+  // 状态码，为0则使用示例的状态码（无示例时为200）
+  status?: number;
+  // 延时(ms)
+  delay?: number;
+  contentType?: string;
+  headers?: KVParam[];
+  // 响应数据，支持{{params.id}}、{{query.name}}、{{headers.name}}
+  // 以及{{request.body}}等占位符，为空则使用示例的响应数据
+  body?: string;
+}
+
+export interface MockServerStatus {
+  running: boolean;
+  collection: string;
+  port: number;
+  startedAt: string;
+}
+
+export interface MockRequestLog {
+  id: string;
+  method: string;
+  path: string;
+  query: string;
+  headers: [string, string][];
+  body: string;
+  // 匹配的API，未匹配时为空
+  api: string;
+  status: number;
+  // 延时(ms)
+  delay: number;
+  // 处理耗时(ms)，包括延时
+  duration: number;
+  createdAt: string;
+}
+
+// 端口为0时随机选择
+export async function startMockServer(collection: string, port: number) {
+  return await run<MockServerStatus>(cmdStartMockServer, {
+    collection,
+    port,
+  });
+}
+
+export async function stopMockServer() {
+  await run(cmdStopMockServer);
+}
+
+export async function getMockServerStatus() {
+  return await run<MockServerStatus>(cmdGetMockServerStatus);
+}
+
+// 最近接收到的请求，按时间倒序
+export async function listMockRequests() {
+  return await run<MockRequestLog[]>(cmdListMockRequests);
+}
+
+export async function clearMockRequests() {
+  await run(cmdClearMockRequests);
+}
+
+// mock服务接收到请求后触发
+export async function onMockRequest(ln: (log: MockRequestLog) => void) {
+  if (isWebMode()) {
+    return () => {
+      // 浏览器模式无需处理
+    };
+  }
+  return await listen<MockRequestLog>(mockRequestEvent, (event) => {
+    ln(event.payload);
+  });
+}
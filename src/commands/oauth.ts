@@ -0,0 +1,86 @@
+import { isWebMode } from "../helpers/util";
+import { cmdDeleteOAuthToken, cmdGetOAuthToken, run } from "./invoke";
+
+export enum OAuthGrantType {
+  ClientCredentials = "clientCredentials",
+  // resource owner password
+  Password = "password",
+  // 使用PKCE，授权后回调至本机监听的地址
+  AuthorizationCode = "authorizationCode",
+}
+
+// client的认证方式
+export enum ClientAuthentication {
+  // 使用Basic认证的请求头
+  Basic = "basic",
+  // client_id与client_secret在请求数据中
+  Body = "body",
+}
+
+export interface OAuthConfig {
+  grantType?: OAuthGrantType;
+  tokenUrl: string;
+  // 授权码模式的授权地址
+  authorizeUrl?: string;
+  clientId: string;
+  // 为空则为public client
+  clientSecret?: string;
+  scope?: string;
+  audience?: string;
+  username?: string;
+  password?: string;
+  // 授权码回调监听的端口，为0时随机选择
+  redirectPort?: number;
+  clientAuthentication?: ClientAuthentication;
+}
+
+export interface OAuthToken {
+  [key: string]: unknown;
+  id: string;
+  collection: string;
+  environment: string;
+  accessToken: string;
+  tokenType: string;
+  refreshToken: string;
+  scope: string;
+  // 过期时间，为空表示未返回有效期
+  expiresAt: string;
+  createdAt: string;
+  updatedAt: string;
+}
+
+// 获取collection当前环境的token，已缓存且未过期则直接返回
+// token加密缓存，变量的密钥未解锁时不缓存
+export async function getOAuthToken(
+  collection: string,
+  config: OAuthConfig,
+): Promise<OAuthToken> {
+  if (isWebMode()) {
+    return Promise.resolve({
+      id: "",
+      collection,
+      environment: "",
+      accessToken: "",
+      tokenType: "bearer",
+      refreshToken: "",
+      scope: config.scope || "",
+      expiresAt: "",
+      createdAt: "",
+      updatedAt: "",
+    });
+  }
+  return await run<OAuthToken>(cmdGetOAuthToken, {
+    collection,
+    config,
+  });
+}
+
+// 删除collection缓存的token，下次请求时重新获取
+export async function deleteOAuthToken(collection: string) {
+  if (isWebMode()) {
+    return 0;
+  }
+  return await run<number>(cmdDeleteOAuthToken, {
+    collection,
+  });
+}
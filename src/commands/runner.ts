@@ -0,0 +1,126 @@
+import { listen } from "@tauri-apps/api/event";
+
+import { isWebMode } from "../helpers/util";
+import { RequestTimeout } from "./http_request";
+import { AssertionResult, ExtractionResult } from "./http_response";
+import { cmdRunCollection, cmdSaveRunnerReport, run } from "./invoke";
+
+const runnerProgressEvent = "runner-progress";
+
+export interface RunnerOptions {
+  [key: string]: unknown;
+  collection: string;
+  // 执行的目录，为空则执行整个collection
+  folder?: string;
+  // 顶层目录与API的顺序
+  topTreeItems?: string[];
+  // 执行的轮数，指定数据文件时按数据的行数执行
+  iterations?: number;
+  // 每个请求之间的间隔(ms)
+  delay?: number;
+  // 失败时停止执行
+  stopOnFailure?: boolean;
+  timeout?: RequestTimeout;
+  // 数据文件(csv或json)，每行的值作为该轮的变量
+  dataFile?: string;
+  // 仅执行数据文件中的这些行（从1开始）
+  rows?: number[];
+}
+
+export interface RunnerStep {
+  // 第几轮，从1开始
+  iteration: number;
+  // 在该轮中的序号，从1开始
+  index: number;
+  // 数据文件的行号，从1开始，无数据文件时为0
+  row: number;
+  // 该行的数据
+  data: Record<string, string>;
+  api: string;
+  name: string;
+  method: string;
+  uri: string;
+  // 请求失败时为0
+  status: number;
+  // 耗时(ms)
+  duration: number;
+  bodySize: number;
+  passed: boolean;
+  // 请求的出错信息
+  error: string;
+  extractions: ExtractionResult[];
+  assertions: AssertionResult[];
+}
+
+export interface RunnerProgress {
+  id: string;
+  // 已完成的请求数
+  completed: number;
+  // 所有轮次的请求总数
+  total: number;
+  step: RunnerStep;
+}
+
+export interface RunnerReport {
+  [key: string]: unknown;
+  id: string;
+  collection: string;
+  folder: string;
+  dataFile: string;
+  // 数据文件的列
+  columns: string[];
+  iterations: number;
+  total: number;
+  passed: number;
+  failed: number;
+  // 因失败而提前停止
+  stopped: boolean;
+  // 执行失败的数据行
+  failedRows: number[];
+  // 总耗时(ms)
+  duration: number;
+  startedAt: string;
+  finishedAt: string;
+  steps: RunnerStep[];
+}
+
+export async function runCollection(options: RunnerOptions) {
+  return await run<RunnerReport>(cmdRunCollection, {
+    options,
+  });
+}
+
+// 使用相同的数据文件仅重新执行失败的行，无失败的行时直接返回原报告
+export async function rerunFailedRows(
+  options: RunnerOptions,
+  report: RunnerReport,
+) {
+  if (!report.dataFile || report.failedRows.length === 0) {
+    return report;
+  }
+  return await runCollection(
+    Object.assign({}, options, {
+      dataFile: report.dataFile,
+      rows: report.failedRows,
+    }),
+  );
+}
+
+export async function saveRunnerReport(file: string, report: RunnerReport) {
+  await run(cmdSaveRunnerReport, {
+    file,
+    report,
+  });
+}
+
+// 每个请求执行完成后触发
+export async function onRunnerProgress(ln: (progress: RunnerProgress) => void) {
+  if (isWebMode()) {
+    return () => {
+      // 浏览器模式无需处理
+    };
+  }
+  return await listen<RunnerProgress>(runnerProgressEvent, (event) => {
+    ln(event.payload);
+  });
+}
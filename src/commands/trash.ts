@@ -0,0 +1,59 @@
+import { isWebMode } from "../helpers/util";
+import {
+  cmdCleanTrash,
+  cmdListTrash,
+  cmdPurgeTrash,
+  cmdRestoreTrash,
+  run,
+} from "./invoke";
+
+export enum TrashCategory {
+  Collection = "collection",
+  Folder = "folder",
+  Setting = "setting",
+}
+
+export interface TrashItem {
+  [key: string]: unknown;
+  id: string;
+  category: TrashCategory;
+  collection: string;
+  // 名称
+  name: string;
+  // 删除时间
+  deletedAt: string;
+}
+
+export async function listTrash(): Promise<TrashItem[]> {
+  if (isWebMode()) {
+    return [];
+  }
+  return await run<TrashItem[]>(cmdListTrash);
+}
+
+export async function restoreTrash(ids: string[]) {
+  if (isWebMode()) {
+    return;
+  }
+  await run(cmdRestoreTrash, {
+    ids,
+  });
+}
+
+export async function purgeTrash(ids: string[]) {
+  if (isWebMode()) {
+    return;
+  }
+  await run(cmdPurgeTrash, {
+    ids,
+  });
+}
+
+export async function cleanTrash(expiredDays: number): Promise<TrashItem[]> {
+  if (isWebMode()) {
+    return [];
+  }
+  return await run<TrashItem[]>(cmdCleanTrash, {
+    expiredDays,
+  });
+}
@@ -1,10 +1,22 @@
 import { ulid } from "ulid";
 import dayjs from "dayjs";
 import { isWebMode } from "../helpers/util";
+import { KVParam } from "./interface";
 import {
+  cmdAddGlobalVariable,
   cmdAddVariable,
+  cmdDeleteGlobalVariable,
   cmdDeleteVariable,
+  cmdGetSecretStatus,
+  cmdInitSecretKey,
+  cmdListGlobalVariable,
+  cmdListResolvedVariable,
   cmdListVariable,
+  cmdLockSecret,
+  cmdResolveTemplate,
+  cmdRevealVariable,
+  cmdUnlockSecret,
+  cmdUpdateGlobalVariable,
   cmdUpdateVariable,
   run,
 } from "./invoke";
@@ -17,6 +29,9 @@ export enum VariableStatus {
   Disabled = "0",
 }
 
+// 加密变量在列表中展示的值，更新时保持该值则不修改
+export const secretMask = "******";
+
 export enum VariableCategory {
   // 环境变量
   Environment = "env",
@@ -31,12 +46,16 @@ export interface Variable {
   id: string;
   category: string;
   collection: string;
+  // 所属环境，为空则为基础环境
+  environment?: string;
   // 名称
   name: string;
   // 值
   value: string;
   // 是否启用(0:禁用 1:启用)
   enabled: string;
+  // 是否加密保存(0:否 1:是)
+  secret: string;
   // 创建时间
   createdAt: string;
   // 更新时间
@@ -49,9 +68,11 @@ export function newDefaultVariable(): Variable {
     id,
     category: "",
     collection: "",
+    environment: "",
     name: "",
     value: "",
     enabled: VariableStatus.Enabled,
+    secret: VariableStatus.Disabled,
     createdAt: dayjs().format(),
     updatedAt: dayjs().format(),
   };
@@ -66,9 +87,13 @@ export async function createVariable(value: Variable) {
   });
 }
 
+// 加密变量的值默认以掩码返回，reveal为true时返回解密后的值
+// environment为空时返回基础环境的变量
 export async function listVariable(
   collection: string,
   category: string,
+  reveal = false,
+  environment = "",
 ): Promise<Variable[]> {
   if (isWebMode()) {
     return await fakeList<Variable>(store);
@@ -76,6 +101,19 @@ export async function listVariable(
   return await run<Variable[]>(cmdListVariable, {
     collection,
     category,
+    environment,
+    reveal,
+  });
+}
+
+// 获取单个变量的值，加密变量则为解密后的值
+export async function revealVariable(id: string) {
+  if (isWebMode()) {
+    const arr = await fakeList<Variable>(store);
+    return arr.find((item) => item.id === id)?.value || "";
+  }
+  return await run<string>(cmdRevealVariable, {
+    id,
   });
 }
 
@@ -101,3 +139,140 @@ export async function deleteVariable(ids: string[]) {
     ids,
   });
 }
+
+// 全局变量，所有collection均可使用
+export type GlobalVariable = Omit<Variable, "category" | "collection">;
+
+export function newDefaultGlobalVariable(): GlobalVariable {
+  const { id, name, value, enabled, secret, createdAt, updatedAt } =
+    newDefaultVariable();
+  return {
+    id,
+    name,
+    value,
+    enabled,
+    secret,
+    createdAt,
+    updatedAt,
+  };
+}
+
+export async function createGlobalVariable(value: GlobalVariable) {
+  await run(cmdAddGlobalVariable, {
+    value,
+  });
+}
+
+export async function listGlobalVariable(
+  reveal = false,
+): Promise<GlobalVariable[]> {
+  if (isWebMode()) {
+    return [];
+  }
+  return await run<GlobalVariable[]>(cmdListGlobalVariable, {
+    reveal,
+  });
+}
+
+export async function updateGlobalVariable(value: GlobalVariable) {
+  await run(cmdUpdateGlobalVariable, {
+    value,
+  });
+}
+
+export async function deleteGlobalVariable(ids: string[]) {
+  await run(cmdDeleteGlobalVariable, {
+    ids,
+  });
+}
+
+// 变量的作用域，优先级从低到高
+export enum VariableScope {
+  Global = "global",
+  Collection = "collection",
+  Environment = "environment",
+  Local = "local",
+}
+
+export interface ResolvedVariable {
+  name: string;
+  value: string;
+  scope: VariableScope;
+}
+
+export interface UnresolvedPlaceholder {
+  name: string;
+  // 占位符所在的字段，如uri、headers.Authorization
+  field: string;
+}
+
+export interface TemplatePreview {
+  value: string;
+  // 未定义的变量，占位符保持原样
+  unresolved: UnresolvedPlaceholder[];
+}
+
+// 预览模板中{{name}}替换后的结果，未定义的变量保持原样，循环引用时出错，
+// 错误的details中包含相关的变量
+export async function resolveTemplate(
+  collection: string,
+  template: string,
+  variables: KVParam[] = [],
+): Promise<TemplatePreview> {
+  return await run<TemplatePreview>(cmdResolveTemplate, {
+    collection,
+    template,
+    variables,
+  });
+}
+
+export async function listResolvedVariable(
+  collection: string,
+  variables: KVParam[] = [],
+): Promise<ResolvedVariable[]> {
+  return await run<ResolvedVariable[]>(cmdListResolvedVariable, {
+    collection,
+    variables,
+  });
+}
+
+export enum SecretCategory {
+  // 由密码生成密钥
+  Passphrase = "passphrase",
+  // 本机的密钥文件
+  KeyFile = "keyFile",
+}
+
+export interface SecretStatus {
+  // 密钥类型，未初始化则为空
+  category: string;
+  // 是否已解锁
+  unlocked: boolean;
+}
+
+export async function getSecretStatus(): Promise<SecretStatus> {
+  if (isWebMode()) {
+    return {
+      category: "",
+      unlocked: false,
+    };
+  }
+  return await run<SecretStatus>(cmdGetSecretStatus);
+}
+
+// 初始化密钥，未指定密码则生成保存在本机的密钥文件
+export async function initSecretKey(passphrase?: string) {
+  return await run<SecretStatus>(cmdInitSecretKey, {
+    passphrase,
+  });
+}
+
+export async function unlockSecret(passphrase?: string) {
+  return await run<SecretStatus>(cmdUnlockSecret, {
+    passphrase,
+  });
+}
+
+export async function lockSecret() {
+  await run(cmdLockSecret);
+}
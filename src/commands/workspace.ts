@@ -0,0 +1,89 @@
+import { isWebMode } from "../helpers/util";
+import { dropWorkspaceStore, setWorkspace } from "../stores/local";
+import {
+  cmdCloseWorkspace,
+  cmdCreateWorkspace,
+  cmdGetCurrentWorkspace,
+  cmdListWorkspace,
+  cmdOpenWorkspace,
+  cmdSwitchWorkspace,
+  run,
+} from "./invoke";
+
+// 默认工作区的ID
+export const defaultWorkspace = "default";
+
+export interface Workspace {
+  [key: string]: unknown;
+  id: string;
+  // 名称
+  name: string;
+  // 数据库文件路径
+  path: string;
+  // 最近打开时间
+  openedAt: string;
+}
+
+// 切换后记录当前工作区，请求响应等记录按工作区保存
+function setCurrentWorkspace(workspace: Workspace) {
+  setWorkspace(workspace.id === defaultWorkspace ? "" : workspace.id);
+  return workspace;
+}
+
+// 获取最近打开的工作区，默认工作区在首位
+export async function listWorkspace(): Promise<Workspace[]> {
+  if (isWebMode()) {
+    return [];
+  }
+  return await run<Workspace[]>(cmdListWorkspace);
+}
+
+export async function getCurrentWorkspace(): Promise<Workspace> {
+  if (isWebMode()) {
+    return {
+      id: defaultWorkspace,
+      name: "Default",
+      path: "",
+      openedAt: "",
+    };
+  }
+  const result = await run<Workspace>(cmdGetCurrentWorkspace);
+  return setCurrentWorkspace(result);
+}
+
+// 在指定路径（绝对路径）创建新的数据库并切换至该工作区
+export async function createWorkspace(
+  name: string,
+  path: string,
+): Promise<Workspace> {
+  const result = await run<Workspace>(cmdCreateWorkspace, {
+    name,
+    path,
+  });
+  return setCurrentWorkspace(result);
+}
+
+// 打开已存在的数据库文件（如网络盘中共享的数据库）
+export async function openWorkspace(path: string): Promise<Workspace> {
+  const result = await run<Workspace>(cmdOpenWorkspace, {
+    path,
+  });
+  return setCurrentWorkspace(result);
+}
+
+// 切换至最近打开的工作区，切换后需重新加载页面
+export async function switchWorkspace(id: string): Promise<Workspace> {
+  const result = await run<Workspace>(cmdSwitchWorkspace, {
+    id,
+  });
+  return setCurrentWorkspace(result);
+}
+
+// 关闭工作区（不删除数据库文件），返回关闭后的当前工作区
+export async function closeWorkspace(id: string): Promise<Workspace> {
+  const result = await run<Workspace>(cmdCloseWorkspace, {
+    id,
+  });
+  await dropWorkspaceStore(id);
+  return setCurrentWorkspace(result);
+}
@@ -42,6 +42,7 @@ import { useAPIFolderStore } from "../../stores/api_folder";
 import { useAPICollectionStore } from "../../stores/api_collection";
 import { HandleKey } from "../../constants/handle_key";
 import { newImportDialog } from "../ExDialog";
+import { listCollectionAPIExample } from "../../commands/api_example";
 
 const collapseWidth = 50;
 
@@ -133,6 +134,8 @@ export default defineComponent({
       apiFolderStore.apiFolders.forEach((folder) => arr.push(folder));
       apiSettingStore.apiSettings.forEach((apiSetting) => arr.push(apiSetting));
       try {
+        const examples = await listCollectionAPIExample(collection);
+        examples.forEach((example) => arr.push(example));
         let name = "unknown";
         const result = collectionStore.findByID(collection);
         if (result) {
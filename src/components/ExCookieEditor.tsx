@@ -62,13 +62,24 @@ export default defineComponent({
         });
         cookie.value = value;
         cookie.expires = expires;
+        // 以选择的过期时间为准
+        cookie.maxAge = null;
         // 新增
         if (!cookie.name) {
           cookie.name = name;
           cookie.path = path || "/";
           cookie.domain = domain || "";
+          await cookieStore.addOrUpdate(cookie);
+        } else {
+          await cookieStore.update(
+            {
+              name: props.cookie.name,
+              domain: props.cookie.domain,
+              path: props.cookie.path,
+            },
+            cookie,
+          );
         }
-        await cookieStore.addOrUpdate(cookie);
         if (props.onBack) {
           props.onBack();
         }
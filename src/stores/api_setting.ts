@@ -10,7 +10,6 @@ import {
 } from "../commands/api_setting";
 import { HTTPRequest } from "../commands/http_request";
 import { getAPISettingStore } from "./local";
-import { useEnvironmentStore, ENVRegexp } from "./environment";
 import { isWebMode, setAppTitle } from "../helpers/util";
 import { useGlobalReqHeaderStore } from "./global_req_header";
 import { cloneDeep } from "lodash-es";
@@ -58,14 +57,8 @@ export const useAPISettingStore = defineStore("apiSettings", {
       }
       return JSON.parse(setting.setting || "{}") as HTTPRequest;
     },
+    // 环境变量由后端按collection当前使用的环境替换
     fillValues(req: HTTPRequest) {
-      const arr = ENVRegexp.exec(req.uri);
-      if (arr?.length === 2) {
-        const envValue = useEnvironmentStore().getValue(arr[1]);
-        if (envValue) {
-          req.uri = req.uri.replace(arr[0], envValue);
-        }
-      }
       const variables = useGlobalReqHeaderStore().listEnable();
       if (variables) {
         if (!req.headers) {
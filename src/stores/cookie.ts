@@ -7,9 +7,13 @@ import {
   deleteCookie,
   clearCookie,
   addOrUpdate,
+  updateCookie,
+  CookieKey,
+  CookieScope,
+  newSharedCookieScope,
 } from "../commands/cookies";
 
-function isSameCookie(c1: Cookie, c2: Cookie) {
+function isSameCookie(c1: CookieKey, c2: CookieKey) {
   return c1.name === c2.name && c1.domain === c2.domain && c1.path === c2.path;
 }
 
@@ -17,19 +21,25 @@ export const useCookieStore = defineStore("cookie", {
   state: () => {
     return {
       cookies: [] as Cookie[],
+      scope: newSharedCookieScope(),
       fetching: false,
       removing: false,
       adding: false,
     };
   },
   actions: {
+    // 切换作用域后需重新获取
+    setScope(scope: CookieScope) {
+      this.scope = scope;
+      this.cookies = [];
+    },
     async fetch() {
       if (this.fetching) {
         return;
       }
       this.fetching = true;
       try {
-        const cookies = await listCookie();
+        const cookies = await listCookie(this.scope);
         this.cookies = sortBy(cookies, (c) => c.name);
       } finally {
         this.fetching = false;
@@ -41,7 +51,7 @@ export const useCookieStore = defineStore("cookie", {
       }
       this.removing = true;
       try {
-        await deleteCookie(c);
+        await deleteCookie(this.scope, c);
         const cookies = this.cookies.slice(0).filter((item) => {
           return !isSameCookie(item, c);
         });
@@ -56,7 +66,7 @@ export const useCookieStore = defineStore("cookie", {
       }
       this.removing = true;
       try {
-        await clearCookie();
+        await clearCookie(this.scope);
         this.cookies = [];
       } finally {
         this.removing = false;
@@ -68,7 +78,7 @@ export const useCookieStore = defineStore("cookie", {
       }
       this.adding = true;
       try {
-        await addOrUpdate(c);
+        await addOrUpdate(this.scope, c);
         const arr = this.cookies.slice(0);
         let found = -1;
         arr.forEach((item, index) => {
@@ -86,5 +96,19 @@ export const useCookieStore = defineStore("cookie", {
         this.adding = false;
       }
     },
+    // 更新cookie，original为更新前的name、domain与path
+    async update(original: CookieKey, c: Cookie) {
+      if (this.adding) {
+        return;
+      }
+      this.adding = true;
+      try {
+        await updateCookie(this.scope, original, c);
+      } finally {
+        this.adding = false;
+      }
+      // 创建与访问时间等由后端生成，因此重新获取
+      await this.fetch();
+    },
   },
 });
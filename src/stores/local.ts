@@ -2,15 +2,35 @@ import localforage from "localforage";
 
 const stores: Map<string, LocalForage> = new Map();
 
-function createNewStore(name: string) {
-  let store: LocalForage;
+// 当前工作区，默认工作区为空
+let currentWorkspace = "";
+// 按工作区区分的存储
+const workspaceStores: Set<string> = new Set();
+
+export function setWorkspace(workspace: string) {
+  currentWorkspace = workspace;
+}
+
+function getStoreName(name: string) {
+  if (!currentWorkspace || !workspaceStores.has(name)) {
+    return name;
+  }
+  return `${name}-${currentWorkspace}`;
+}
+
+function createNewStore(name: string, perWorkspace = false) {
+  if (perWorkspace) {
+    workspaceStores.add(name);
+  }
   return function () {
+    const storeName = getStoreName(name);
+    let store = stores.get(storeName);
     if (!store) {
       store = localforage.createInstance({
-        name,
+        name: storeName,
       });
+      stores.set(storeName, store);
     }
-    stores.set(name, store);
     return store;
   };
 }
@@ -44,8 +64,11 @@ export const getSettingStore = createNewStore(StoreKey.setting);
 // Pin的API配置
 export const getPinRequestStore = createNewStore(StoreKey.pinRequests);
 
-// 最新请求响应
-export const getLatestResponseStore = createNewStore(StoreKey.latestResponse);
+// 最新请求响应，各工作区分别保存
+export const getLatestResponseStore = createNewStore(
+  StoreKey.latestResponse,
+  true,
+);
 
 const langKey = "lang";
 
@@ -65,9 +88,20 @@ export async function setLang(lang: string) {
 }
 
 export async function clearStore(name: StoreKey) {
-  const s = stores.get(name);
+  const s = stores.get(getStoreName(name));
   if (!s) {
     return;
   }
   await s.clear();
 }
+
+// 删除工作区的存储
+export async function dropWorkspaceStore(workspace: string) {
+  for (const name of workspaceStores) {
+    const storeName = `${name}-${workspace}`;
+    stores.delete(storeName);
+    await localforage.dropInstance({
+      name: storeName,
+    });
+  }
+}
@@ -28,6 +28,11 @@ interface AppSetting {
     query: LocationQuery;
   };
   timeout: Timeout;
+  // 回收站数据保留天数
+  trashExpiredDays: number;
+  // API配置历史版本保留数量与天数
+  revisionMaxCount: number;
+  revisionExpiredDays: number;
 }
 
 export enum ResizeType {
@@ -64,6 +69,40 @@ export async function getAppLatestRoute() {
   return setting.latestRoute;
 }
 
+const defaultTrashExpiredDays = 30;
+
+export async function getTrashExpiredDays() {
+  const setting = await getAppSetting();
+  return setting.trashExpiredDays || defaultTrashExpiredDays;
+}
+
+export async function updateTrashExpiredDays(days: number) {
+  const setting = await getAppSetting();
+  setting.trashExpiredDays = days;
+  await updateAppSetting(setting);
+}
+
+const defaultRevisionMaxCount = 50;
+const defaultRevisionExpiredDays = 90;
+
+export async function getRevisionRetention() {
+  const setting = await getAppSetting();
+  return {
+    maxCount: setting.revisionMaxCount || defaultRevisionMaxCount,
+    expiredDays: setting.revisionExpiredDays || defaultRevisionExpiredDays,
+  };
+}
+
+export async function updateRevisionRetention(params: {
+  maxCount: number;
+  expiredDays: number;
+}) {
+  const setting = await getAppSetting();
+  setting.revisionMaxCount = params.maxCount;
+  setting.revisionExpiredDays = params.expiredDays;
+  await updateAppSetting(setting);
+}
+
 function isDarkTheme(theme: string) {
   return theme === "dark";
 }
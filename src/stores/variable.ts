@@ -5,10 +5,12 @@ import {
   createVariable,
   deleteVariable,
   listVariable,
+  revealVariable,
   updateVariable,
   Variable,
   VariableCategory,
   VariableStatus,
+  secretMask,
 } from "../commands/variable";
 
 export const useCustomizeStore = newVariableStore(
@@ -21,6 +23,8 @@ export function newVariableStore(name: string, category: string) {
     state: () => {
       return {
         variables: [] as Variable[],
+        // 用户查看的加密变量解密后的值
+        revealedValues: {} as Record<string, string>,
         fetching: false,
         adding: false,
         updating: false,
@@ -32,8 +36,27 @@ export function newVariableStore(name: string, category: string) {
         const value = this.variables.find((item) => {
           return item.enabled === VariableStatus.Enabled && item.name === name;
         });
+        if (value?.secret === VariableStatus.Enabled) {
+          return this.revealedValues[value.id];
+        }
         return value?.value;
       },
+      // 加密变量保存后以掩码展示，解密后的值仅在用户查看时获取
+      maskSecret(value: Variable) {
+        delete this.revealedValues[value.id];
+        if (value.secret === VariableStatus.Enabled && value.value) {
+          value.value = secretMask;
+        }
+      },
+      // 查看加密变量的值
+      async reveal(id: string) {
+        const value = await revealVariable(id);
+        this.revealedValues[id] = value;
+        return value;
+      },
+      conceal(id: string) {
+        delete this.revealedValues[id];
+      },
       async add(value: Variable) {
         if (this.adding) {
           return;
@@ -42,6 +65,7 @@ export function newVariableStore(name: string, category: string) {
         try {
           value.category = category;
           await createVariable(value);
+          this.maskSecret(value);
           this.variables.push(value);
         } finally {
           this.adding = false;
@@ -55,13 +79,17 @@ export function newVariableStore(name: string, category: string) {
         try {
           const result = await listVariable(collection, category);
           this.variables = sortBy(result, (item) => item.name);
+          this.revealedValues = {};
         } finally {
           this.fetching = false;
         }
       },
+      // 加密变量由后端解密后使用，不返回
       listEnable(): Variable[] {
         return this.variables.filter(
-          (item) => item.enabled === VariableStatus.Enabled,
+          (item) =>
+            item.enabled === VariableStatus.Enabled &&
+            item.secret !== VariableStatus.Enabled,
         );
       },
       async update(value: Variable) {
@@ -73,6 +101,7 @@ export function newVariableStore(name: string, category: string) {
           value.updatedAt = dayjs().format();
           value.category = category;
           await updateVariable(value);
+          this.maskSecret(value);
           const arr = this.variables.slice(0);
           let found = -1;
           arr.forEach((item, index) => {
@@ -96,6 +125,7 @@ export function newVariableStore(name: string, category: string) {
         try {
           await deleteVariable([id]);
           this.variables = this.variables.filter((item) => item.id !== id);
+          delete this.revealedValues[id];
         } finally {
           this.removing = false;
         }
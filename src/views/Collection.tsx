@@ -33,6 +33,7 @@ import {
 import APIResponse from "../components/APIResponse";
 import { usePinRequestStore } from "../stores/pin_request";
 import { useAPIFolderStore } from "../stores/api_folder";
+import { onFileStoreChanged } from "../commands/file_store";
 
 const contentClass = css`
   position: fixed;
@@ -203,9 +204,29 @@ export default defineComponent({
       response.value = resp;
     });
 
+    // collection目录被外部修改时重新加载
+    const offFileStoreListen = onFileStoreChanged(async (result) => {
+      if (result.collection !== collection) {
+        return;
+      }
+      if (result.message) {
+        showError(message, new Error(result.message));
+        return;
+      }
+      try {
+        await apiFolderStore.fetch(collection);
+        await apiSettingStore.fetch(collection);
+        await useEnvironmentStore().fetch(collection);
+        await useGlobalReqHeaderStore().fetch(collection);
+      } catch (err) {
+        showError(message, err);
+      }
+    });
+
     onBeforeUnmount(() => {
       stop();
       offListen();
+      offFileStoreListen.then((fn) => fn());
       usePinRequestStore().$reset();
       window.removeEventListener("resize", handleResize);
       // 清空选中id
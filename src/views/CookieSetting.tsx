@@ -12,6 +12,7 @@ import {
   NPopconfirm,
 } from "naive-ui";
 import { css } from "@linaria/core";
+import { useRoute } from "vue-router";
 
 import { useCookieStore } from "../stores/cookie";
 import { showError, getNormalDialogStyle } from "../helpers/util";
@@ -36,12 +37,18 @@ export default defineComponent({
   setup() {
     const message = useMessage();
     const dialog = useDialog();
+    const route = useRoute();
     const cookieStore = useCookieStore();
     const { cookies } = storeToRefs(cookieStore);
     const mode = ref(Mode.List);
     const updatedCookie = ref({} as Cookie);
     onBeforeMount(async () => {
       try {
+        // 在collection中打开时展示该collection的cookie
+        cookieStore.setScope({
+          collection: (route.query.collection as string) || "",
+          environment: "",
+        });
         await cookieStore.fetch();
       } catch (err) {
         showError(message, err);